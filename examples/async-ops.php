@@ -0,0 +1,34 @@
+<?php
+
+$extension = new Deno\Core\Extension();
+$extension->async_ops = [
+    'read_config' => fn( string $key ) => [ 'key' => $key, 'value' => 'hello' ],
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+$runtime->execute_script( 'index.js', '
+    (async () => {
+        const config = await Deno.core.ops.read_config( "greeting" );
+        Deno.core.print( config.value );
+    })();
+' );
+$runtime->run_event_loop();
+
+// Async ops can also be called with Deno's own `opAsync()` calling convention.
+$runtime->execute_script( 'index.js', '
+    globalThis.results = [];
+    (async () => {
+        const config = await Deno.core.opAsync( "read_config", "port" );
+        results.push( config.key );
+    })();
+' );
+$runtime->run_event_loop();
+assert( $runtime->get_global( 'results' ) === [ 'port' ] );
+
+// The same goes for ops registered after the runtime has been constructed.
+$runtime->register_op( 'op_double', fn( int $value ) => $value * 2 );
+$runtime->execute_script( 'index.js', 'Deno.core.opAsync( "op_double", 21 ).then( ( value ) => results.push( value ) );' );
+$runtime->run_event_loop();
+assert( $runtime->get_global( 'results' ) === [ 'port', 42 ] );
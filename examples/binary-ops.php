@@ -0,0 +1,54 @@
+<?php
+
+$extension = new Deno\Core\Extension();
+$extension->ops = [
+    'md5' => fn( string $bytes ) => md5( $bytes ),
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+$result = $runtime->execute_script( 'index.js', 'Deno.core.ops.md5( new Uint8Array( [ 0, 1, 2, 255 ] ) )' );
+
+assert( $result === md5( "\x00\x01\x02\xff" ) );
+
+// Wrap bytes in Deno\Core\Uint8Array to pass them to JavaScript without mangling non-UTF-8 bytes.
+$extension->ops['bytes'] = fn() => new Deno\Core\Uint8Array( "\x00\x01\x02\xff" );
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+$result = $runtime->execute_script( 'index.js', 'const bytes = Deno.core.ops.bytes(); bytes instanceof Uint8Array && bytes.join() === "0,1,2,255"' );
+
+assert( $result === 'true' );
+
+// Transfer large buffers to ops without copying them, with Deno.core.transfer().
+$extension = new Deno\Core\Extension();
+$extension->ops = [
+    'op_hash' => function ( Deno\Core\ArrayBuffer $buffer ) {
+        $context = hash_init( 'md5' );
+        for ( $offset = 0; $offset < $buffer->length(); $offset += 4096 ) {
+            hash_update( $context, $buffer->read( $offset, 4096 ) );
+        }
+        return hash_final( $context );
+    },
+    'op_echo' => fn( Deno\Core\ArrayBuffer $buffer ) => $buffer,
+];
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+$result = $runtime->execute_script( 'index.js', '
+    const bytes = new Uint8Array( 10000 ).fill( 120 );
+    const hash = Deno.core.ops.op_hash( Deno.core.transfer( bytes ) );
+    // The transferred buffer is detached.
+    `${ hash } ${ bytes.byteLength }`
+' );
+assert( $result === md5( str_repeat( 'x', 10000 ) ) . ' 0' );
+
+// Typed arrays transfer their part of the buffer, and returning the buffer transfers it back.
+$result = $runtime->execute_script( 'index.js', '
+    const view = new Uint8Array( [ 0, 1, 2, 3, 4 ] ).subarray( 1, 4 );
+    const echoed = Deno.core.ops.op_echo( Deno.core.transfer( view ) );
+    `${ echoed instanceof Uint8Array } ${ echoed.join() } ${ view.byteLength }`
+' );
+assert( $result === 'true 1,2,3 0' );
@@ -0,0 +1,11 @@
+<?php
+
+$module_loader = new Deno\Core\FsModuleLoader( __DIR__ . '/fs-modules' );
+$bundle = Deno\AST\bundle( 'file://' . __DIR__ . '/fs-modules/main.ts', $module_loader, new Deno\AST\EmitOptions );
+
+echo $bundle->text;
+
+// The bundle is a single module, which no longer needs the module loader.
+$runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions );
+$module_id = $runtime->load_main_module( 'file:///bundle.js', $bundle->text );
+$runtime->mod_evaluate( $module_id );
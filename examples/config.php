@@ -0,0 +1,30 @@
+<?php
+
+class ModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        return null;
+    }
+}
+
+// Structured parameters are available to scripts as `globalThis.__config`.
+$bootstrap_options = new Deno\Runtime\BootstrapOptions();
+$bootstrap_options->args = [ '--verbose' ];
+$bootstrap_options->config = [
+    'request' => [ 'method' => 'GET', 'path' => '/posts', 'query' => [ 'page' => 2 ] ],
+    'tags' => [ 'news', 'php' ],
+];
+$options = new Deno\Runtime\WorkerOptions( $bootstrap_options, [], new ModuleLoader() );
+$worker = new Deno\Runtime\MainWorker( 'index.js', new Deno\Runtime\PermissionsOptions(), $options );
+
+assert( $worker->execute_script( 'index.js', '`${ __config.request.method } ${ __config.request.path }?page=${ __config.request.query.page }`' ) === 'GET /posts?page=2' );
+assert( $worker->execute_script( 'index.js', '__config.tags.join( "," )' ) === 'news,php' );
+assert( $worker->execute_script( 'index.js', 'Deno.args.join( " " )' ) === '--verbose' );
+
+// Without a config, the global is not set.
+$options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [], new ModuleLoader() );
+$worker = new Deno\Runtime\MainWorker( 'index.js', new Deno\Runtime\PermissionsOptions(), $options );
+assert( $worker->execute_script( 'index.js', 'typeof globalThis.__config' ) === 'undefined' );
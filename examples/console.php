@@ -0,0 +1,21 @@
+<?php
+
+$messages = [];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->console = function ( string $level, array $args ) use ( &$messages ) {
+    $messages[] = $level . ': ' . implode( ' ', $args );
+};
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+$runtime->execute_script( 'index.js', 'console.log( "Hello", "World" ); console.warn( "Careful" );' );
+
+assert( $messages === [ 'log: Hello World', 'warn: Careful' ] );
+
+// A MainWorker keeps the rest of Deno's console.
+$messages = [];
+$worker_options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [], new Deno\Core\FsModuleLoader( __DIR__ ) );
+$worker_options->console = $runtime_options->console;
+$worker = new Deno\Runtime\MainWorker( 'index.js', new Deno\Runtime\PermissionsOptions(), $worker_options );
+assert( $worker->execute_script( 'index.js', 'typeof console.table' ) === 'function' );
+$worker->execute_script( 'index.js', 'console.info( "Ready" );' );
+assert( $messages === [ 'info: Ready' ] );
@@ -0,0 +1,22 @@
+<?php
+
+class ModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        return null;
+    }
+}
+
+$options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [], new ModuleLoader() );
+$options->cwd = __DIR__ . '/fs-modules';
+$permissions = new Deno\Runtime\PermissionsOptions();
+$permissions->allow_read = [];
+
+$php_cwd = getcwd();
+$worker = new Deno\Runtime\MainWorker( 'main.ts', $permissions, $options );
+assert( $worker->execute_script( 'cwd.js', 'Deno.cwd()' ) === realpath( __DIR__ . '/fs-modules' ) );
+assert( $worker->execute_script( 'cwd.js', 'JSON.parse( Deno.readTextFileSync( "config.json" ) ) !== null' ) === 'true' );
+assert( getcwd() === $php_cwd );
@@ -0,0 +1,52 @@
+<?php
+
+$runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions() );
+
+// Load the module once...
+$id = $runtime->load_main_module( 'file:///render.js', '
+    let renders = 0;
+    export default function render( name ) {
+        renders++;
+        return `Hello ${ name } (render ${ renders })`;
+    }
+' );
+$runtime->mod_evaluate( $id );
+$render = $runtime->get_default_export( $id );
+assert( $render instanceof Deno\Core\JsFunction );
+assert( $render->name === 'render' );
+
+// ...and call its default export many times, keeping the module's state.
+assert( $runtime->call_function( $render, [ 'Alice' ] ) === 'Hello Alice (render 1)' );
+assert( $runtime->call_function( $render, [ 'Bob' ] ) === 'Hello Bob (render 2)' );
+
+// Returned Promises are awaited. Functions exported by execute_module() can be called in the same way.
+$add = $runtime->execute_module( 'file:///async.js', 'export default async ( a, b ) => a + b;' );
+assert( $runtime->call_function( $add, [ 1, 2 ] ) === 3 );
+
+// Functions are converted back to the same function when passed to JavaScript.
+$runtime->set_global( 'render', $render );
+assert( $runtime->execute_script( 'index.js', 'render( "Carol" )' ) === 'Hello Carol (render 3)' );
+
+// Modules without a default export return null.
+$other_runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions() );
+$id = $other_runtime->load_main_module( 'file:///named.js', 'export const value = 1;' );
+$other_runtime->mod_evaluate( $id );
+assert( $other_runtime->get_default_export( $id ) === null );
+
+// Functions can only be called by the runtime that returned them.
+try {
+    $other_runtime->call_function( $render, [ 'Dave' ] );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( $e->getMessage() === 'The function belongs to a different runtime.' );
+}
+
+// Including after the runtime that returned them is freed, when a new runtime may reuse its memory.
+unset( $runtime );
+$new_runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions() );
+try {
+    $new_runtime->call_function( $render, [ 'Erin' ] );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( $e->getMessage() === 'The function belongs to a different runtime.' );
+}
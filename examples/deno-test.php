@@ -0,0 +1,41 @@
+<?php
+
+class ModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        return null;
+    }
+}
+
+$bootstrap_options = new Deno\Runtime\BootstrapOptions();
+$bootstrap_options->enable_testing_features = true;
+$options = new Deno\Runtime\WorkerOptions( $bootstrap_options, [], new ModuleLoader() );
+$worker = new Deno\Runtime\MainWorker( 'file:///test.js', new Deno\Runtime\PermissionsOptions(), $options );
+
+$worker->execute_module( 'file:///math_test.js', '
+    const add = ( a, b ) => a + b;
+
+    Deno.test( "adds numbers", () => {
+        if ( add( 1, 2 ) !== 3 ) throw new Error( "1 + 2 should be 3" );
+    } );
+    Deno.test( "adds strings", async ( t ) => {
+        await t.step( "concatenates", () => {
+            if ( add( "a", "b" ) !== "ab" ) throw new Error( "a + b should be ab" );
+        } );
+        await t.step( "fails", () => {
+            throw new Error( "Oops" );
+        } );
+    } );
+    Deno.test( { name: "not ready", ignore: true, fn: () => {} } );
+' );
+
+$results = $worker->run_tests();
+
+assert( count( $results ) === 3 );
+assert( $results[0]['name'] === 'adds numbers' && $results[0]['status'] === 'passed' && $results[0]['error'] === null );
+assert( $results[1]['status'] === 'failed' && str_contains( $results[1]['error'], 'Oops' ) );
+assert( is_float( $results[1]['duration_ms'] ) || is_int( $results[1]['duration_ms'] ) );
+assert( $results[2]['status'] === 'ignored' );
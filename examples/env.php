@@ -0,0 +1,42 @@
+<?php
+
+class ModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        return null;
+    }
+}
+
+$permissions = new Deno\Runtime\PermissionsOptions();
+$permissions->allow_env = [];
+
+// Deno.env uses PHP's environment.
+putenv( 'APP_KEY=secret' );
+$options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [], new ModuleLoader() );
+$options->env = true;
+$worker = new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
+assert( $worker->execute_script( 'env.js', 'Deno.env.get( "APP_KEY" )' ) === 'secret' );
+$worker->execute_script( 'env.js', 'Deno.env.set( "FROM_JS", "1" )' );
+assert( getenv( 'FROM_JS' ) === '1' );
+
+// Deno.env uses the given variables, without changing PHP's environment.
+$options->env = [ 'APP_KEY' => 'other' ];
+$worker = new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
+assert( $worker->execute_script( 'env.js', 'Deno.env.get( "APP_KEY" )' ) === 'other' );
+assert( $worker->execute_script( 'env.js', 'Deno.env.set( "APP_KEY", "changed" ); Deno.env.get( "APP_KEY" )' ) === 'changed' );
+assert( $worker->execute_script( 'env.js', 'String( Deno.env.has( "FROM_JS" ) )' ) === 'false' );
+assert( getenv( 'APP_KEY' ) === 'secret' );
+
+// Access still requires the allow_env permission.
+$permissions->allow_env = [ 'APP_KEY' ];
+$worker = new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
+assert( $worker->execute_script( 'env.js', 'Deno.env.get( "APP_KEY" )' ) === 'other' );
+try {
+    $worker->execute_script( 'env.js', 'Deno.env.get( "SECRET" )' );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'SECRET' ) );
+}
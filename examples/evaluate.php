@@ -0,0 +1,35 @@
+<?php
+
+// Use the runtime as an expression evaluator, getting the completion value as a PHP value.
+
+$runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions );
+
+assert( $runtime->evaluate( 'calc.js', '1 + 2' ) === 3 );
+assert( $runtime->evaluate( 'calc.js', '0.5 * 3' ) === 1.5 );
+assert( $runtime->evaluate( 'calc.js', '"a" + "b"' ) === 'ab' );
+$result = $runtime->evaluate( 'calc.js', '({ sum: [ 1, 2 ].reduce( ( a, b ) => a + b ) })' );
+assert( $result instanceof Deno\Core\V8Object );
+assert( $result->sum === 3 );
+assert( $runtime->evaluate( 'calc.js', 'const x = 1;' ) === null );
+
+// The completion value is the value of the last evaluated expression statement.
+assert( $runtime->evaluate( 'calc.js', 'let total = 0; for ( let i = 1; i <= 4; i++ ) { total += i; } total;' ) === 10 );
+assert( $runtime->evaluate( 'calc.js', 'if ( true ) { "yes" } else { "no" }' ) === 'yes' );
+
+// execute_script() converts the same value to a string.
+assert( $runtime->execute_script( 'calc.js', '1 + 2' ) === '3' );
+
+$options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [], new Deno\Core\FsModuleLoader( __DIR__ ) );
+$worker = new Deno\Runtime\MainWorker( 'index.js', new Deno\Runtime\PermissionsOptions(), $options );
+assert( $worker->evaluate( 'calc.js', '[ 1, 2, 3 ].map( n => n * 2 )' ) === [ 2, 4, 6 ] );
+
+// Both can be given a timeout, after which the script is terminated.
+foreach ( [ $runtime, $worker ] as $evaluator ) {
+    try {
+        $evaluator->evaluate( 'loop.js', 'while ( true ) {}', 50 );
+        assert( false );
+    } catch ( Exception $e ) {
+        assert( str_contains( $e->getMessage(), 'timed out' ) );
+    }
+    assert( $evaluator->evaluate( 'calc.js', '1 + 2', 50 ) === 3 );
+}
@@ -0,0 +1,49 @@
+<?php
+
+$greeter = Deno\Core\Extension::from_array( [
+    'name' => 'greeter',
+    'ops'  => [
+        'op_greet' => fn ( string $name ) => "Hello $name",
+    ],
+    'js'   => [
+        'greeter.js' => 'globalThis.greet = ( name ) => Deno.core.ops.op_greet( name );',
+    ],
+] );
+assert( $greeter->name === 'greeter' );
+assert( count( $greeter->js_files ) === 1 );
+
+$log = [];
+$logger = Deno\Core\Extension::from_array( [
+    'name'     => 'logger',
+    'void_ops' => [
+        'op_log' => function ( string $message ) use ( &$log ) {
+            $log[] = $message;
+        },
+    ],
+    'js'       => [
+        new Deno\Core\JsFile( 'logger.js', 'globalThis.log = ( message ) => Deno.core.ops.op_log( message );' ),
+    ],
+] );
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $greeter, $logger ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+assert( $runtime->execute_script( 'index.js', 'log( "greeting" ); greet( "World" )' ) === 'Hello World' );
+assert( $log === [ 'greeting' ] );
+
+// Unknown keys are rejected, so typos don't go unnoticed.
+try {
+    Deno\Core\Extension::from_array( [ 'opps' => [] ] );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'Unknown extension key "opps"' ) );
+}
+
+// Ops must be callables keyed by their name.
+try {
+    Deno\Core\Extension::from_array( [ 'ops' => [ 'not a callable' ] ] );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'callables keyed by op name' ) );
+}
@@ -0,0 +1,30 @@
+<?php
+
+function resident_memory() : int {
+    return (int) explode( ' ', file_get_contents( '/proc/self/statm' ) )[1] * 4096;
+}
+
+function create_runtime() : Deno\Core\JsRuntime {
+    $extension = new Deno\Core\Extension();
+    $extension->js_files = [
+        new Deno\Core\JsFile( 'extension.js', str_repeat( '// padding' . PHP_EOL, 1000000 ) . 'globalThis.hello = () => Deno.core.ops.hello();' ),
+    ];
+    $extension->ops = [
+        'hello' => fn() => 'Hello World',
+    ];
+    $runtime_options = new Deno\Core\RuntimeOptions();
+    $runtime_options->extensions = [ $extension ];
+    return new Deno\Core\JsRuntime( $runtime_options );
+}
+
+// Warm up, so the first construction's allocations aren't counted.
+create_runtime();
+$start = resident_memory();
+
+for ( $i = 0; $i < 100; $i++ ) {
+    create_runtime()->execute_script( 'index.js', 'hello()' );
+}
+
+// Constructing the same extension repeatedly should not grow memory by the size of its js files each time.
+// The js file is about 11 MB, so leaking it every time would grow memory by about 1.1 GB, far above the limit.
+assert( resident_memory() - $start < 100 * 1024 * 1024 );
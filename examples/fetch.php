@@ -0,0 +1,26 @@
+<?php
+
+class ModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        return null;
+    }
+}
+
+$boostrap_options = new Deno\Runtime\BootstrapOptions();
+$options = new Deno\Runtime\WorkerOptions( $boostrap_options , [], new ModuleLoader() );
+$options->fetch = function ( string $url, string $method, array $headers, ?string $body ) {
+    return [
+        'status' => 200,
+        'headers' => [ 'content-type' => 'text/plain' ],
+        'body' => "$method $url",
+    ];
+};
+$permissions = new Deno\Runtime\PermissionsOptions();
+
+$runtime = new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
+$runtime->execute_script( 'index.js', 'fetch( "http://example.com" ).then( r => r.text() ).then( r => Deno.core.print( r ) );' );
+$runtime->run_event_loop();
@@ -0,0 +1,31 @@
+<?php
+
+$options = new Deno\Core\RuntimeOptions;
+$options->module_loader = new Deno\Core\FsModuleLoader( __DIR__ . '/fs-modules', [ __DIR__ . '/fs-modules' ] );
+
+$runtime = new Deno\Core\JsRuntime( $options );
+$module_id = $runtime->load_main_module( 'file://' . __DIR__ . '/fs-modules/main.ts' );
+$runtime->mod_evaluate( $module_id );
+$runtime->run_event_loop();
+
+// Dynamic imports from scripts are resolved against the base directory.
+$runtime->execute_script( 'index.js', 'import( "./greet.ts" ).then( ( { greet } ) => Deno.core.print( greet( "again" ) + "\n" ) );' );
+$runtime->run_event_loop();
+
+// Without allow_read, only modules within the base directory can be loaded.
+$options->module_loader = new Deno\Core\FsModuleLoader( __DIR__ . '/fs-modules' );
+$runtime = new Deno\Core\JsRuntime( $options );
+try {
+    $runtime->load_main_module( 'file://' . __FILE__ );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'fs-module-loader.php' ) );
+}
+
+// A MainWorker's dynamic imports also require its allow_read permission.
+$permissions = new Deno\Runtime\PermissionsOptions();
+$worker_options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [], new Deno\Core\FsModuleLoader( __DIR__ . '/fs-modules' ) );
+$worker = new Deno\Runtime\MainWorker( __DIR__ . '/fs-modules/main.ts', $permissions, $worker_options );
+$worker->execute_script( 'index.js', 'import( "./greet.ts" ).catch( ( error ) => { globalThis.importError = error.message; } );' );
+$worker->run_event_loop();
+assert( str_contains( $worker->execute_script( 'index.js', 'globalThis.importError' ), 'read access' ) );
@@ -0,0 +1,4 @@
+import { greet } from "./greet.ts";
+import config from "./config.json" assert { type: "json" };
+
+Deno.core.print(greet(config.name) + "\n");
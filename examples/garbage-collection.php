@@ -0,0 +1,10 @@
+<?php
+
+$runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions() );
+
+// Allocate garbage, as a request would.
+$runtime->execute_script( 'request.js', 'let garbage = Array.from( { length: 100000 }, ( _, i ) => ( { i } ) ); garbage = null;' );
+$before = $runtime->get_heap_statistics()['used_heap_size'];
+
+$runtime->request_garbage_collection();
+assert( $runtime->get_heap_statistics()['used_heap_size'] < $before );
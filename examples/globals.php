@@ -0,0 +1,61 @@
+<?php
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+$runtime->set_global( 'request', [ 'path' => '/hello', 'query' => [ 'name' => 'World' ] ] );
+$runtime->execute_script( 'index.js', 'globalThis.greeting = `Hello ${ request.query.name } from ${ request.path }`;' );
+
+assert( $runtime->get_global( 'greeting' ) === 'Hello World from /hello' );
+assert( $runtime->get_global( 'not_set' ) === null );
+
+$runtime->execute_script( 'index.js', 'function add( a, b ) { return a + b; } async function greet( name ) { return `Hello ${ name }`; }' );
+
+assert( $runtime->call_global_function( 'add', [ 1, 2 ] ) === 3 );
+assert( $runtime->call_global_function( 'greet', [ 'World' ] ) === 'Hello World' );
+
+// Cyclic values are converted with null in place of the cycle, rather than overflowing the stack.
+$runtime->execute_script( 'cycle.js', 'globalThis.node = { name: "node" }; node.self = node;' );
+assert( $runtime->get_global( 'node' )->self === null );
+
+// Objects are converted to a Deno\Core\V8Object with their properties.
+$runtime->execute_script( 'object.js', 'globalThis.point = { x: 1, y: 2 };' );
+$point = $runtime->get_global( 'point' );
+assert( $point instanceof Deno\Core\V8Object );
+assert( $point->x === 1 && $point->y === 2 );
+
+$array = [ 'name' => 'array' ];
+$array['self'] = &$array;
+$runtime->set_global( 'array', $array );
+assert( $runtime->execute_script( 'cycle.js', 'array.name' ) === 'array' );
+
+// PHP ints arrive in JavaScript as integers, and ints too large for a JavaScript number as a BigInt.
+$runtime->set_global( 'small', 42 );
+$runtime->set_global( 'large', PHP_INT_MAX );
+assert( $runtime->execute_script( 'ints.js', 'typeof small === "number" && Number.isInteger( small )' ) === 'true' );
+assert( $runtime->execute_script( 'ints.js', 'large === 9223372036854775807n' ) === 'true' );
+assert( $runtime->get_global( 'large' ) === PHP_INT_MAX );
+
+// Arrays with mixed keys become objects with all of their keys, with integer keys ordered first.
+$runtime->set_global( 'mixed', [ 0 => 'a', 'x' => 'b', 1 => 'c' ] );
+assert( $runtime->execute_script( 'mixed.js', 'JSON.stringify( Object.entries( mixed ) )' ) === '[["0","a"],["1","c"],["x","b"]]' );
+
+// Arrays with integer keys that are not a list keep their keys.
+$runtime->set_global( 'sparse', [ 5 => 'a', 7 => 'b' ] );
+assert( $runtime->execute_script( 'sparse.js', 'Array.isArray( sparse ) === false && sparse[5] === "a" && sparse[7] === "b"' ) === 'true' );
+
+// Associative arrays become objects with a null prototype, which are converted back to arrays, so
+// nested associative arrays round trip with the same structure.
+$config = [
+    'name'    => 'app',
+    'db'      => [ 'host' => 'localhost', 'ports' => [ 5432, 5433 ] ],
+    'weights' => [ 5 => 0.5, 'default' => [ 'enabled' => true, 'tags' => [] ] ],
+];
+$runtime->set_global( 'config', $config );
+assert( $runtime->get_global( 'config' ) === $config );
+$runtime->execute_script( 'identity.js', 'function identity( value ) { return value; }' );
+assert( $runtime->call_global_function( 'identity', [ $config ] ) === $config );
+
+// Objects created in JavaScript are still converted to objects.
+$runtime->execute_script( 'object.js', 'globalThis.point = { x: 1, y: 2 }; globalThis.dict = Object.create( null ); dict.key = "value";' );
+assert( is_object( $runtime->get_global( 'point' ) ) );
+assert( $runtime->get_global( 'dict' ) === [ 'key' => 'value' ] );
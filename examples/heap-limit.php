@@ -0,0 +1,31 @@
+<?php
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->heap_limit_mb = 16;
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+$caused_exception = false;
+
+try {
+    $runtime->execute_script( 'index.js', 'const a = []; while ( true ) { a.push( new Array( 1000 ).fill( "x" ) ) }' );
+} catch ( Deno\Core\JsException $e ) {
+    echo $e->getMessage();
+    $caused_exception = true;
+}
+
+assert( $caused_exception === true );
+
+// Exceeding the heap limit leaves the runtime unhealthy, so a pool knows to replace it.
+assert( $runtime->is_healthy() === false );
+assert( $runtime->is_terminating() === false );
+$runtime->reset();
+assert( $runtime->is_healthy() === true );
+
+// A function that is terminated while it is called throws instead of crashing the process.
+$runtime->execute_script( 'fill.js', 'function fill() { const a = []; while ( true ) { a.push( new Array( 1000 ).fill( "x" ) ) } }' );
+try {
+    $runtime->call_global_function( 'fill', [] );
+    assert( false );
+} catch ( Deno\Core\JsException $e ) {
+    assert( $e->getMessage() === 'Heap limit exceeded.' );
+}
+assert( $runtime->is_healthy() === false );
@@ -0,0 +1,25 @@
+<?php
+
+// Returns the smallest non-zero difference between successive performance.now() calls.
+$resolution = <<<END
+let resolution = Infinity;
+let last = performance.now();
+const end = last + 50;
+while ( last < end ) {
+    const now = performance.now();
+    if ( now > last ) {
+        resolution = Math.min( resolution, now - last );
+    }
+    last = now;
+}
+resolution
+END;
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+assert( (float) $runtime->execute_script( 'resolution.js', $resolution ) >= 2 );
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->allow_hrtime = true;
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+assert( (float) $runtime->execute_script( 'resolution.js', $resolution ) < 2 );
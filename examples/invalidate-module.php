@@ -0,0 +1,43 @@
+<?php
+
+// A module loader serving modules from memory, standing in for files a development server watches.
+class MemoryModuleLoader implements Deno\Core\ModuleLoader {
+    public $modules = [];
+    public $loaded = [];
+
+    function resolve( string $specifier, string $referrer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        $this->loaded[] = $specifier;
+        return new Deno\Core\ModuleSource( $this->modules[ $specifier ], 'application/javascript', $specifier, $specifier );
+    }
+}
+
+$loader = new MemoryModuleLoader;
+$loader->modules['file:///app/handler.js'] = 'export default () => "v1";';
+
+$options = new Deno\Core\RuntimeOptions;
+$options->module_loader = $loader;
+$runtime = new Deno\Core\JsRuntime( $options );
+
+$handle = 'const { default: handler } = await import( "file:///app/handler.js" ); export default handler();';
+assert( $runtime->execute_module( 'file:///app/request-1.js', $handle ) === 'v1' );
+
+// Imports of a loaded module use the module map, even after its source changed.
+$loader->modules['file:///app/handler.js'] = 'export default () => "v2";';
+assert( $runtime->execute_module( 'file:///app/request-2.js', $handle ) === 'v1' );
+assert( $loader->loaded === [ 'file:///app/handler.js' ] );
+
+// Once invalidated, the next import calls the module loader again.
+$runtime->invalidate_module( 'file:///app/handler.js' );
+assert( $runtime->execute_module( 'file:///app/request-3.js', $handle ) === 'v2' );
+assert( $loader->loaded === [ 'file:///app/handler.js', 'file:///app/handler.js' ] );
+assert( $runtime->execute_module( 'file:///app/request-4.js', $handle ) === 'v2' );
+assert( count( $loader->loaded ) === 2 );
+
+// The reloaded module is registered with a fragment, as deno_core can't remove modules.
+$loader->modules['file:///app/handler.js'] = 'export default () => import.meta.url;';
+$runtime->invalidate_module( 'file:///app/handler.js' );
+assert( $runtime->execute_module( 'file:///app/request-5.js', $handle ) === 'file:///app/handler.js#reload=2' );
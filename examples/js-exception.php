@@ -8,3 +8,101 @@ try {
     echo $e->getMessage();
     var_dump( $e );
 };
+
+try {
+    $runtime->execute_script( 'index.js', 'function willThrow() { throw new Error( "Oops" ); } willThrow();' );
+} catch ( Deno\Core\JsException $e ) {
+    assert( $e->frames[0]->function_name === 'willThrow' );
+    assert( $e->frames[0]->file === 'index.js' );
+}
+
+try {
+    $runtime->execute_script( 'index.js', 'const error = new Error( "Missing" ); error.name = "NotFound"; throw error;' );
+} catch ( Deno\Core\JsException $e ) {
+    assert( $e->getCode() === Deno\Core\JsException::NOT_FOUND );
+}
+
+class ModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        return null;
+    }
+}
+
+$options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [], new ModuleLoader() );
+$permissions = new Deno\Runtime\PermissionsOptions();
+$permissions->allow_read = [];
+$worker = new Deno\Runtime\MainWorker( 'main.js', $permissions, $options );
+try {
+    $worker->execute_script( 'main.js', 'Deno.readTextFileSync( "/does/not/exist" );' );
+} catch ( Deno\Errors\NotFound $e ) {
+    // System errors carry the OS errno, ENOENT.
+    assert( $e->getCode() === 2 );
+}
+
+// Stack frames of transpiled TypeScript are mapped back to the original source with its inline source map.
+$parse_params = new Deno\AST\ParseParams;
+$parse_params->specifier = 'file:///app/main.ts';
+$parse_params->media_type = Deno\AST\MediaType::TYPESCRIPT;
+$parse_params->text_info = "interface Options {\n  fail: boolean;\n}\n\nfunction willThrow( options: Options ): void {\n  throw new Error( 'Oops' );\n}\nwillThrow( { fail: true } );";
+$transpiled = Deno\AST\parse_module( $parse_params )->transpile( new Deno\AST\EmitOptions );
+try {
+    $runtime->execute_script( 'file:///app/main.ts', $transpiled->text );
+} catch ( Deno\Core\JsException $e ) {
+    assert( $e->frames[0]->file === 'file:///app/main.ts' );
+    assert( $e->frames[0]->line === 6 );
+}
+
+// Errors can be formatted to match the application's own format.
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->format_js_error = function ( Deno\Core\JsException $e ) {
+    return sprintf( '[js] %s at %s:%d', $e->getMessage(), $e->frames[0]->file, $e->frames[0]->line );
+};
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+try {
+    $runtime->execute_script( 'file:///app.js', 'throw new Error( "Oops" );' );
+} catch ( Deno\Core\JsException $e ) {
+    assert( $e->getMessage() === '[js] Oops at file:///app.js:1' );
+}
+
+// A failing formatter doesn't hide the error.
+$failing_options = new Deno\Core\RuntimeOptions();
+$failing_options->format_js_error = function ( Deno\Core\JsException $e ) {
+    throw new Exception( 'Formatter failed' );
+};
+$failing_runtime = new Deno\Core\JsRuntime( $failing_options );
+try {
+    $failing_runtime->execute_script( 'file:///app.js', 'throw new Error( "Oops" );' );
+    assert( false );
+} catch ( Deno\Core\JsException $e ) {
+    assert( str_contains( $e->getMessage(), 'Oops' ) );
+}
+
+// The error's cause becomes the previous exception, so the whole chain is available.
+try {
+    $runtime->execute_script( 'cause.js', '
+        try {
+            null.property;
+        } catch ( error ) {
+            throw new Error( "Unable to load config", { cause: new Error( "Unable to parse", { cause: error } ) } );
+        }
+    ' );
+} catch ( Deno\Core\JsException $e ) {
+    assert( str_contains( $e->getMessage(), 'Unable to load config' ) );
+    assert( $e->getPrevious() instanceof Deno\Core\JsException );
+    assert( $e->getPrevious()->getMessage() === 'Unable to parse' );
+    assert( $e->getPrevious()->getPrevious() instanceof Deno\Errors\TypeError );
+    assert( $e->getPrevious()->getPrevious()->getPrevious() === null );
+}
+
+// The errors of an AggregateError are available as `errors`.
+try {
+    $runtime->execute_script( 'aggregate.js', 'throw new AggregateError( [ new Error( "First" ), new RangeError( "Second" ) ], "All failed" );' );
+} catch ( Deno\Core\JsException $e ) {
+    assert( count( $e->errors ) === 2 );
+    assert( $e->errors[0]->getMessage() === 'First' );
+    assert( $e->errors[1]->getMessage() === 'Second' );
+}
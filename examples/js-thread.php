@@ -0,0 +1,48 @@
+<?php
+
+// Run a module on its own thread, and poll for it to finish while PHP does other work.
+$thread = new Deno\Core\JsThread( 'file:///app/sum.js', '
+    let total = 0;
+    for ( let i = 0; i < 1000000; i++ ) {
+        total += i;
+    }
+    export default { total, values: [ 1, "two", null ] };
+' );
+while ( ! $thread->is_finished() ) {
+    usleep( 1000 );
+}
+$result = $thread->join();
+assert( $result->total === 499999500000 );
+assert( $result->values === [ 1, 'two', null ] );
+
+// Bound the time spent in JavaScript.
+$thread = new Deno\Core\JsThread( 'file:///app/loop.js', 'while ( true ) {}' );
+assert( $thread->wait( 100 ) === false );
+$thread->terminate();
+try {
+    $thread->join();
+    assert( false );
+} catch ( Deno\Core\JsException $e ) {
+    assert( $e->getMessage() === 'Execution of the JsThread was terminated.' );
+}
+
+// Dropping an unfinished thread terminates it, rather than leaving it running.
+$thread = new Deno\Core\JsThread( 'file:///app/loop.js', 'while ( true ) {}' );
+unset( $thread );
+
+// Errors thrown by the module are thrown by join().
+$thread = new Deno\Core\JsThread( 'file:///app/error.js', 'throw new Error( "Oops" );' );
+try {
+    $thread->join();
+    assert( false );
+} catch ( Deno\Core\JsException $e ) {
+    assert( str_contains( $e->getMessage(), 'Oops' ) );
+}
+
+// Modules can be imported with a FsModuleLoader.
+$thread = new Deno\Core\JsThread(
+    'file://' . __DIR__ . '/fs-modules/thread.js',
+    'import { greet } from "./greet.ts"; export default greet( "thread" );',
+    new Deno\Core\FsModuleLoader( __DIR__ . '/fs-modules' )
+);
+assert( $thread->join() === "Hello thread" );
@@ -0,0 +1,44 @@
+<?php
+
+class ModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        if ( str_ends_with( $specifier, '.json' ) ) {
+            return new Deno\Core\ModuleSource( '{ "debug": true }', 'application/json', $specifier, $specifier );
+        }
+        return new Deno\Core\ModuleSource( 'export default "js";', 'application/javascript', $specifier, $specifier );
+    }
+}
+
+$options = new Deno\Core\RuntimeOptions;
+$options->module_loader = new ModuleLoader;
+$runtime = new Deno\Core\JsRuntime( $options );
+
+// JSON modules are imported with a JSON import assertion.
+assert( $runtime->execute_module( 'file:///config.js', 'import config from "file:///config.json" assert { type: "json" }; export default config.debug;' ) === true );
+
+// As in Deno, the assertion must match the type of the module.
+try {
+    $runtime->execute_module( 'file:///missing-assertion.js', 'import config from "file:///other.json"; export default config;' );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'Expected a "JavaScript" module but loaded a "JSON" module.' ) );
+}
+
+try {
+    $runtime->execute_module( 'file:///wrong-assertion.js', 'import value from "file:///value.js" assert { type: "json" }; export default value;' );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'Expected a "JSON" module but loaded a "JavaScript" module.' ) );
+}
+
+// Unsupported assertion types are rejected.
+try {
+    $runtime->execute_module( 'file:///unknown-assertion.js', 'import value from "file:///value.css" assert { type: "css" }; export default value;' );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'type' ) );
+}
@@ -0,0 +1,34 @@
+<?php
+
+// JavaScript strings may contain lone surrogates, which are replaced with U+FFFD by default.
+
+$runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions );
+assert( $runtime->evaluate( 'index.js', '"a\uD800b"' ) === "a\u{FFFD}b" );
+
+// With lossless_strings, they are converted to a Deno\Core\Utf16String holding the exact code units.
+
+$options = new Deno\Core\RuntimeOptions;
+$options->lossless_strings = true;
+$runtime = new Deno\Core\JsRuntime( $options );
+
+$string = $runtime->evaluate( 'index.js', 'globalThis.broken = "a\uD800b"; broken' );
+assert( $string instanceof Deno\Core\Utf16String );
+assert( $string->length() === 3 );
+assert( $string->to_utf16le() === "a\x00\x00\xD8b\x00" );
+assert( $string->to_utf8( true ) === "a\u{FFFD}b" );
+try {
+    $string->to_utf8();
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'lone surrogates' ) );
+}
+
+// Passing it back to JavaScript gives the identical string.
+$runtime->set_global( 'round_trip', $string );
+assert( $runtime->evaluate( 'index.js', 'round_trip === broken' ) === true );
+
+// Valid strings, including ones outside of the Basic Multilingual Plane, are still PHP strings.
+assert( $runtime->evaluate( 'index.js', '"café 😀"' ) === "café 😀" );
+
+$emoji = new Deno\Core\Utf16String( mb_convert_encoding( '😀', 'UTF-16LE', 'UTF-8' ) );
+assert( $emoji->to_utf8() === '😀' );
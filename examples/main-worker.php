@@ -27,4 +27,5 @@ $permissions = new Deno\Runtime\PermissionsOptions();
 
 $runtime = new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
 
-$module = $runtime->execute_main_module();
+// Executes the main module and then runs the event loop until all async actions complete.
+$runtime->run();
@@ -0,0 +1,37 @@
+<?php
+
+$runtime = null;
+$deepest = 0;
+
+// An op that calls back into JavaScript, which calls the op again.
+$extension = new Deno\Core\Extension();
+$extension->ops = [
+    'op_descend' => function ( int $depth ) use ( &$runtime, &$deepest ) {
+        $deepest = max( $deepest, $depth );
+        return $runtime->call_global_function( 'descend', [ $depth + 1 ] );
+    },
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime_options->max_call_depth = 10;
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+$runtime->execute_script( 'index.js', 'function descend( depth ) { return Deno.core.ops.op_descend( depth ); }' );
+
+// Without a limit, this would recurse until the stack overflows.
+try {
+    $runtime->call_global_function( 'descend', [ 1 ] );
+    assert( false );
+} catch ( Deno\Core\JsException $e ) {
+    assert( str_contains( $e->getMessage(), 'Maximum call depth of 10 exceeded.' ) );
+}
+// The first call and each op call and call back into JavaScript is one level.
+assert( $deepest === 5 );
+
+// The depth is counted down again as calls return, so the runtime can still be used.
+$runtime->execute_script( 'index.js', 'function shallow() { return "ok"; }' );
+assert( $runtime->call_global_function( 'shallow', [] ) === 'ok' );
+
+// JavaScript can catch the error.
+$runtime->execute_script( 'index.js', 'function guarded( depth ) { try { return Deno.core.ops.op_descend( depth ); } catch ( e ) { return e.message; } }' );
+assert( str_contains( $runtime->call_global_function( 'guarded', [ 1 ] ), 'Maximum call depth of 10 exceeded.' ) );
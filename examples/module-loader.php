@@ -22,3 +22,157 @@ $options->module_loader = new ModuleLoader;
 $runtime = new Deno\Core\JsRuntime( $options );
 $module_id = $runtime->load_main_module( 'file:///index.js', 'import foo from "file:///bar.js"; Deno.core.print(foo);' );
 $runtime->mod_evaluate( $module_id );
+
+// data: URL modules are loaded without calling the module loader.
+$runtime = new Deno\Core\JsRuntime( $options );
+$module_id = $runtime->load_main_module( 'file:///data.js', 'import foo from "data:application/javascript,export%20default%20%22HI%22"; import bar from "data:application/javascript;base64,ZXhwb3J0IGRlZmF1bHQgIkJZRSI="; Deno.core.print(foo + bar);' );
+$runtime->mod_evaluate( $module_id );
+
+// execute_module() runs a snippet as a module, with imports and top level await, and returns its default export.
+$runtime = new Deno\Core\JsRuntime( $options );
+$value = $runtime->execute_module( 'file:///snippet.js', 'import foo from "file:///bar.js"; const later = await Promise.resolve( "!" ); export default foo + later;' );
+assert( $value === 'HI!' );
+assert( $runtime->execute_module( 'file:///no-default.js', 'export const a = 1;' ) === null );
+
+// Module loaders can provide source maps for the modules they transpile.
+class TypeScriptModuleLoader implements Deno\Core\ModuleLoader {
+    private $source_maps = [];
+
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        $parse_params = new Deno\AST\ParseParams;
+        $parse_params->specifier = $specifier;
+        $parse_params->media_type = Deno\AST\MediaType::TYPESCRIPT;
+        $parse_params->text_info = "type Reason = string;\n\nexport function fail( reason: Reason ): never {\n  throw new Error( reason );\n}";
+        $emit_options = new Deno\AST\EmitOptions;
+        $emit_options->inline_source_map = false;
+        $emit_options->source_map = true;
+        $transpiled = Deno\AST\parse_module( $parse_params )->transpile( $emit_options );
+        $this->source_maps[ $specifier ] = $transpiled->source_map;
+        return new Deno\Core\ModuleSource( $transpiled->text, 'application/javascript', $specifier, $specifier );
+    }
+
+    function get_source_map( string $specifier ) : ?string {
+        return $this->source_maps[ $specifier ] ?? null;
+    }
+}
+
+$options = new Deno\Core\RuntimeOptions;
+$options->module_loader = new TypeScriptModuleLoader;
+$runtime = new Deno\Core\JsRuntime( $options );
+try {
+    $runtime->execute_module( 'file:///main.js', 'import { fail } from "file:///fail.ts"; fail( "Oops" );' );
+} catch ( Deno\Core\JsException $e ) {
+    assert( $e->frames[0]->file === 'file:///fail.ts' );
+    assert( $e->frames[0]->line === 4 );
+}
+
+// Errors loading a module say which module failed and how it was imported.
+class ChainModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        $modules = [
+            'file:///a.js' => 'import "file:///b.js";',
+            'file:///b.js' => 'import "file:///missing.js";',
+        ];
+        if ( ! isset( $modules[ $specifier ] ) ) {
+            return null;
+        }
+        return new Deno\Core\ModuleSource( $modules[ $specifier ], 'application/javascript', $specifier, $specifier );
+    }
+}
+
+$options = new Deno\Core\RuntimeOptions;
+$options->module_loader = new ChainModuleLoader;
+$runtime = new Deno\Core\JsRuntime( $options );
+try {
+    $runtime->load_main_module( 'file:///main.js', 'import "file:///a.js";' );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'Unable to load module file:///missing.js, imported from file:///b.js, imported from file:///a.js, imported from file:///main.js' ) );
+}
+
+// Binary module code is given as a Deno\Core\Uint8Array and passed through unchanged, while strings must be valid UTF-8.
+class BinaryModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        if ( $specifier === 'file:///data.json' ) {
+            return new Deno\Core\ModuleSource( new Deno\Core\Uint8Array( '{"name":"caf' . "\xc3\xa9" . '"}' ), 'json', $specifier, $specifier );
+        }
+        return new Deno\Core\ModuleSource( "export default '\xff';", 'application/javascript', $specifier, $specifier );
+    }
+}
+
+$options = new Deno\Core\RuntimeOptions;
+$options->module_loader = new BinaryModuleLoader;
+$runtime = new Deno\Core\JsRuntime( $options );
+assert( $runtime->execute_module( 'file:///json.js', 'import data from "file:///data.json" assert { type: "json" }; export default data.name;' ) === 'café' );
+try {
+    $runtime->execute_module( 'file:///invalid.js', 'import "file:///invalid-utf8.js";' );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'is not valid UTF-8' ) );
+}
+
+// A module that was redirected is registered under its final URL, which its relative imports are resolved against.
+class RedirectModuleLoader implements Deno\Core\ModuleLoader {
+    public $referrers = [];
+
+    function resolve( string $specifier, string $referer ) : string {
+        if ( str_starts_with( $specifier, './' ) ) {
+            $this->referrers[ $specifier ] = $referer;
+            return substr( $referer, 0, strrpos( $referer, '/' ) ) . substr( $specifier, 1 );
+        }
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        $modules = [
+            'https://example.com/v2/mod.js' => 'import dep from "./dep.js"; export default import.meta.url + " " + dep;',
+            'https://example.com/v2/dep.js' => 'export default "dep";',
+        ];
+        // The latest version is redirected to v2, the same as an HTTP 301 redirect.
+        $found = str_replace( '/latest/', '/v2/', $specifier );
+        return new Deno\Core\ModuleSource( $modules[ $found ], 'application/javascript', $specifier, $found );
+    }
+}
+
+$options = new Deno\Core\RuntimeOptions;
+$options->module_loader = new RedirectModuleLoader;
+$runtime = new Deno\Core\JsRuntime( $options );
+assert( $runtime->execute_module( 'file:///redirect.js', 'import mod from "https://example.com/latest/mod.js"; export default mod;' ) === 'https://example.com/v2/mod.js dep' );
+assert( $options->module_loader->referrers['./dep.js'] === 'https://example.com/v2/mod.js' );
+
+// Loaders can refuse modules by throwing, which rejects a dynamic import() with the exception's message.
+class PolicyModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier, bool $is_dyn_import = false ) : ?Deno\Core\ModuleSource {
+        if ( $is_dyn_import && ! str_starts_with( $specifier, 'file:///' ) ) {
+            throw new Exception( "Dynamic import of $specifier is not allowed." );
+        }
+        return new Deno\Core\ModuleSource( 'export default "allowed";', 'application/javascript', $specifier, $specifier );
+    }
+}
+
+$options = new Deno\Core\RuntimeOptions;
+$options->module_loader = new PolicyModuleLoader;
+$runtime = new Deno\Core\JsRuntime( $options );
+$value = $runtime->execute_module( 'file:///policy.js', '
+    const allowed = ( await import( "file:///allowed.js" ) ).default;
+    const refused = await import( "https://example.com/remote.js" ).catch( ( error ) => error.message );
+    export default [ allowed, refused ];
+' );
+assert( $value[0] === 'allowed' );
+assert( str_contains( $value[1], 'Dynamic import of https://example.com/remote.js is not allowed.' ) );
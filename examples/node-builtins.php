@@ -0,0 +1,51 @@
+<?php
+
+$options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [], new Deno\Core\FsModuleLoader( __DIR__ ) );
+$options->node_builtins = true;
+$worker = new Deno\Runtime\MainWorker( __DIR__ . '/index.js', new Deno\Runtime\PermissionsOptions(), $options );
+
+$result = $worker->execute_module( 'file:///app/node.js', '
+    import { Buffer } from "node:buffer";
+    import { EventEmitter, once } from "node:events";
+    import path from "node:path";
+
+    const emitter = new EventEmitter();
+    const received = [];
+    emitter.on( "data", ( chunk ) => received.push( chunk ) );
+    emitter.emit( "data", "a" );
+    emitter.emit( "data", "b" );
+    const ready = once( emitter, "ready" );
+    emitter.emit( "ready", 42 );
+
+    export default {
+        base64: Buffer.from( "Hello" ).toString( "base64" ),
+        hex: Buffer.from( "68656c6c6f", "hex" ).toString(),
+        received,
+        ready: ( await ready )[ 0 ],
+        joined: path.join( "/app", "src", "..", "index.js" ),
+        extname: path.extname( "index.test.js" ),
+    };
+' );
+
+assert( $result->base64 === 'SGVsbG8=' );
+assert( $result->hex === 'hello' );
+assert( $result->received === [ 'a', 'b' ] );
+assert( $result->ready === 42 );
+assert( $result->joined === '/app/index.js' );
+assert( $result->extname === '.js' );
+
+// Other node: specifiers can not be imported.
+try {
+    $worker->execute_module( 'file:///app/fs.js', 'import fs from "node:fs";' );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'only the Node built-in modules node:buffer, node:events, node:path are supported' ) );
+}
+
+// Large buffers can be converted to strings too.
+$length = $worker->execute_module( 'file:///app/large.js', '
+    import { Buffer } from "node:buffer";
+    const buffer = Buffer.alloc( 1024 * 1024, 97 );
+    export default Buffer.from( buffer.toString( "base64" ), "base64" ).toString( "latin1" ).length;
+' );
+assert( $length === 1024 * 1024 );
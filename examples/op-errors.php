@@ -0,0 +1,68 @@
+<?php
+
+$extension = new Deno\Core\Extension();
+$extension->name = 'errors';
+$extension->ops = [
+    'op_concat' => fn( string $a, string $b ) => $a . $b,
+    'op_fail' => function () {
+        throw new RuntimeException( 'Database is not available.' );
+    },
+];
+$extension->async_ops = [
+    'op_fail_async' => function () {
+        throw new RuntimeException( 'Database is not available.' );
+    },
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+// Errors calling a PHP function, such as too few arguments, are thrown in JavaScript.
+assert( str_contains( $runtime->execute_script( 'arity.js', 'try { Deno.core.ops.op_concat( "a" ); "no error" } catch ( error ) { error.message }' ), 'Too few arguments' ) );
+
+// Exceptions thrown by a PHP function are thrown in JavaScript with their message.
+assert( $runtime->execute_script( 'throw.js', 'try { Deno.core.ops.op_fail() } catch ( error ) { error.message }' ) === 'Database is not available.' );
+
+// Exceptions thrown by async ops reject their Promise.
+$runtime->execute_script( 'async.js', 'Deno.core.ops.op_fail_async().catch( ( error ) => { globalThis.message = error.message; } );' );
+$runtime->run_event_loop();
+assert( $runtime->get_global( 'message' ) === 'Database is not available.' );
+
+// Uncaught errors from ops become a JsException in PHP.
+try {
+    $runtime->execute_script( 'uncaught.js', 'Deno.core.ops.op_fail();' );
+    assert( false );
+} catch ( Deno\Core\JsException $e ) {
+    assert( str_contains( $e->getMessage(), 'Database is not available.' ) );
+}
+
+// get_error_class maps exceptions thrown by ops to JavaScript error classes.
+class RecordNotFound extends Exception {}
+
+$extension = new Deno\Core\Extension();
+$extension->name = 'records';
+$extension->ops = [
+    'op_find' => function ( int $id ) {
+        throw new RecordNotFound( "Record $id does not exist." );
+    },
+    'op_check' => function ( $value ) {
+        throw new InvalidArgumentException( 'The value is out of range.' );
+    },
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime_options->get_error_class = fn( Throwable $exception ) => match ( true ) {
+    $exception instanceof RecordNotFound => 'NotFound',
+    $exception instanceof InvalidArgumentException => 'RangeError',
+    default => null,
+};
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+assert( $runtime->execute_script( 'range.js', 'try { Deno.core.ops.op_check( 1 ) } catch ( error ) { `${ error instanceof RangeError } ${ error.message }` }' ) === 'true The value is out of range.' );
+// Without Deno.errors, other class names become an Error with the class name as its name.
+assert( $runtime->execute_script( 'name.js', 'try { Deno.core.ops.op_find( 1 ) } catch ( error ) { `${ error.name } ${ error.message }` }' ) === 'NotFound Record 1 does not exist.' );
+$runtime->execute_script( 'async.js', '(async () => { try { await Deno.core.opAsync( "op_check", 1 ) } catch ( error ) { globalThis.async_error = error.constructor.name } })();' );
+$runtime->run_event_loop();
+assert( $runtime->get_global( 'async_error' ) === 'RangeError' );
@@ -0,0 +1,20 @@
+<?php
+
+$extension = new Deno\Core\Extension();
+$extension->ops = [
+    'render' => function( string $template ) {
+        usleep( 1000 );
+        return strtoupper( $template );
+    },
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime_options->op_metrics = true;
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+$runtime->execute_script( 'index.js', 'for ( let i = 0; i < 3; i++ ) Deno.core.ops.render( "hello" );' );
+
+$metrics = $runtime->get_op_metrics();
+assert( $metrics['render']['calls'] === 3 );
+assert( $metrics['render']['total_time_ms'] >= 3 );
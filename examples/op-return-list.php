@@ -0,0 +1,25 @@
+<?php
+
+$extension = new Deno\Core\Extension();
+$extension->name = 'reader';
+$extension->ops = [
+    // A list becomes a JavaScript array, so several values can be returned and destructured.
+    'op_read' => fn( string $data ) => [ strlen( $data ), strtoupper( $data ) ],
+    // Arrays with string keys, or integer keys that are not 0, 1, 2..., become objects.
+    'op_filter' => fn() => array_filter( [ 1, 2, 3, 4 ], fn( $n ) => $n % 2 === 0 ),
+];
+$extension->async_ops = [
+    'op_read_async' => fn( string $data ) => [ true, $data ],
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+assert( $runtime->execute_script( 'list.js', 'const [ bytesRead, data ] = Deno.core.ops.op_read( "hello" ); bytesRead === 5 && data === "HELLO"' ) === 'true' );
+assert( $runtime->execute_script( 'empty.js', 'Array.isArray( Deno.core.ops.op_read( "" ) )' ) === 'true' );
+assert( $runtime->execute_script( 'object.js', 'const filtered = Deno.core.ops.op_filter(); !Array.isArray( filtered ) && filtered[1] === 2 && filtered[3] === 4' ) === 'true' );
+
+$runtime->execute_script( 'async.js', 'Deno.core.ops.op_read_async( "hello" ).then( ( [ ok, data ] ) => { globalThis.result = ok && data === "hello"; } );' );
+$runtime->run_event_loop();
+assert( $runtime->get_global( 'result' ) === true );
@@ -0,0 +1,52 @@
+<?php
+
+$extension = new Deno\Core\Extension();
+$extension->ops = [
+    'db_open' => function ( string $dsn ) {
+        return Deno\Core\OpState::current()->add_resource( 'db', new ArrayObject( [ 'dsn' => $dsn ] ) );
+    },
+    'db_dsn' => function ( int $rid ) {
+        return Deno\Core\OpState::current()->get_resource( $rid )['dsn'];
+    },
+    'db_close' => function ( int $rid ) {
+        Deno\Core\OpState::current()->close_resource( $rid );
+    },
+    'app_name' => function () {
+        return Deno\Core\OpState::current()->get( 'app_name' );
+    },
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+$runtime->op_state()->set( 'app_name', 'example' );
+
+$result = $runtime->execute_script( 'index.js', '
+    const rid = Deno.core.ops.db_open( "sqlite::memory:" );
+    const dsn = Deno.core.ops.db_dsn( rid );
+    Deno.core.ops.db_close( rid );
+    Deno.core.ops.app_name() + " " + dsn;
+' );
+
+assert( $result === 'example sqlite::memory:' );
+
+// One PHP function can serve several ops, using the name of the op that is being called.
+$dispatch = function ( ...$args ) {
+    return match ( Deno\Core\OpState::current_op_name() ) {
+        'op_math_add' => $args[0] + $args[1],
+        'op_math_multiply' => $args[0] * $args[1],
+    };
+};
+$extension = new Deno\Core\Extension();
+$extension->name = 'math';
+$extension->ops = [
+    'op_math_add' => $dispatch,
+    'op_math_multiply' => $dispatch,
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+assert( $runtime->execute_script( 'math.js', 'Deno.core.ops.op_math_add( 2, 3 ) + Deno.core.ops.op_math_multiply( 2, 3 )' ) === '11' );
+assert( Deno\Core\OpState::current_op_name() === null );
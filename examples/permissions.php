@@ -39,3 +39,35 @@ $runtime = new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
 
 $runtime->execute_script( 'index.js', 'fetch( "http://example.com" ).then( r => r.text() ).then( r => Deno.core.print(r) );' );
 $runtime->run_event_loop();
+
+// Permission errors can be caught by their Deno error class.
+$caused_exception = false;
+try {
+    $runtime->execute_script( 'index.js', 'Deno.readTextFileSync( "/etc/hosts" );' );
+} catch ( Deno\Errors\PermissionDenied $e ) {
+    $caused_exception = true;
+}
+
+assert( $caused_exception === true );
+
+// Ops can enforce the worker's permissions before acting on behalf of JavaScript.
+$extension = new Deno\Core\Extension();
+$extension->name = 'files';
+$extension->ops = [
+    'op_read_file' => function ( string $path ) {
+        try {
+            Deno\Core\OpState::current()->check_read( $path, 'readFile()' );
+        } catch ( Deno\Errors\PermissionDenied $e ) {
+            return null;
+        }
+        return file_get_contents( $path );
+    },
+];
+
+$permissions = new Deno\Runtime\PermissionsOptions();
+$permissions->allow_read = [ __FILE__ ];
+$options = new Deno\Runtime\WorkerOptions( $boostrap_options, [ $extension ], new ModuleLoader() );
+$runtime = new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
+
+assert( $runtime->execute_script( 'allowed.js', 'Deno.core.ops.op_read_file( ' . json_encode( __FILE__ ) . ' ) !== null' ) === 'true' );
+assert( $runtime->execute_script( 'denied.js', 'Deno.core.ops.op_read_file( "/etc/hosts" ) === null' ) === 'true' );
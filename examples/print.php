@@ -0,0 +1,28 @@
+<?php
+
+// Capture the output of Deno.core.print() in a buffer.
+$output = '';
+$errors = '';
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->print = function ( string $message, bool $is_err ) use ( &$output, &$errors ) {
+    if ( $is_err ) {
+        $errors .= $message;
+    } else {
+        $output .= $message;
+    }
+};
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+$runtime->execute_script( 'index.js', 'Deno.core.print( "Hello\n" ); Deno.core.print( "Oops\n", true ); Deno.core.print( 42 );' );
+
+assert( $output === "Hello\n42" );
+assert( $errors === "Oops\n" );
+
+// Echoing the output makes it subject to PHP's output buffering.
+$runtime_options->print = function ( string $message ) {
+    echo $message;
+};
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+ob_start();
+$runtime->execute_script( 'index.js', 'Deno.core.print( "Buffered" );' );
+assert( ob_get_clean() === 'Buffered' );
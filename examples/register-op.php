@@ -0,0 +1,17 @@
+<?php
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+// Ops can be registered once the runtime exists, such as when a plugin is enabled.
+$runtime->register_op( 'uppercase', fn( string $string ) => strtoupper( $string ) );
+$runtime->register_op( 'lowercase', fn( string $string ) => strtolower( $string ), true );
+
+assert( $runtime->execute_script( 'index.js', 'Deno.core.ops.uppercase( "hello" )' ) === 'HELLO' );
+assert( $runtime->call_global_function( 'eval', [ 'Deno.core.ops.lowercase( "HELLO" )' ] ) === 'hello' );
+
+// The names of all available ops can be listed, to check that the op names used in JavaScript exist.
+$op_names = $runtime->get_op_names();
+assert( in_array( 'uppercase', $op_names, true ) );
+assert( in_array( 'lowercase', $op_names, true ) );
+assert( in_array( 'op_print', $op_names, true ) );
@@ -0,0 +1,14 @@
+<?php
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+while ( ( $line = readline( '> ' ) ) !== false ) {
+	try {
+		var_dump( $runtime->execute_repl( $line ) );
+	} catch ( Deno\Core\JsException $e ) {
+		echo $e->getMessage() . "\n";
+	} catch ( Exception $e ) {
+		echo $e->getMessage() . "\n";
+	}
+}
@@ -0,0 +1,19 @@
+<?php
+
+$extension = new Deno\Core\Extension();
+$extension->name = 'app';
+$extension->ops = [
+    'op_app_name' => fn() => 'example',
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+$runtime->register_op( 'op_request_id', fn() => 42 );
+
+$runtime->execute_script( 'request-1.js', 'globalThis.leaked = "secret";' );
+
+// Resetting discards the state of the previous request, but keeps the extensions and registered ops.
+$runtime->reset();
+assert( $runtime->execute_script( 'request-2.js', 'typeof globalThis.leaked' ) === 'undefined' );
+assert( $runtime->execute_script( 'ops.js', 'Deno.core.ops.op_app_name() + " " + Deno.core.ops.op_request_id()' ) === 'example 42' );
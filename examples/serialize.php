@@ -0,0 +1,11 @@
+<?php
+
+$source = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions() );
+$source->execute_script( 'source.js', 'globalThis.state = { map: new Map( [ [ "a", 1 ] ] ), set: new Set( [ 1, 2 ] ), date: new Date( 0 ), bytes: new Uint8Array( [ 1, 2, 3 ] ) }; state.self = state;' );
+
+$data = $source->serialize_value( 'state' );
+
+$target = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions() );
+$target->deserialize_value( 'state', $data );
+
+assert( $target->execute_script( 'check.js', 'state.map.get( "a" ) === 1 && state.set.has( 2 ) && state.date.getTime() === 0 && state.bytes instanceof Uint8Array && state.self === state' ) === 'true' );
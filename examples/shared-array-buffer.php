@@ -0,0 +1,19 @@
+<?php
+
+$store = new Deno\Core\SharedArrayBufferStore();
+
+$options = new Deno\Core\RuntimeOptions();
+$options->shared_array_buffer_store = $store;
+
+$writer = new Deno\Core\JsRuntime( $options );
+$reader = new Deno\Core\JsRuntime( $options );
+
+$writer->execute_script( 'writer.js', 'globalThis.counter = new Int32Array( new SharedArrayBuffer( 4 ) ); globalThis.data = Deno.core.serialize( counter );' );
+$data = $writer->get_global( 'data' );
+
+$reader->set_global( 'data', new Deno\Core\Uint8Array( $data ) );
+$reader->execute_script( 'reader.js', 'globalThis.counter = Deno.core.deserialize( data );' );
+
+// Both runtimes share the memory of the SharedArrayBuffer.
+$writer->execute_script( 'increment.js', 'Atomics.add( counter, 0, 42 );' );
+assert( $reader->execute_script( 'read.js', 'Atomics.load( counter, 0 )' ) === '42' );
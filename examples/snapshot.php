@@ -15,3 +15,116 @@ $runtime_options->startup_snapshot = $snapshot;
 
 $js_runtime = new Deno\Core\JsRuntime( $runtime_options );
 $js_runtime->execute_script( "index.js", "foo();" );
+
+// Snapshots can also be written to and loaded from a file directly.
+
+$runtime_options = new \Deno\Core\RuntimeOptions;
+$runtime_options->will_snapshot = true;
+
+$js_runtime = new Deno\Core\JsRuntime( $runtime_options );
+$js_runtime->execute_script( "index.js", "function bar() { return 2; }" );
+
+$snapshot_file = tempnam( sys_get_temp_dir(), 'snapshot' );
+$js_runtime->snapshot_to_file( $snapshot_file );
+
+$runtime_options = new \Deno\Core\RuntimeOptions;
+$runtime_options->set_startup_snapshot_from_file( $snapshot_file );
+
+$js_runtime = new Deno\Core\JsRuntime( $runtime_options );
+$js_runtime->execute_script( "index.js", "bar();" );
+
+// SnapshotBuilder bakes extensions and setup scripts into a snapshot in one call.
+
+$extension = new Deno\Core\Extension( 'greeter' );
+$extension->ops = [
+    'op_greeting' => fn() => 'Hello',
+];
+$builder = new Deno\Core\SnapshotBuilder( [ $extension ], [
+    new Deno\Core\JsFile( 'greet.js', 'function greet( name ) { return Deno.core.ops.op_greeting() + " " + name; }' ),
+] );
+$snapshot = $builder->build();
+
+$runtime_options = new \Deno\Core\RuntimeOptions;
+$runtime_options->startup_snapshot = $snapshot;
+$runtime_options->extensions = [ $extension ];
+
+$js_runtime = new Deno\Core\JsRuntime( $runtime_options );
+assert( $js_runtime->execute_script( "index.js", "greet( 'World' )" ) === 'Hello World' );
+
+// A runtime can not take a snapshot when it was itself started from one.
+
+$runtime_options = new \Deno\Core\RuntimeOptions;
+$runtime_options->startup_snapshot = $snapshot;
+$runtime_options->will_snapshot = true;
+try {
+    new Deno\Core\JsRuntime( $runtime_options );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'will_snapshot' ) );
+}
+
+// A runtime can not be used after it has been snapshotted.
+
+$runtime_options = new \Deno\Core\RuntimeOptions;
+$runtime_options->will_snapshot = true;
+$js_runtime = new Deno\Core\JsRuntime( $runtime_options );
+$js_runtime->snapshot();
+foreach ( [ fn() => $js_runtime->run_event_loop(), fn() => $js_runtime->get_global( 'foo' ), fn() => $js_runtime->snapshot() ] as $call ) {
+    try {
+        $call();
+        assert( false );
+    } catch ( Exception $e ) {
+        assert( str_contains( $e->getMessage(), 'snapshotted' ) );
+    }
+}
+
+// A StaticSnapshot is loaded once and shared by many runtimes without being copied for each one.
+
+$snapshot_file = tempnam( sys_get_temp_dir(), 'snapshot' );
+$builder->build_to_file( $snapshot_file );
+$static_snapshot = Deno\Core\StaticSnapshot::from_file( $snapshot_file );
+assert( $static_snapshot->length() === filesize( $snapshot_file ) );
+
+for ( $i = 0; $i < 3; $i++ ) {
+    $runtime_options = new \Deno\Core\RuntimeOptions;
+    $runtime_options->startup_snapshot = $static_snapshot;
+    $runtime_options->extensions = [ $extension ];
+
+    $js_runtime = new Deno\Core\JsRuntime( $runtime_options );
+    assert( $js_runtime->execute_script( "index.js", "greet( 'Request $i' )" ) === "Hello Request $i" );
+}
+
+$static_snapshot = new Deno\Core\StaticSnapshot( $snapshot );
+$runtime_options = new \Deno\Core\RuntimeOptions;
+$runtime_options->startup_snapshot = $static_snapshot;
+$runtime_options->extensions = [ $extension ];
+$js_runtime = new Deno\Core\JsRuntime( $runtime_options );
+assert( $js_runtime->execute_script( "index.js", "greet( 'again' )" ) === 'Hello again' );
+
+// startup_snapshot may also be the path of a snapshot file.
+
+$runtime_options = new \Deno\Core\RuntimeOptions;
+$runtime_options->startup_snapshot = $snapshot_file;
+$runtime_options->extensions = [ $extension ];
+$js_runtime = new Deno\Core\JsRuntime( $runtime_options );
+assert( $js_runtime->execute_script( "index.js", "greet( 'file' )" ) === 'Hello file' );
+
+// Anything else, such as a base64 encoded snapshot, throws an exception instead of crashing V8.
+
+$runtime_options = new \Deno\Core\RuntimeOptions;
+$runtime_options->startup_snapshot = base64_encode( $snapshot );
+try {
+    new Deno\Core\JsRuntime( $runtime_options );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'neither valid snapshot data nor the path of a snapshot file' ) );
+}
+
+try {
+    new Deno\Core\StaticSnapshot( base64_encode( $snapshot ) );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'not valid snapshot data' ) );
+}
+
+unlink( $snapshot_file );
@@ -0,0 +1,103 @@
+<?php
+
+// Stream ops return a Generator (or any Iterator), whose chunks JavaScript reads one at a time.
+
+$produced = 0;
+$closed = false;
+$extension = new Deno\Core\Extension( 'files' );
+$extension->stream_ops = [
+    'op_read_file' => function ( string $path, int $chunk_size ) {
+        $handle = fopen( $path, 'rb' );
+        try {
+            while ( ! feof( $handle ) ) {
+                yield fread( $handle, $chunk_size );
+            }
+        } finally {
+            fclose( $handle );
+        }
+    },
+    'op_count' => function () use ( &$produced, &$closed ) {
+        try {
+            for ( $i = 0; ; $i++ ) {
+                $produced++;
+                yield $i;
+            }
+        } finally {
+            $closed = true;
+        }
+    },
+    'op_list' => fn () => [ 'a', [ 'b' => 1 ] ],
+    'op_invalid' => fn () => 42,
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+// String chunks are Uint8Arrays of their bytes, so binary files are read exactly.
+$path = tempnam( sys_get_temp_dir(), 'stream' );
+file_put_contents( $path, random_bytes( 10000 ) );
+$runtime->set_global( 'path', $path );
+$runtime->execute_script( 'index.js', <<<'JS'
+(async () => {
+    const chunks = [];
+    for await ( const chunk of Deno.core.ops.op_read_file( path, 4096 ) ) {
+        chunks.push( chunk );
+    }
+    globalThis.result = chunks.map( ( chunk ) => chunk.length );
+    globalThis.first_byte = chunks[0][0];
+})();
+JS );
+$runtime->run_event_loop();
+assert( $runtime->get_global( 'result' ) === [ 4096, 4096, 1808 ] );
+assert( $runtime->get_global( 'first_byte' ) === ord( file_get_contents( $path )[0] ) );
+
+// Chunks are only produced when they are read, and stopping early destroys the Generator.
+$runtime->execute_script( 'index.js', <<<'JS'
+(async () => {
+    for await ( const i of Deno.core.ops.op_count() ) {
+        if ( i === 2 ) {
+            break;
+        }
+    }
+})();
+JS );
+$runtime->run_event_loop();
+assert( $produced === 3 );
+assert( $closed === true );
+
+// Arrays are streamed too, and other values are converted like the return values of ops.
+$runtime->execute_script( 'index.js', <<<'JS'
+(async () => {
+    globalThis.list = [];
+    for await ( const value of Deno.core.ops.op_list() ) {
+        list.push( value );
+    }
+})();
+JS );
+$runtime->run_event_loop();
+assert( $runtime->get_global( 'list' ) === [ 'a', [ 'b' => 1 ] ] );
+
+try {
+    $runtime->execute_script( 'index.js', 'Deno.core.ops.op_invalid()' );
+    assert( false );
+} catch ( Deno\Core\JsException $e ) {
+    assert( str_contains( $e->getMessage(), 'Stream ops must return an array or an Iterator' ) );
+}
+
+// A MainWorker has ReadableStream, so stream ops return one.
+$worker_options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [ $extension ], new Deno\Core\FsModuleLoader( __DIR__ ) );
+$worker = new Deno\Runtime\MainWorker( 'index.js', new Deno\Runtime\PermissionsOptions(), $worker_options );
+file_put_contents( $path, 'Hello from a PHP stream' );
+$worker->execute_script( 'index.js', <<<JS
+(async () => {
+    const stream = Deno.core.ops.op_read_file( "$path", 5 );
+    globalThis.is_stream = stream instanceof ReadableStream;
+    globalThis.text = await new Response( stream ).text();
+})();
+JS );
+$worker->run_event_loop();
+assert( $worker->evaluate( 'index.js', 'is_stream' ) === true );
+assert( $worker->evaluate( 'index.js', 'text' ) === 'Hello from a PHP stream' );
+
+unlink( $path );
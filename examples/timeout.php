@@ -0,0 +1,21 @@
+<?php
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+$caused_exception = false;
+
+try {
+    $runtime->execute_script( 'index.js', 'while ( true ) {}', 100 );
+} catch ( Deno\Core\JsException $e ) {
+    echo $e->getMessage();
+    $caused_exception = true;
+}
+
+assert( $caused_exception === true );
+
+// The runtime can still be used after a timeout.
+assert( $runtime->execute_script( 'index.js', '1 + 1' ) === '2' );
+
+// A timeout does not leave the runtime unhealthy.
+assert( $runtime->is_terminating() === false );
+assert( $runtime->is_healthy() === true );
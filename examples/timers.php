@@ -0,0 +1,45 @@
+<?php
+
+class ModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        return null;
+    }
+}
+
+$boostrap_options = new Deno\Runtime\BootstrapOptions();
+$options = new Deno\Runtime\WorkerOptions( $boostrap_options , [], new ModuleLoader() );
+$permissions = new Deno\Runtime\PermissionsOptions();
+$worker = new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
+
+$worker->execute_script( 'index.js', 'globalThis.fired = []; setTimeout( () => fired.push( "timeout" ), 10 ); let ticks = 0; const interval = setInterval( () => { fired.push( "interval" ); if ( ++ticks === 2 ) clearInterval( interval ); }, 5 );' );
+assert( $worker->execute_script( 'check.js', 'fired.length' ) === '0' );
+
+// Timers registered by execute_script() are driven by a later run_event_loop().
+$worker->run_event_loop();
+assert( $worker->execute_script( 'check.js', 'fired.sort().join()' ) === 'interval,interval,timeout' );
+
+// A timeout caps how long the event loop runs, for example for an interval that is never cleared.
+$worker->execute_script( 'index.js', 'globalThis.polls = 0; globalThis.poller = setInterval( () => polls++, 5 );' );
+assert( $worker->run_event_loop( 50 ) === false );
+assert( (int) $worker->execute_script( 'check.js', 'polls' ) > 0 );
+assert( $worker->has_pending_work() === true );
+
+// The event loop reports that it completed once no work is left.
+$worker->execute_script( 'index.js', 'clearInterval( poller );' );
+assert( $worker->run_event_loop( 50 ) === true );
+assert( $worker->run_event_loop() === true );
+assert( $worker->has_pending_work() === false );
+
+// A scheduler can pump the event loop in slices while work remains.
+$worker->execute_script( 'index.js', 'globalThis.done = false; setTimeout( () => { done = true; }, 30 );' );
+$slices = 0;
+while ( $worker->has_pending_work() ) {
+    $worker->run_event_loop( 5 );
+    $slices++;
+}
+assert( $worker->execute_script( 'check.js', 'done' ) === 'true' );
+assert( $slices >= 1 );
@@ -16,3 +16,163 @@ var_dump($emit_options);
 var_dump( $module->transpile( $emit_options ) );
 
 var_dump( microtime(true) - $start );
+
+$parse_params->text_info = "const a = 1;\nconst b: number = 2;";
+$parse_params->media_type = Deno\AST\MediaType::TYPESCRIPT;
+$module = Deno\AST\parse_module( $parse_params );
+assert( $module->line_and_column_index( 19 ) == [ 'line' => 1, 'column' => 6 ] );
+assert( $module->byte_index( 1, 6 ) === 19 );
+
+// Write the transpiled file with its source map alongside it.
+$emit_options->inline_source_map = false;
+$emit_options->source_map = true;
+$transpiled = $module->transpile( $emit_options );
+$transpiled->write_to_file( sys_get_temp_dir() . '/module.js' );
+assert( str_ends_with( file_get_contents( sys_get_temp_dir() . '/module.js' ), "//# sourceMappingURL=module.js.map\n" ) );
+assert( file_get_contents( sys_get_temp_dir() . '/module.js.map' ) === $transpiled->source_map );
+
+// Only remove the types, keeping JSX as-is.
+$parse_params->text_info = 'const element = <div>{ name as string }</div>;';
+$parse_params->media_type = Deno\AST\MediaType::TSX;
+$stripped = Deno\AST\parse_module( $parse_params )->strip_types();
+assert( str_contains( $stripped->text, '<div>' ) && ! str_contains( $stripped->text, 'as string' ) );
+
+// Force decorator syntax on for JavaScript, which the media type alone wouldn't enable.
+$parse_params->text_info = '@decorator class Component {}';
+$parse_params->media_type = Deno\AST\MediaType::JAVASCRIPT;
+try {
+    Deno\AST\parse_module( $parse_params );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( $e->getMessage() !== '' );
+}
+$syntax = new Deno\AST\Syntax;
+$syntax->typescript = false;
+$syntax->decorators = true;
+$parse_params->syntax = $syntax;
+$module = Deno\AST\parse_module( $parse_params );
+assert( $module->diagnostics() === [] );
+assert( str_contains( $module->strip_types()->text, 'class Component' ) );
+
+// Without a media type, the type is inferred from the specifier's file extension.
+$parse_params = new Deno\AST\ParseParams;
+$parse_params->specifier = 'file:///app/types.ts';
+$parse_params->text_info = 'const count: number = 1;';
+$module = Deno\AST\parse_module( $parse_params );
+assert( ! str_contains( $module->transpile( new Deno\AST\EmitOptions )->text, ': number' ) );
+
+// Unknown media types are an error, rather than silently parsing with the wrong syntax.
+$parse_params->media_type = 'typescript/unknown';
+try {
+    Deno\AST\parse_module( $parse_params );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'Unknown media type' ) );
+}
+
+// Parse and transpile in one call.
+$transpiled = Deno\AST\transpile( 'export const add = ( a: number, b: number ): number => a + b;', 'file:///app/add.ts', new Deno\AST\EmitOptions );
+assert( ! str_contains( $transpiled->text, ': number' ) );
+
+try {
+    Deno\AST\transpile( 'const = 1;', 'file:///app/invalid.ts', new Deno\AST\EmitOptions );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'file:///app/invalid.ts' ) );
+}
+
+// Legacy decorators can emit the design-time metadata used by reflect-metadata.
+$emit_options = new Deno\AST\EmitOptions;
+assert( $emit_options->decorators === 'legacy' );
+$emit_options->emit_metadata = true;
+$source = 'declare const Column: PropertyDecorator; class User { @Column name: string; }';
+$transpiled = Deno\AST\transpile( $source, 'file:///app/user.ts', $emit_options );
+assert( str_contains( $transpiled->text, 'design:type' ) );
+
+// Metadata can't be emitted for TC39 decorators.
+$emit_options->decorators = 'tc39';
+try {
+    Deno\AST\transpile( $source, 'file:///app/user.ts', $emit_options );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'emit_metadata' ) );
+}
+
+// TC39 decorators can't be transformed by this version of deno_ast.
+$emit_options->emit_metadata = false;
+try {
+    Deno\AST\transpile( $source, 'file:///app/user.ts', $emit_options );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'TC39 decorators are not supported' ) );
+}
+
+// The parser recovers from some syntax errors, which are available as diagnostics.
+$parse_params = new Deno\AST\ParseParams;
+$parse_params->specifier = 'file:///app/editor.ts';
+$parse_params->text_info = "const a = 1;\nlet let = 2;";
+$module = Deno\AST\parse_module( $parse_params );
+$diagnostics = $module->diagnostics();
+assert( count( $diagnostics ) > 0 );
+assert( $diagnostics[0] instanceof Deno\AST\Diagnostic );
+assert( $diagnostics[0]->specifier === 'file:///app/editor.ts' );
+assert( $diagnostics[0]->line === 2 );
+
+// The imports and exports of a module, for example to compute a dependency graph.
+$parse_params = new Deno\AST\ParseParams;
+$parse_params->specifier = 'file:///app/main.ts';
+$parse_params->text_info = <<<'TS'
+import { add } from "./add.ts";
+import type { Options } from "./options.ts";
+export * from "./utils.ts";
+export { sub as subtract } from "./sub.ts";
+export const { a, b: [ b ] } = { a: 1, b: [ 2 ] };
+export function main( options: Options ) { return add( 1, 2 ); }
+export interface Result { value: number }
+export default main;
+TS;
+$module_info = Deno\AST\parse_module( $parse_params )->module_info();
+assert( $module_info['imports'] === [ './add.ts', './options.ts', './utils.ts', './sub.ts' ] );
+assert( $module_info['exports'] === [ 'subtract', 'a', 'b', 'main', 'Result', 'default' ] );
+
+// Check which media type the module was parsed as.
+$parse_params = new Deno\AST\ParseParams;
+$parse_params->specifier = 'file:///app/component.tsx';
+$parse_params->text_info = 'export const App = () => <div />;';
+$module = Deno\AST\parse_module( $parse_params );
+assert( $module->specifier() === 'file:///app/component.tsx' );
+assert( $module->media_type() === Deno\AST\MediaType::TSX );
+
+$parse_params->specifier = 'file:///app/module.mts';
+$parse_params->text_info = 'export const answer: number = 42;';
+assert( Deno\AST\parse_module( $parse_params )->media_type() === 'mts' );
+
+$parse_params->media_type = Deno\AST\MediaType::JSX;
+$parse_params->text_info = 'export const App = () => <div />;';
+assert( Deno\AST\parse_module( $parse_params )->media_type() === Deno\AST\MediaType::JSX );
+
+// Cache transpiled modules on disk, so they're only transpiled again when their source changes.
+$cache_dir = sys_get_temp_dir() . '/transpile-cache-' . getmypid();
+$cache = new Deno\AST\TranspileCache( $cache_dir );
+$source = 'const answer: number = 42;';
+$transpiled = $cache->transpile( $source, 'file:///app/answer.ts' );
+assert( str_contains( $transpiled->text, 'const answer = 42;' ) );
+assert( count( glob( "$cache_dir/*.js" ) ) === 1 );
+assert( $cache->transpile( $source, 'file:///app/answer.ts' )->text === $transpiled->text );
+assert( count( glob( "$cache_dir/*.js" ) ) === 1 );
+
+// A changed source is transpiled again.
+$transpiled = $cache->transpile( 'const answer: number = 43;', 'file:///app/answer.ts' );
+assert( str_contains( $transpiled->text, 'const answer = 43;' ) );
+assert( count( glob( "$cache_dir/*.js" ) ) === 2 );
+
+// The module loader can use the cache too.
+$options = new Deno\Core\RuntimeOptions;
+$options->module_loader = new Deno\Core\FsModuleLoader( __DIR__ . '/fs-modules', null, $cache );
+$runtime = new Deno\Core\JsRuntime( $options );
+$module_id = $runtime->load_main_module( 'file://' . __DIR__ . '/fs-modules/main.ts' );
+$runtime->mod_evaluate( $module_id );
+assert( count( glob( "$cache_dir/*.js" ) ) > 2 );
+
+array_map( 'unlink', glob( "$cache_dir/*" ) );
+rmdir( $cache_dir );
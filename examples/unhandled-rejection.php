@@ -0,0 +1,39 @@
+<?php
+
+// By default, an unhandled Promise rejection stops the event loop and is thrown.
+
+$runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions );
+$runtime->execute_script( 'index.js', 'Promise.reject( new Error( "boom" ) );' );
+try {
+    $runtime->run_event_loop();
+    assert( false );
+} catch ( Deno\Core\JsException $e ) {
+    assert( $e->getMessage() === 'boom' );
+}
+
+// With on_unhandled_rejection, rejections are passed to the callable, such as to log them, and
+// the event loop continues.
+
+$rejections = [];
+$options = new Deno\Core\RuntimeOptions;
+$options->on_unhandled_rejection = function ( Deno\Core\JsException $e ) use ( &$rejections ) {
+    $rejections[] = $e;
+};
+$runtime = new Deno\Core\JsRuntime( $options );
+$runtime->execute_script( 'file:///app/index.js', <<<'JS'
+async function failing() {
+    throw new TypeError( "bad input" );
+}
+failing();
+Promise.reject( new Error( "boom" ) );
+// A rejection that is handled is not reported.
+Promise.reject( new Error( "handled" ) ).catch( () => {} );
+globalThis.finished = false;
+setTimeout( () => { globalThis.finished = true; }, 10 );
+JS );
+assert( $runtime->run_event_loop() === true );
+assert( $runtime->get_global( 'finished' ) === true );
+assert( count( $rejections ) === 2 );
+assert( $rejections[0]->getMessage() === 'bad input' );
+assert( $rejections[0]->frames[0]->file === 'file:///app/index.js' );
+assert( $rejections[1]->getMessage() === 'boom' );
@@ -0,0 +1,21 @@
+<?php
+
+// V8 flags have to be set before the first runtime is created.
+Deno\Core\set_v8_flags( [ '--expose-gc' ] );
+
+try {
+    Deno\Core\set_v8_flags( [ '--not-a-v8-flag' ] );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), '--not-a-v8-flag' ) );
+}
+
+$runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions() );
+assert( $runtime->execute_script( 'gc.js', 'typeof gc' ) === 'function' );
+
+try {
+    Deno\Core\set_v8_flags( [ '--jitless' ] );
+    assert( false );
+} catch ( Exception $e ) {
+    echo $e->getMessage() . "\n";
+}
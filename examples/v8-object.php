@@ -0,0 +1,34 @@
+<?php
+
+$runtime = new Deno\Core\JsRuntime( new Deno\Core\RuntimeOptions() );
+
+// Objects returned from JavaScript are Deno\Core\V8Objects.
+$runtime->execute_script( 'index.js', 'globalThis.post = { title: "Hello", tags: [ "news" ], author: { name: "Alice" } };' );
+$post = $runtime->get_global( 'post' );
+assert( $post instanceof Deno\Core\V8Object );
+assert( $post->title === 'Hello' );
+assert( $post->get( 'title' ) === 'Hello' );
+assert( $post->get( 'missing' ) === null );
+assert( $post->keys() === [ 'title', 'tags', 'author' ] );
+
+$array = $post->to_array();
+assert( $array['tags'] === [ 'news' ] );
+assert( $array['author'] instanceof Deno\Core\V8Object );
+assert( $array['author']->get( 'name' ) === 'Alice' );
+
+// Setting a property changes the PHP object, which is converted back to a JavaScript object with
+// its current properties.
+$post->set( 'title', 'Hello again' );
+$post->set( 'views', 10 );
+assert( $post->keys() === [ 'title', 'tags', 'author', 'views' ] );
+$runtime->set_global( 'post', $post );
+assert( $runtime->execute_script( 'index.js', '`${ post.title } by ${ post.author.name } (${ post.views })`' ) === 'Hello again by Alice (10)' );
+assert( $runtime->execute_script( 'index.js', 'Object.getPrototypeOf( post ) === Object.prototype' ) === 'true' );
+
+// Properties with symbol keys are skipped, rather than colliding with string keys.
+$runtime->execute_script( 'index.js', 'globalThis.tagged = { name: "string key", [ Symbol( "name" ) ]: "symbol key" };' );
+$tagged = $runtime->get_global( 'tagged' );
+assert( $tagged->keys() === [ 'name' ] );
+assert( $tagged->name === 'string key' );
+$runtime->execute_script( 'index.js', 'globalThis.tagged_array = Object.assign( Object.create( null ), { 0: "a", [ Symbol( "0" ) ]: "b" } );' );
+assert( $runtime->get_global( 'tagged_array' ) === [ 0 => 'a' ] );
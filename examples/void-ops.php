@@ -0,0 +1,34 @@
+<?php
+
+$log = [];
+
+$extension = new Deno\Core\Extension( 'metrics' );
+$extension->void_ops = [
+    'op_log' => function ( string $message ) use ( &$log ) {
+        $log[] = $message;
+        // The return value of a void op is discarded.
+        return [ 'ignored' => true ];
+    },
+];
+
+$runtime_options = new Deno\Core\RuntimeOptions();
+$runtime_options->extensions = [ $extension ];
+$runtime = new Deno\Core\JsRuntime( $runtime_options );
+
+assert( $runtime->execute_script( 'index.js', 'typeof Deno.core.ops.op_log( "started" )' ) === 'undefined' );
+$runtime->execute_script( 'index.js', 'for ( let i = 0; i < 3; i++ ) Deno.core.ops.op_log( `tick ${ i }` );' );
+assert( $log === [ 'started', 'tick 0', 'tick 1', 'tick 2' ] );
+
+// Void ops can also be registered after the runtime has been constructed.
+$counter = 0;
+$runtime->register_void_op( 'op_increment', function () use ( &$counter ) {
+    $counter++;
+} );
+$runtime->execute_script( 'index.js', 'Deno.core.ops.op_increment(); Deno.core.ops.op_increment();' );
+assert( $counter === 2 );
+
+// Exceptions thrown by void ops are still thrown in JavaScript.
+$runtime->register_void_op( 'op_fail', function () {
+    throw new Exception( 'Failed to record' );
+} );
+assert( $runtime->execute_script( 'index.js', 'try { Deno.core.ops.op_fail(); "no error" } catch ( e ) { e.message }' ) === 'Failed to record' );
@@ -0,0 +1,22 @@
+<?php
+
+$store = new Deno\Core\CompiledWasmModuleStore();
+
+$options = new Deno\Core\RuntimeOptions();
+$options->compiled_wasm_module_store = $store;
+
+$compiler = new Deno\Core\JsRuntime( $options );
+$compiler->execute_script( 'compile.js', <<<END
+const bytes = new Uint8Array( [
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x07, 0x01, 0x60, 0x02, 0x7f, 0x7f, 0x01,
+    0x7f, 0x03, 0x02, 0x01, 0x00, 0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64, 0x00, 0x00, 0x0a, 0x09,
+    0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b,
+] );
+globalThis.data = Deno.core.serialize( new WebAssembly.Module( bytes ) );
+END );
+$data = $compiler->get_global( 'data' );
+
+// The module is instantiated from the compiled module in the store, without compiling it again.
+$runtime = new Deno\Core\JsRuntime( $options );
+$runtime->set_global( 'data', new Deno\Core\Uint8Array( $data ) );
+assert( $runtime->execute_script( 'instantiate.js', 'new WebAssembly.Instance( Deno.core.deserialize( data ) ).exports.add( 1, 2 )' ) === '3' );
@@ -0,0 +1,65 @@
+<?php
+
+class ModuleLoader implements Deno\Core\ModuleLoader {
+    function resolve( string $specifier, string $referer ) : string {
+        return $specifier;
+    }
+
+    function load( string $specifier ) : ?Deno\Core\ModuleSource {
+        return null;
+    }
+}
+
+$options = new Deno\Runtime\WorkerOptions( new Deno\Runtime\BootstrapOptions(), [], new ModuleLoader() );
+$permissions = new Deno\Runtime\PermissionsOptions();
+$permissions->allow_net = [];
+
+// Invalid certificates are rejected when the worker is created.
+$options->ca_certificates = [ 'not a certificate' ];
+try {
+    new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
+    assert( false );
+} catch ( Exception $e ) {
+    assert( str_contains( $e->getMessage(), 'ca_certificates' ) );
+}
+
+// A local WebSocket echo server using the example certificates in tls/, which are signed by the
+// example CA in tls/ca.crt. The worker connects to its own server, sends a message and records the
+// echo, or "error" when the connection fails.
+function websocket_echo( Deno\Runtime\WorkerOptions $options, Deno\Runtime\PermissionsOptions $permissions ) : string {
+    $cert = json_encode( file_get_contents( __DIR__ . '/tls/localhost.crt' ) );
+    $key = json_encode( file_get_contents( __DIR__ . '/tls/localhost.key' ) );
+    $worker = new Deno\Runtime\MainWorker( 'index.js', $permissions, $options );
+    $worker->execute_script( 'websocket.js', <<<END
+        const server = Deno.listenTls( { hostname: "localhost", port: 0, cert: $cert, key: $key } );
+        ( async () => {
+            const httpConn = Deno.serveHttp( await server.accept() );
+            const requestEvent = await httpConn.nextRequest();
+            const { socket, response } = Deno.upgradeWebSocket( requestEvent.request );
+            socket.onmessage = ( event ) => socket.send( event.data );
+            await requestEvent.respondWith( response );
+        } )().catch( () => {} ).finally( () => server.close() );
+
+        new Promise( ( resolve ) => {
+            const socket = new WebSocket( `wss://localhost:\${server.addr.port}` );
+            socket.onopen = () => socket.send( "ping" );
+            socket.onmessage = ( event ) => { resolve( event.data ); socket.close(); };
+            socket.onerror = () => resolve( "error" );
+        } ).then( ( result ) => globalThis.result = result );
+        END );
+    $worker->run_event_loop();
+    return $worker->execute_script( 'result.js', 'globalThis.result' );
+}
+
+// The server's certificate is not trusted by default.
+$options->ca_certificates = [];
+assert( websocket_echo( $options, $permissions ) === 'error' );
+
+// WebSocket (and fetch) trust the application's CA bundle, for example for a realtime API behind a private CA.
+$options->ca_certificates = [ file_get_contents( __DIR__ . '/tls/ca.crt' ) ];
+assert( websocket_echo( $options, $permissions ) === 'ping' );
+
+// Certificate errors can be ignored instead, here for every host.
+$options->ca_certificates = [];
+$options->unsafely_ignore_certificate_errors = [];
+assert( websocket_echo( $options, $permissions ) === 'ping' );
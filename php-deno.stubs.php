@@ -8,11 +8,126 @@ namespace Deno\AST {
      */
     function parse_module(\Deno\AST\ParseParams $params): \Deno\AST\ParsedSource {}
 
+    /**
+     * Parse and transpile a TypeScript (or similar) module in one call. The media type is inferred
+     * from the file extension of `specifier`, such as TypeScript for `file:///app/index.ts`. Throws
+     * an exception with the diagnostics if the source has syntax errors.
+     */
+    function transpile(string $source, string $specifier, \Deno\AST\EmitOptions $options): \Deno\AST\TranspiledSource {}
+
+    /**
+     * Bundle an ES module and the modules it imports into a single ES module, for example to
+     * precompute a file for a snapshot. Modules are loaded with `module_loader` (see
+     * `Deno\Core\RuntimeOptions::module_loader`) and transpiled with `options`. The bundle
+     * exports the exports of the entry module.
+     *
+     * Each module is evaluated in a function in the order it is imported, so top level await and
+     * cyclic imports are not supported. Imported bindings are read when the importing module is
+     * evaluated rather than being live bindings. Dynamic `import()`s are left as-is, and source maps
+     * are not emitted.
+     *
+     * @param \Deno\Core\ModuleLoader|\Deno\Core\FsModuleLoader $module_loader
+     */
+    function bundle(string $entry_specifier, mixed $module_loader, \Deno\AST\EmitOptions $options): \Deno\AST\TranspiledSource {}
+
+    /**
+     * A problem found while parsing a module, such as a syntax error. See `ParsedSource::diagnostics()`.
+     */
+    class Diagnostic {
+        /**
+         * The specifier of the module.
+         * @var string
+         */
+        public $specifier;
+
+        /**
+         * @var string
+         */
+        public $message;
+
+        /**
+         * The one-based line number of the start of the problem.
+         * @var int
+         */
+        public $line;
+
+        /**
+         * The one-based column number of the start of the problem.
+         * @var int
+         */
+        public $column;
+
+        /**
+         * The byte offset of the start of the problem in the source text.
+         * @var int
+         */
+        public $start;
+
+        /**
+         * The byte offset of the end of the problem in the source text.
+         * @var int
+         */
+        public $end;
+    }
+
     class ParsedSource {
         /**
          * Transpile the ASP to TypeScript, with the provided EmitOptions. Throws an exception or returns Deno\AST\TranspiledSource
          */
         public function transpile(\Deno\AST\EmitOptions $options): \Deno\AST\TranspiledSource {}
+
+        /**
+         * Remove TypeScript types, without the other transforms of `transpile()`. JSX is preserved
+         * and no source map is generated, which makes this faster than `transpile()` for large files.
+         * Note that decorators are still transformed, as deno_ast always transforms them.
+         */
+        public function strip_types(): \Deno\AST\TranspiledSource {}
+
+        /**
+         * Get the diagnostics, such as syntax errors, that were found while parsing the module. The
+         * parser recovers from some syntax errors, so a module can be parsed (and transpiled) although
+         * it has diagnostics, which is useful for tools such as editors. Errors that the parser can not
+         * recover from are thrown by `parse_module()` instead.
+         *
+         * @return \Deno\AST\Diagnostic[]
+         */
+        public function diagnostics(): array {}
+
+        /**
+         * Get the specifiers of the modules that this module imports (including re-exports such as
+         * `export * from "./mod.ts"`), and the names that it exports, for example to compute a
+         * dependency graph without parsing the modules again. The specifiers are as written in the
+         * source, and are not resolved. Dynamic imports are not included.
+         *
+         * @return array{imports: string[], exports: string[]}
+         */
+        public function module_info(): array {}
+
+        /**
+         * Get the zero-based line and column index of a byte offset in the source text, such as an
+         * offset from a diagnostic.
+         *
+         * @return array{line: int, column: int}
+         */
+        public function line_and_column_index(int $offset): array {}
+
+        /**
+         * Get the specifier of the module, as given in `ParseParams::specifier`.
+         */
+        public function specifier(): string {}
+
+        /**
+         * Get the media type that the module was parsed as, which is inferred from the file extension
+         * of the specifier unless `ParseParams::media_type` was given. This is one of the
+         * `Deno\AST\MediaType` constants, or the lowercase name of another media type known to
+         * deno_ast, such as "mts" or "dts".
+         */
+        public function media_type(): string {}
+
+        /**
+         * Get the byte offset in the source text of a zero-based line and column index.
+         */
+        public function byte_index(int $line, int $column): int {}
     }
 
     /**
@@ -30,6 +145,33 @@ namespace Deno\AST {
          * @var string|null
          */
         public $source_map;
+
+        /**
+         * Write the transpiled text to a file. When there is a `source_map` (see
+         * `EmitOptions::source_map`), it is written alongside as `$path.map`, and a
+         * `//# sourceMappingURL` comment referencing it is appended to the text.
+         */
+        public function write_to_file(string $path): mixed {}
+    }
+
+    /**
+     * A cache of transpiled modules on disk, so a module is only transpiled again when its source code
+     * changes. Entries are keyed by a hash of the source code, the specifier and the `EmitOptions`, so
+     * changed modules never need to be removed from the cache. Pass the cache to
+     * `Deno\Core\FsModuleLoader`, or call `transpile()` from a `Deno\Core\ModuleLoader`.
+     */
+    class TranspileCache {
+        /**
+         * The cache directory is created if it doesn't exist. Modules are transpiled with the default
+         * `EmitOptions` unless `options` is given.
+         */
+        public function __construct(string $dir, ?\Deno\AST\EmitOptions $options = null) {}
+
+        /**
+         * Transpile a module in the same way as `Deno\AST\transpile()`, returning the cached result
+         * when the same source code has been transpiled before.
+         */
+        public function transpile(string $source, string $specifier): \Deno\AST\TranspiledSource {}
     }
 
     /**
@@ -38,7 +180,9 @@ namespace Deno\AST {
      */
     class ParseParams {
         /**
-         * The type of the module, specified as a mime-type such as application/typescript etc.
+         * The type of the module, specified as one of the `Deno\AST\MediaType` constants or a
+         * mime-type such as application/typescript etc. When empty (the default), the type is inferred
+         * from the file extension of `specifier`, such as TypeScript for `file:///app/index.ts`.
          * @var string
          */
         public $media_type;
@@ -55,9 +199,77 @@ namespace Deno\AST {
          */
         public $text_info;
 
+        /**
+         * Override the syntax that is otherwise inferred from `media_type`, for example to parse
+         * experimental decorators in JavaScript.
+         * @var \Deno\AST\Syntax|null
+         */
+        public $syntax;
+
+        public function __construct() {}
+    }
+
+    /**
+     * The syntax to parse a module with, for `ParseParams::syntax`.
+     */
+    class Syntax {
+        /**
+         * Parse TypeScript rather than JavaScript. Defaults to `true`.
+         * @var bool
+         */
+        public $typescript;
+
+        /**
+         * Parse JSX (TSX when `typescript` is true). Defaults to `false`.
+         * @var bool
+         */
+        public $jsx;
+
+        /**
+         * Parse decorators. Defaults to `false`.
+         * @var bool
+         */
+        public $decorators;
+
+        /**
+         * Parse decorators before `export`, such as `@decorator export class A {}`. JavaScript only.
+         * Defaults to `false`.
+         * @var bool
+         */
+        public $decorators_before_export;
+
+        /**
+         * Parse a TypeScript declaration (`.d.ts`) file. TypeScript only. Defaults to `false`.
+         * @var bool
+         */
+        public $dts;
+
         public function __construct() {}
     }
 
+    /**
+     * MediaType contains the module types understood by Deno\AST\parse_module. Use these constants
+     * for `ParseParams::media_type` rather than hand-writing content-type strings.
+     */
+    class MediaType {
+        const TYPESCRIPT = 'typescript';
+
+        const TSX = 'tsx';
+
+        const JAVASCRIPT = 'javascript';
+
+        const JSX = 'jsx';
+
+        const JSON = 'json';
+
+        /**
+         * Get the content-type string for one of the MediaType constants, such as
+         * `application/typescript` for `MediaType::TYPESCRIPT`. Returns null for unknown media types.
+         * @return string|null
+         */
+        public static function content_type(string $media_type): ?string {}
+    }
+
     /**
      * TypeScript compiler options used when transpiling.
      */
@@ -120,9 +332,28 @@ namespace Deno\AST {
          */
         public $inline_source_map;
 
+        /**
+         * What to do with import statements that only import types i.e. whether to
+         * remove them (`remove`), keep them as side-effect imports (`preserve`) or
+         * error (`error`). Matches the TypeScript `importsNotUsedAsValues` compiler
+         * option. Defaults to `remove`.
+         * @var string
+         */
+        public $imports_not_used_as_values;
+
+        /**
+         * Which decorator transform to apply. This version of deno_ast only
+         * implements `legacy` (TypeScript's `experimentalDecorators`), so `tc39`
+         * (the standard decorators proposal) is rejected when transpiling.
+         * Defaults to `legacy`.
+         * @var string
+         */
+        public $decorators;
+
         /**
          * When emitting a legacy decorator, also emit experimental decorator meta
-         * data.  Defaults to `false`.
+         * data, as used by `reflect-metadata`. Only supported when `decorators` is
+         * `legacy`.  Defaults to `false`.
          * @var bool
          */
         public $emit_metadata;
@@ -132,63 +363,777 @@ namespace Deno\AST {
          * transpiling JSX.
          * @var string
          */
-        public $jsx_import_source;
+        public $jsx_import_source;
+
+        /**
+         * Should the sources be inlined in the source map.  Defaults to `true`.
+         * @var bool
+         */
+        public $inline_sources;
+
+        public function __construct() {}
+    }
+}
+
+namespace Deno\Core {
+    /**
+     * Set V8 flags, such as `--expose-gc`, `--jitless` or `--max-old-space-size=512`. V8 flags apply
+     * to all isolates in the process, so they must be set before the first `Deno\Core\JsRuntime` or
+     * `Deno\Runtime\MainWorker` is created. Throws an exception if any flag is not recognized by V8.
+     *
+     * @param string[] $flags
+     */
+    function set_v8_flags(array $flags): mixed {}
+
+    class JsException extends \Exception {
+        const NOT_FOUND = 1000;
+
+        const PERMISSION_DENIED = 1001;
+
+        const CONNECTION_REFUSED = 1002;
+
+        const CONNECTION_RESET = 1003;
+
+        const CONNECTION_ABORTED = 1004;
+
+        const NOT_CONNECTED = 1005;
+
+        const ADDR_IN_USE = 1006;
+
+        const ADDR_NOT_AVAILABLE = 1007;
+
+        const BROKEN_PIPE = 1008;
+
+        const ALREADY_EXISTS = 1009;
+
+        const INVALID_DATA = 1010;
+
+        const TIMED_OUT = 1011;
+
+        const INTERRUPTED = 1012;
+
+        const WRITE_ZERO = 1013;
+
+        const UNEXPECTED_EOF = 1014;
+
+        const BAD_RESOURCE = 1015;
+
+        const BUSY = 1016;
+
+        const NOT_SUPPORTED = 1017;
+
+        const HTTP = 1018;
+
+        /**
+         * The JavaScript stack frames, the first being where the exception was thrown.
+         * @var \Deno\Core\JsStackFrame[]
+         */
+        public $frames;
+
+        /**
+         * The errors of an `AggregateError`, such as the rejections of `Promise.any()`.
+         * @var \Deno\Core\JsException[]
+         */
+        public $errors;
+
+        public function __construct() {}
+
+        /**
+         * Get the column number where the exception was thrown.
+         */
+        public function getColumn(): int {}
+    }
+
+    /**
+     * The JsRuntime is a wrapper around a V8 isolate. It can execute ES6 including ES6 modules. The JsRuntime
+     * does not include any of the Deno.core.* ops, and does not provide implementations for web apis, such as
+     * fetch(). Use JsRuntime if you want to provide low-level v8 isolates, and implement extensions for all
+     * functionality such as local storage, remote requests etc.
+     */
+    class JsRuntime {
+        public function __construct(\Deno\Core\RuntimeOptions $options) {}
+
+        /**
+         * Discard all of the runtime's JavaScript state, such as globals, loaded modules and pending
+         * timers, by replacing its isolate with a fresh one created from the same `RuntimeOptions`, for
+         * example to isolate requests from one another. The extensions are initialized again, and ops
+         * registered with `register_op()` are registered in the fresh isolate. Values and resources in
+         * the `OpState`, source maps added with `add_source_map()` and op metrics are discarded.
+         *
+         * deno_core 0.149 can't give a runtime a new global context, so this costs about as much as
+         * creating a new `JsRuntime`; it saves setting up the options and ops again in PHP.
+         */
+        public function reset(): mixed {}
+
+        /**
+         * Execute JavaSscript inside the V8 Isolate.
+         *
+         * This does not support top level await for Es6 imports. use `execute_module`
+         * to execute JavaScript in modules.
+         *
+         * When `timeout_ms` is given, the script is terminated once it has run for that many
+         * milliseconds and a `Deno\Core\JsException` is thrown. The runtime can still be used
+         * after a timeout, however any global state the script modified before it was terminated
+         * is left as-is, so consider discarding the runtime when running untrusted code.
+         *
+         * `name` is the file name used for the script in stack traces, so use a URL such as
+         * `file:///app/index.js` for the frames of a `Deno\Core\JsException` to point at a resolvable
+         * file. If the source code has an inline source map, such as transpiled code with
+         * `EmitOptions::inline_source_map` set, the frames are mapped back to the original source.
+         * Use `add_source_map()` for source maps that are not inline.
+         */
+        public function execute_script(string $name, string $source_code, ?int $timeout_ms = null): mixed {}
+
+        /**
+         * Execute a script like `execute_script()`, but return its completion value (the value of
+         * the last evaluated expression statement) converted to a PHP value, rather than converted to
+         * a string. This is useful to use the runtime as an expression evaluator, for example
+         * `evaluate('calc.js', '1 + 2')` returns the int 3. A returned Promise is not awaited, use
+         * `execute_repl()` for that. Modules have no completion value, use `get_default_export()`
+         * or `execute_module()` to get a value from a module.
+         *
+         * @return mixed
+         */
+        public function evaluate(string $name, string $source_code, ?int $timeout_ms = null): mixed {}
+
+        /**
+         * Load an ES6 module as the main starting module.
+         *
+         * This function returns a module ID which should be passed to `mod_evaluate()`.
+         *
+         * @return int
+         */
+        public function load_main_module(string $specifier, ?string $code): int {}
+
+        /**
+         * Evaluate a given module ID. This will run all schyonous code in the module.
+         * If there are pending Promises or async axtions, use `run_event_loop()` to
+         * wait until all async actions complete.
+         */
+        public function mod_evaluate(int $id): mixed {}
+
+        /**
+         * Get the default export of a module that has been evaluated with `mod_evaluate()`, converted
+         * to a PHP value, or null if it has no default export. A function is returned as a
+         * `Deno\Core\JsFunction`, so a module can be loaded once and its default export called many
+         * times with `call_function()`.
+         *
+         * @return mixed
+         */
+        public function get_default_export(int $id): mixed {}
+
+        /**
+         * Wait for the event loop to run all pending async actions.
+         *
+         * When `timeout_ms` is given, the event loop is stopped once it has run for that many
+         * milliseconds, for example to cap the background work of a request when a `setInterval()`
+         * would otherwise keep it running forever. Returns true when all pending async actions have
+         * completed, or false when the timeout was reached while some were still pending. Call
+         * `run_event_loop()` again to continue them. The timeout is checked between tasks, so use
+         * the timeout of `execute_script()` to stop a single long running script.
+         *
+         * @return bool
+         */
+        public function run_event_loop(?int $timeout_ms = null): mixed {}
+
+        /**
+         * Whether the event loop has pending work, such as timers, unresolved async ops or a `fetch()`
+         * that has not completed, so a scheduler can decide whether to call `run_event_loop()` again.
+         * This polls the event loop once without waiting, so any work that is ready runs first.
+         */
+        public function has_pending_work(): bool {}
+
+        /**
+         * Takes a snapshot. The isolate should have been created with will_snapshot set to true.
+         *
+         * Taking a snapshot consumes the isolate, so any method that runs code or accesses the isolate
+         * throws an exception afterwards. Create a new JsRuntime with the snapshot as
+         * `RuntimeOptions::startup_snapshot` to continue running code.
+         *
+         * @return string
+         */
+        public function snapshot(): mixed {}
+
+        /**
+         * Takes a snapshot and writes it to a file, without copying it in to a PHP string. The
+         * isolate should have been created with will_snapshot set to true.
+         */
+        public function snapshot_to_file(string $path): mixed {}
+
+        /**
+         * Get the V8 heap statistics for the isolate, such as `used_heap_size` and `heap_size_limit`.
+         * All sizes are in bytes.
+         *
+         * @return array<string, int>
+         */
+        public function get_heap_statistics(): array {}
+
+        /**
+         * Run a full garbage collection, for example between requests to keep memory usage flat.
+         * This sends V8 a low memory notification, which collects all garbage it can, so it does not
+         * need V8 to be started with `--expose-gc`.
+         */
+        public function request_garbage_collection(): mixed {}
+
+        /**
+         * Whether the isolate's execution is being terminated, such as by a timeout or the heap limit,
+         * and the termination has not been cancelled yet.
+         */
+        public function is_terminating(): bool {}
+
+        /**
+         * Whether the runtime is still safe to use, for example for a pool of runtimes to decide
+         * whether to discard a runtime and create a new one (or `reset()` it) after an error. A runtime
+         * is not healthy once it has been snapshotted, while its execution is being terminated, or after
+         * it has exceeded `RuntimeOptions::heap_limit_mb`, as the heap limit is raised to let V8 unwind
+         * the terminated script, so the limit no longer applies. Scripts terminated by a timeout leave
+         * the runtime healthy, although any global state they modified is left as-is.
+         */
+        public function is_healthy(): bool {}
+
+        /**
+         * Get the names of the ops that are available to JavaScript in `Deno.core.ops`, sorted. This
+         * includes the ops of the extensions, ops registered with `register_op()` and deno_core's
+         * built-in ops, which helps to find mismatches between op names in JavaScript and PHP.
+         *
+         * @return string[]
+         */
+        public function get_op_names(): array {}
+
+        /**
+         * Add the source map for a script or module, such as `TranspiledSource::source_map` when the
+         * code was transpiled without an inline source map. `file_name` is the script name passed
+         * to `execute_script()`, or the module specifier. Stack frames in the script are then mapped
+         * back to the original source in a `Deno\Core\JsException`.
+         */
+        public function add_source_map(string $file_name, string $source_map): mixed {}
+
+        /**
+         * Invalidate a loaded module, so the next import of `specifier` calls the module loader again
+         * and evaluates the module's new source, such as when a development server sees the module's
+         * file change. Modules that already imported it keep the instance they were linked to, so
+         * re-import the module with a dynamic `import()`, or invalidate its importers as well. The
+         * module's own imports are only loaded again if they are invalidated too.
+         *
+         * deno_core can't remove a module from its module map, so the module is loaded again under
+         * its specifier with a `#reload=N` fragment, which shows in `import.meta.url` and stack traces.
+         * The module loader is called with the specifier without the fragment.
+         */
+        public function invalidate_module(string $specifier): mixed {}
+
+        /**
+         * Execute source code as an ES module, returning its default export converted to a PHP value,
+         * or null if it has no default export. Unlike `execute_script()`, the source may use `import`
+         * and top level `await`, and the event loop is run until the module has finished evaluating.
+         *
+         * The specifier must be a URL, which imports are resolved relative to. Modules are only
+         * evaluated once, so use a different specifier for each call.
+         *
+         * @return mixed
+         */
+        public function execute_module(string $specifier, string $source_code): mixed {}
+
+        /**
+         * Evaluate a line of REPL input, returning the value of the last expression statement.
+         *
+         * The input may be TypeScript and may contain `import` statements, which are transpiled
+         * to dynamic imports (see `EmitOptions::var_decl_imports`) and loaded via the runtime's
+         * module loader. Top level declarations are made global so they are visible to later
+         * inputs, and may be redeclared by later inputs as they can in the Deno REPL.
+         *
+         * @return mixed
+         */
+        public function execute_repl(string $source_code): mixed {}
+
+        /**
+         * Call a global JavaScript function with the given arguments, returning its return value
+         * converted to a PHP value. If the function returns a Promise, the event loop is run until
+         * the Promise settles.
+         *
+         * @param mixed[] $args
+         * @return mixed
+         */
+        public function call_global_function(string $name, array $args): mixed {}
+
+        /**
+         * Call a `Deno\Core\JsFunction` that was returned by this runtime with the given arguments,
+         * returning its return value converted to a PHP value. If the function returns a Promise, the
+         * event loop is run until the Promise settles.
+         *
+         * @param mixed[] $args
+         * @return mixed
+         */
+        public function call_function(\Deno\Core\JsFunction $function, array $args): mixed {}
+
+        /**
+         * Set a global variable in the runtime, such as request data for a script to use. The PHP value
+         * is converted to a JavaScript value. Arrays that are lists become JavaScript arrays, and other
+         * arrays become objects, which order integer keys before string keys.
+         */
+        public function set_global(string $name, mixed $value): mixed {}
+
+        /**
+         * Get a global variable from the runtime, converted to a PHP value. Returns null if the global
+         * is not set. Objects with a null prototype, such as those created from PHP arrays by
+         * `set_global()`, are converted to arrays, and other objects are converted to objects.
+         *
+         * @return mixed
+         */
+        public function get_global(string $name): mixed {}
+
+        /**
+         * Serialize a global variable with V8's structured clone algorithm (the algorithm used by
+         * `postMessage()`), returning it as a binary string. Unlike `get_global()`, this preserves
+         * types such as Map, Set, Date and typed arrays, as well as cyclic references. Pass the result
+         * to `deserialize_value()` of this or another runtime to restore the value.
+         *
+         * @return string
+         */
+        public function serialize_value(string $name): string {}
+
+        /**
+         * Deserialize a value serialized with `serialize_value()` and set it as the global variable `name`.
+         *
+         * @param string $data
+         */
+        public function deserialize_value(string $name, string $data): mixed {}
+
+        /**
+         * Get the number of calls to each op and the total time spent in its PHP function, keyed by
+         * op name. Requires `RuntimeOptions::op_metrics` to be enabled. The time of async ops is the
+         * time spent in their PHP function, not the time until their Promise settled.
+         *
+         * @return array<string, array{calls: int, total_time_ms: float}>
+         */
+        public function get_op_metrics(): array {}
+
+        /**
+         * Register an op after the runtime has been constructed, for example when a feature that needs
+         * it is enabled. The op is added to `Deno.core.ops` in the same way as the ops of an Extension.
+         *
+         * JavaScript that has already copied the functions from `Deno.core.ops` (such as an Extension's
+         * `js_files`) will not see the new op, so any bindings for it have to be exposed again, for
+         * example with `execute_script()`.
+         *
+         * @param callable $callback
+         */
+        public function register_op(string $name, callable $callback, ?bool $is_async = null): mixed {}
+
+        /**
+         * Register a sync op whose return value is discarded, like the `void_ops` of an Extension,
+         * after the runtime has been constructed. See `register_op()`.
+         *
+         * @param callable $callback
+         */
+        public function register_void_op(string $name, callable $callback): mixed {}
+
+        /**
+         * Get the runtime's op state, which is shared by all ops of the runtime. This can be used to
+         * provide values to ops before any JavaScript is executed.
+         *
+         * @return \Deno\Core\OpState
+         */
+        public function op_state(): \Deno\Core\OpState {}
+    }
+
+    /**
+     * OpState is the per-runtime state that is shared between all ops of a JsRuntime. Ops can use it to
+     * store values and resources (such as an open database connection) in one op call and retrieve
+     * them in another. Use `OpState::current()` inside an op to get the state of the calling runtime,
+     * or `JsRuntime::op_state()` to get it from outside of an op.
+     */
+    class OpState {
+        /**
+         * Get the op state of the runtime that is calling the current op. Returns null when not
+         * called from within an op.
+         *
+         * @return \Deno\Core\OpState|null
+         */
+        public static function current(): ?\Deno\Core\OpState {}
+
+        /**
+         * Get the name of the op that is currently being called, which lets one PHP function serve
+         * several ops, such as a dispatcher for all of an Extension's ops. Returns null when not called
+         * from within an op.
+         *
+         * @return string|null
+         */
+        public static function current_op_name(): ?string {}
+
+        /**
+         * Store a value in the op state.
+         */
+        public function set(string $key, mixed $value): mixed {}
+
+        /**
+         * Get a value from the op state, or null if it has not been set.
+         *
+         * @return mixed
+         */
+        public function get(string $key): mixed {}
+
+        /**
+         * Whether a value has been set in the op state.
+         */
+        public function has(string $key): bool {}
+
+        /**
+         * Remove a value from the op state.
+         */
+        public function delete(string $key): mixed {}
+
+        /**
+         * Add a value to the runtime's resource table, returning the resource ID. The resource ID can
+         * be passed to JavaScript and back to other ops to retrieve the value with `get_resource()`.
+         *
+         * @return int
+         */
+        public function add_resource(string $name, mixed $value): int {}
+
+        /**
+         * Get a value from the runtime's resource table. Throws an exception if the resource does
+         * not exist.
+         *
+         * @return mixed
+         */
+        public function get_resource(int $rid): mixed {}
+
+        /**
+         * Close a resource, removing it from the runtime's resource table. Throws an exception if
+         * the resource does not exist.
+         */
+        public function close_resource(int $rid): mixed {}
+
+        /**
+         * Check that the worker's permissions allow reading `path`, the same as Deno's built-in file
+         * system APIs. Throws `Deno\Errors\PermissionDenied` if reading is not allowed. Relative paths
+         * are resolved from the current working directory.
+         *
+         * Ops run with all of PHP's privileges, so ops that act on behalf of untrusted JavaScript
+         * should check the permissions first. Only `Deno\Runtime\MainWorker` has permissions, so the
+         * checks always fail in the ops of a `JsRuntime`.
+         *
+         * @param string|null $api_name The name of the API for the error message, such as "readFile()".
+         */
+        public function check_read(string $path, ?string $api_name = null): mixed {}
+
+        /**
+         * Check that the worker's permissions allow writing `path`. Throws
+         * `Deno\Errors\PermissionDenied` if writing is not allowed. See `check_read()`.
+         *
+         * @param string|null $api_name The name of the API for the error message, such as "writeFile()".
+         */
+        public function check_write(string $path, ?string $api_name = null): mixed {}
+
+        /**
+         * Check that the worker's permissions allow network access to `host`, optionally on `port`.
+         * Throws `Deno\Errors\PermissionDenied` if network access is not allowed. See `check_read()`.
+         *
+         * @param string|null $api_name The name of the API for the error message, such as "connect()".
+         */
+        public function check_net(string $host, ?int $port = null, ?string $api_name = null): mixed {}
+
+        /**
+         * Check that the worker's permissions allow reading the environment variable `name`. Throws
+         * `Deno\Errors\PermissionDenied` if it is not allowed. See `check_read()`.
+         */
+        public function check_env(string $name): mixed {}
+    }
+
+    /**
+     * A frame of a JavaScript stack trace, see `Deno\Core\JsException::frames`.
+     */
+    class JsStackFrame {
+        /**
+         * The file name (or URL) of the frame's script.
+         * @var string|null
+         */
+        public $file;
+
+        /**
+         * @var int|null
+         */
+        public $line;
+
+        /**
+         * @var int|null
+         */
+        public $column;
+
+        /**
+         * The function name, or null for anonymous functions and top-level code.
+         * @var string|null
+         */
+        public $function_name;
+
+        /**
+         * Whether the frame is code run via `eval()`.
+         * @var bool
+         */
+        public $is_eval;
+
+        /**
+         * Whether the frame is an async function call.
+         * @var bool
+         */
+        public $is_async;
+    }
+
+    /**
+     * Wraps a PHP string so that it is passed to JavaScript as a `Uint8Array` of its bytes, rather
+     * than a JavaScript string (which would mangle any bytes that are not valid UTF-8). Use this to
+     * return raw bytes, such as from `file_get_contents()`, from ops.
+     */
+    class Uint8Array {
+        /**
+         * @param string $bytes
+         */
+        public function __construct(string $bytes) {}
+    }
+
+    /**
+     * The memory of an `ArrayBuffer` or typed array that JavaScript transferred to an op with
+     * `Deno.core.transfer()`, which is passed to the op without copying it into a PHP string. Use
+     * `read()` to copy parts of it, for example to process a large buffer in chunks.
+     *
+     * Transferring a buffer detaches it (the whole `ArrayBuffer`, for a typed array) when the op is
+     * called, so the buffer has a length of 0 in JavaScript afterwards and must not be used again.
+     * Buffers that can not be detached, such as the memory of a WebAssembly instance, are shared
+     * instead, so JavaScript's later changes to them are visible in PHP. Returning the object from an
+     * op transfers the memory back to JavaScript as a `Uint8Array`, again without copying it.
+     */
+    class ArrayBuffer {
+        /**
+         * The length of the buffer in bytes.
+         */
+        public function length(): int {}
+
+        /**
+         * Copy `length` bytes starting at `offset` into a binary string, or the bytes up to the end of
+         * the buffer when `length` is null.
+         *
+         * @return string
+         */
+        public function read(?int $offset = null, ?int $length = null): mixed {}
+    }
+
+    /**
+     * A JavaScript string that is not valid UTF-16, as it contains lone surrogates, which can't be
+     * converted to a PHP (UTF-8) string exactly. Strings are converted to it when
+     * `RuntimeOptions::lossless_strings` is set, and it is converted back to the identical JavaScript
+     * string when passed to JavaScript.
+     */
+    class Utf16String {
+        /**
+         * Create a string from its UTF-16LE encoding, such as a string returned by `to_utf16le()`.
+         *
+         * @param string $utf16le
+         */
+        public function __construct(string $utf16le) {}
+
+        /**
+         * The number of UTF-16 code units, which is the string's `length` in JavaScript.
+         */
+        public function length(): int {}
+
+        /**
+         * Get the exact code units as a UTF-16LE binary string.
+         *
+         * @return string
+         */
+        public function to_utf16le(): mixed {}
+
+        /**
+         * Convert to a UTF-8 string. Throws an exception if the string contains lone surrogates,
+         * unless `lossy` is true, which replaces them with U+FFFD (�).
+         */
+        public function to_utf8(?bool $lossy = null): string {}
+    }
+
+    /**
+     * A JavaScript object, which is what objects are converted to in PHP, other than those with a null
+     * prototype, which become arrays. The object's own enumerable properties are converted to PHP
+     * properties of the `Deno\Core\V8Object`, so they can be read as `$object->name` or with `get()`.
+     * Properties with symbol keys are skipped, as PHP has no equivalent of them.
+     *
+     * The properties are a snapshot taken when the object is converted, so changes made in JavaScript
+     * afterwards are not visible in PHP, and `set()` only changes the PHP object. Passing the object
+     * back to JavaScript creates a new object with its current properties.
+     */
+    class V8Object {
+        /**
+         * Get the value of a property, or null if the object does not have the property.
+         *
+         * @return mixed
+         */
+        public function get(string $key): mixed {}
+
+        /**
+         * Set the value of a property, adding the property if the object does not have it.
+         *
+         * @param mixed $value
+         */
+        public function set(string $key, mixed $value): mixed {}
+
+        /**
+         * Get the names of the object's properties, in the order of the JavaScript object.
+         *
+         * @return string[]
+         */
+        public function keys(): array {}
+
+        /**
+         * Get the object's properties as an array. Properties that are objects are kept as
+         * `Deno\Core\V8Object`s, rather than being converted to arrays as well.
+         *
+         * @return array<string, mixed>
+         */
+        public function to_array(): mixed {}
+    }
+
+    /**
+     * A JavaScript function, which is what functions are converted to in PHP. It can be called with
+     * `call_function()` of the runtime that returned it, and converts back to the same function when
+     * it is passed to JavaScript, such as in the arguments of an op or of another call.
+     */
+    class JsFunction {
+        /**
+         * The name of the function, which is empty for anonymous functions.
+         */
+        public string $name;
+    }
+
+    /**
+     * A V8 startup snapshot that is loaded into memory once and shared by all the runtimes it is used
+     * for with `RuntimeOptions::startup_snapshot`, rather than being copied for each runtime. This
+     * saves copying large snapshots when many runtimes are constructed, such as one per request.
+     *
+     * The snapshot's memory is never freed, as V8 may read it for as long as the process runs, so
+     * create each snapshot once (for example in a static variable) rather than for every runtime.
+     */
+    class StaticSnapshot {
+        /**
+         * @param string $snapshot
+         */
+        public function __construct(string $snapshot) {}
+
+        /**
+         * Load the snapshot from a file, such as one written by `SnapshotBuilder::build_to_file()`.
+         */
+        public static function from_file(string $path): \Deno\Core\StaticSnapshot {}
+
+        /**
+         * The size of the snapshot in bytes.
+         */
+        public function length(): int {}
+    }
+
+    /**
+     * A store for the memory of `SharedArrayBuffer`s, which lets runtimes and workers that are given the
+     * same store share `SharedArrayBuffer`s. See `RuntimeOptions::shared_array_buffer_store` and
+     * `Deno\Runtime\WorkerOptions::shared_array_buffer_store`.
+     */
+    class SharedArrayBufferStore {
+        public function __construct() {}
+    }
+
+    /**
+     * A store for compiled WebAssembly modules, which lets runtimes and workers that are given the same
+     * store share compiled `WebAssembly.Module`s. See `RuntimeOptions::compiled_wasm_module_store` and
+     * `Deno\Runtime\WorkerOptions::compiled_wasm_module_store`.
+     */
+    class CompiledWasmModuleStore {
+        public function __construct() {}
+    }
+
+    /**
+     * Builds a V8 snapshot with extensions and setup scripts in one call, for example to bake an
+     * application's extensions and libraries into a snapshot that runtimes start from quickly. This
+     * takes care of creating a runtime with `RuntimeOptions::will_snapshot` and running the scripts.
+     *
+     * The snapshot does not contain the extensions' PHP ops, so runtimes created from it must be
+     * given the same `extensions`, in the same order. Only scripts can be snapshotted, not ES modules;
+     * use `Deno\AST\bundle()` and `Deno\AST\ParsedSource::strip_types()` to prepare them as needed.
+     */
+    class SnapshotBuilder {
+        /**
+         * The extensions to initialize before the scripts are run.
+         * @var Deno\Core\Extension[]
+         */
+        public $extensions;
+
+        /**
+         * The scripts to run, in order, before the snapshot is taken.
+         * @var Deno\Core\JsFile[]
+         */
+        public $scripts;
+
+        /**
+         * @param Deno\Core\Extension[] $extensions
+         * @param Deno\Core\JsFile[] $scripts
+         */
+        public function __construct(array $extensions, array $scripts) {}
 
         /**
-         * Should the sources be inlined in the source map.  Defaults to `true`.
-         * @var bool
+         * Build the snapshot, which can be used for `RuntimeOptions::startup_snapshot`.
+         *
+         * @return string
          */
-        public $inline_sources;
+        public function build(): mixed {}
 
-        public function __construct() {}
+        /**
+         * Build the snapshot and write it to a file, which can be loaded with
+         * `RuntimeOptions::set_startup_snapshot_from_file()`.
+         */
+        public function build_to_file(string $path): mixed {}
     }
-}
 
-namespace Deno\Core {
     /**
-     * The JsRuntime is a wrapper around a V8 isolate. It can execute ES6 including ES6 modules. The JsRuntime
-     * does not include any of the Deno.core.* ops, and does not provide implementations for web apis, such as
-     * fetch(). Use JsRuntime if you want to provide low-level v8 isolates, and implement extensions for all
-     * functionality such as local storage, remote requests etc.
+     * Runs an ES module on a dedicated OS thread, with its own isolate and event loop, so PHP can
+     * start JavaScript and poll for its completion rather than being blocked until it finishes. For
+     * example, a web server can bound the time a request spends in JavaScript with `wait()` and
+     * `terminate()`, where the other runtimes' methods block the PHP worker for as long as it runs.
+     *
+     * A V8 isolate is not `Send`: it can only be used by the thread that created it. PHP values can
+     * only be used by PHP's thread too, so the thread's runtime is entirely separate from PHP. It has
+     * no PHP ops, no PHP module loader and no access to the PHP objects of other runtimes; only
+     * `Deno.core` is available to the module, and modules can only be imported when a
+     * `Deno\Core\FsModuleLoader` is given. The module's default export is passed back to PHP as JSON,
+     * so it must be JSON serializable, and is converted to PHP values in the same way as the return
+     * values of the other runtimes.
      */
-    class JsRuntime {
-        public function __construct(\Deno\Core\RuntimeOptions $options) {}
-
+    class JsThread {
         /**
-         * Execute JavaSscript inside the V8 Isolate.
-         *
-         * This does not support top level await for Es6 imports. use `load_main_module`
-         * to execute JavaScript in modules.
+         * Start executing `source_code` as the main module `specifier` on a new thread.
          */
-        public function execute_script(string $name, string $source_code): mixed {}
+        public function __construct(string $specifier, string $source_code, ?\Deno\Core\FsModuleLoader $module_loader = null) {}
 
         /**
-         * Load an ES6 module as the main starting module.
-         *
-         * This function returns a module ID which should be passed to `mod_evaluate()`.
-         *
-         * @return int
+         * Whether the module has finished executing, including its event loop. This does not block.
          */
-        public function load_main_module(string $specifier, ?string $code): int {}
+        public function is_finished(): bool {}
 
         /**
-         * Evaluate a given module ID. This will run all schyonous code in the module.
-         * If there are pending Promises or async axtions, use `run_event_loop()` to
-         * wait until all async actions complete.
+         * Wait up to `timeout_ms` milliseconds for the module to finish executing, returning whether
+         * it has finished.
          */
-        public function mod_evaluate(int $id): mixed {}
+        public function wait(int $timeout_ms): bool {}
 
         /**
-         * Wait for the event loop to run all pending async actions.
+         * Terminate the execution of the module, whether it is running JavaScript or waiting in the
+         * event loop, after which `join()` throws a `Deno\Core\JsException`. This does nothing when
+         * the module has already finished.
          */
-        public function run_event_loop(): mixed {}
+        public function terminate(): mixed {}
 
         /**
-         * Takes a snapshot. The isolate should have been created with will_snapshot set to true.
+         * Wait for the module to finish executing, and return its default export. Throws a
+         * `Deno\Core\JsException` when the module throws or was terminated. The result can only be
+         * taken once.
          *
-         * @return string
+         * @return mixed
          */
-        public function snapshot(): mixed {}
+        public function join(): mixed {}
     }
 
     /**
@@ -218,36 +1163,56 @@ namespace Deno\Core {
      */
     class ModuleSource {
         /**
-         * The resolved module URL, after things like 301 redrects etc.
+         * The final module URL, after any redirects (such as a 301 response when fetching a remote
+         * module). When it differs from `module_url_specified`, the module is registered under this URL,
+         * so `import.meta.url` is this URL and the module's relative imports are resolved against it:
+         * `resolve()` is called with it as the referrer. Later imports of either URL share the module.
          * @var string
          */
         public $module_url_found;
 
         /**
-         * The module type, can be "javascript" or "json".
+         * The module type, can be "javascript" or "json". JSON media types, such as "application/json",
+         * are also JSON modules, and any other type is JavaScript.
+         *
+         * The type is checked against the import's assertion in the same way as Deno: JSON modules can
+         * only be imported with `assert { type: "json" }`, and JavaScript modules only without it.
          * @var string
          */
         public $module_type;
 
         /**
-         * The module's source code.
-         * @var string
+         * The module's source code. Text, such as JavaScript and JSON, is given as a UTF-8 string.
+         * Binary code is given as a `Deno\Core\Uint8Array`, and passed to the runtime unchanged.
+         * @var string|\Deno\Core\Uint8Array
          */
         public $code;
 
         /**
-         * The specified module URL of the import.
+         * The specified module URL of the import, which is the specifier that was passed to `load()`.
          * @var string
          */
         public $module_url_specified;
 
-        public function __construct(string $code, string $module_type, string $module_url_specified, string $module_url_found) {}
+        /**
+         * @param string|\Deno\Core\Uint8Array $code
+         */
+        public function __construct(string|\Deno\Core\Uint8Array $code, string $module_type, string $module_url_specified, string $module_url_found) {}
     }
 
     /**
      * The module loader interface (don't trust the docs, this is an interface not a class!)
      * Pass an instance of your class that implements `Deno\Core\ModuleLoader` to the `module_loader`
      * property of `Deno\Runtime\WorkerOptions` or `Deno\Core\RuntimeOptions`
+     *
+     * `data:` URL modules, such as `data:application/javascript,export default 1`, are resolved and
+     * loaded without calling the module loader.
+     *
+     * Module loaders that transpile modules can also define a `get_source_map(string $specifier): ?string`
+     * method, which is not part of the interface so existing module loaders keep working. It is
+     * called with the specifier of a module when a stack trace includes one of its frames, and should
+     * return the module's source map, so the frames point at the original source (such as a `.ts` file)
+     * instead of the transpiled code.
      */
     class ModuleLoader {
         /**
@@ -259,11 +1224,40 @@ namespace Deno\Core {
         /**
          * The `load` method takes a module specifier and should return the contents for a module.
          * See `Deno\Core\ModuleSource` for the specifics.
+         *
+         * `load` is also passed whether the module is loaded by a dynamic `import()` as a second
+         * argument, which implementations can accept as `bool $is_dyn_import = false`. To refuse to
+         * load a module, such as one a security policy disallows, throw an exception: its message is
+         * the error of the import, so a refused `import()` rejects with it.
          * @return \Deno\Core\ModuleSource
          */
         public function load(string $_specifier): ?\Deno\Core\ModuleSource {}
     }
 
+    /**
+     * A module loader that loads modules from the filesystem, without the need to implement
+     * `Deno\Core\ModuleLoader` in PHP. Pass an instance to the `module_loader` property of
+     * `Deno\Runtime\WorkerOptions` or `Deno\Core\RuntimeOptions`.
+     *
+     * Relative specifiers are resolved against the importing module, or against `base_dir` for the
+     * main module. The module type is inferred from the file extension: `.json` files are loaded as
+     * JSON modules, TypeScript and JSX files (`.ts`, `.tsx`, `.jsx` etc.) are transpiled, and all
+     * other files are loaded as JavaScript.
+     */
+    class FsModuleLoader {
+        /**
+         * Only modules within `base_dir` can be loaded, or when `allow_read` is given, only modules
+         * within those files and directories, in the same way as
+         * `Deno\Runtime\PermissionsOptions::allow_read`. When used by a `Deno\Runtime\MainWorker`,
+         * dynamic imports also require the worker's `allow_read` permission, as in Deno. When
+         * `transpile_cache` is given, TypeScript and JSX modules are transpiled with it, so they are
+         * only transpiled again when they change.
+         *
+         * @param string[]|null $allow_read
+         */
+        public function __construct(string $base_dir, ?array $allow_read = null, ?\Deno\AST\TranspileCache $transpile_cache = null) {}
+    }
+
     /**
      * Extension contains PHP functions (ops) and associated js files which are
      * exposed to JavaScript via the JsRuntime. PHP functions can be called from JavaScript
@@ -271,21 +1265,93 @@ namespace Deno\Core {
      *
      * It's common to provide `ops` and also more user-friendly accessible functions for those
      * `ops` via the `js_files` property.
+     *
+     * Extensions are initialized in the order they are given in the `extensions` array of the runtime
+     * options, so an extension's `js_files` can rely on the extensions before it.
      */
     class Extension {
         /**
-         * The ops for the extension (bridged to PHP functions)
+         * The name of the extension. Names must be unique within a runtime. The extension's `js_files`
+         * are loaded as `ext:$name/$filename`, so errors thrown by them identify the extension.
+         * @var string
+         */
+        public $name;
+
+        /**
+         * The ops for the extension (bridged to PHP functions). The return value of the PHP function is
+         * converted to a JavaScript value, where a list (an array with the keys 0, 1, 2...) becomes a
+         * JavaScript array, so ops can return several values as `[ $a, $b ]` to be destructured with
+         * `const [ a, b ] = Deno.core.ops.op_name()`.
          * @var array<string, callable>
          */
         public $ops;
 
+        /**
+         * The async ops for the extension (bridged to PHP functions). Calling an async op from
+         * JavaScript returns a Promise, which is resolved with the return value of the PHP function
+         * (or rejected if it throws).
+         *
+         * PHP functions can't run in parallel with JavaScript, so the PHP function is not called
+         * straight away: it is queued and called on the isolate's thread once the currently running
+         * JavaScript yields to the event loop (e.g. via `await`). Arguments are converted to PHP
+         * values at the time of the call from JavaScript.
+         *
+         * Async ops can also be called with `await Deno.core.opAsync("op_name", ...args)`, the
+         * calling convention of Deno's own async ops, so code ported from Deno ops works unchanged.
+         * @var array<string, callable>
+         */
+        public $async_ops;
+
+        /**
+         * Sync ops whose return value is discarded, for high-frequency fire-and-forget ops such as
+         * logging and metrics. The return value of the PHP function is not converted to a JavaScript
+         * value, and calling the op from JavaScript always returns `undefined`.
+         * @var array<string, callable>
+         */
+        public $void_ops;
+
+        /**
+         * Ops that stream their output to JavaScript in chunks, such as the contents of a large file.
+         * The PHP function returns an `Iterator` (usually a Generator that `yield`s each chunk) or an
+         * array of chunks, and calling the op from JavaScript returns a `ReadableStream` of the chunks.
+         * Each chunk is only produced by PHP when JavaScript reads it, so a slow consumer applies
+         * backpressure rather than PHP building the whole output in memory. Canceling the stream
+         * destroys the iterator, so the Generator's `finally` blocks run.
+         *
+         * String chunks are converted to `Uint8Array`s of their bytes, and other chunks are converted
+         * like the return values of `ops`. The bare `Deno\Core\JsRuntime` has no `ReadableStream`, so
+         * it returns an async iterator instead, which can be read with `for await` in the same way
+         * as a `ReadableStream`.
+         * @var array<string, callable>
+         */
+        public $stream_ops;
+
         /**
          * The JS files that should be loaded into the V8 Isolate.
          * @var Deno\Core\JsFile[]
          */
         public $js_files;
 
-        public function __construct() {}
+        public function __construct(?string $name = null) {}
+
+        /**
+         * Build an extension from an associative array, rather than setting its properties one by
+         * one. The keys are the names of the properties, with `js` as a shorthand for `js_files`.
+         * The JS files can be given as `JsFile`s, or as the code keyed by its filename:
+         *
+         * ```php
+         * $extension = Deno\Core\Extension::from_array( [
+         *     'name' => 'greeter',
+         *     'ops'  => [ 'op_greet' => fn( $name ) => "Hello $name" ],
+         *     'js'   => [ 'greeter.js' => 'globalThis.greet = Deno.core.ops.op_greet;' ],
+         * ] );
+         * ```
+         *
+         * Throws if a key is unknown, or if a value is not of the expected type.
+         *
+         * @param array $spec
+         */
+        public static function from_array(array $spec): \Deno\Core\Extension {}
     }
 
     /**
@@ -297,7 +1363,7 @@ namespace Deno\Core {
         /**
          * Prepare runtime to take snapshot of loaded code. The snapshot is determinstic and uses predictable random numbers.
          *
-         * Currently can’t be used with startup_snapshot.
+         * Can’t be used with startup_snapshot, the JsRuntime constructor throws an exception if both are set.
          * @var bool
          */
         public $will_snapshot;
@@ -305,15 +1371,21 @@ namespace Deno\Core {
         /**
          * The module loader accepts a callable which is responsible for loading
          * ES6 modules from a given name. See `Deno\Core\ModuleLoader` for methods that should be implemented.
-         * @var Deno\Core\ModuleLoader
+         * Use `Deno\Core\FsModuleLoader` to load modules from the filesystem.
+         * @var Deno\Core\ModuleLoader|Deno\Core\FsModuleLoader
          */
         public $module_loader;
 
         /**
-         * V8 snapshot that should be loaded on startup.
+         * V8 snapshot that should be loaded on startup: either the binary string returned by
+         * `JsRuntime::snapshot()` or `SnapshotBuilder::build()` (not base64 encoded), the path of a file
+         * containing such a snapshot, or a `Deno\Core\StaticSnapshot`. The constructor throws an exception
+         * if the value isn’t snapshot data created by this version of the extension. A string is copied
+         * for each runtime that is constructed, so use a `Deno\Core\StaticSnapshot` to share one snapshot
+         * between many runtimes.
          *
-         * Currently can’t be used with will_snapshot.
-         * @var string
+         * Can’t be used with will_snapshot, the JsRuntime constructor throws an exception if both are set.
+         * @var string|\Deno\Core\StaticSnapshot
          */
         public $startup_snapshot;
 
@@ -324,7 +1396,148 @@ namespace Deno\Core {
          */
         public $extensions;
 
+        /**
+         * The maximum size of the V8 heap in megabytes. When the limit is reached, the running
+         * script is terminated and a `Deno\Core\JsException` is thrown. The runtime should be
+         * discarded after the heap limit has been exceeded.
+         * @var int|null
+         */
+        public $heap_limit_mb;
+
+        /**
+         * A callable that receives all `console` calls from JavaScript, replacing the runtime's
+         * `console`. The callable is called with the log level (one of "log", "info", "warn", "error"
+         * or "debug") and an array of the arguments passed to the console method.
+         * @var callable|null
+         */
+        public $console;
+
+        /**
+         * A callable that receives the output of `Deno.core.print()`, instead of it being written to
+         * the process's stdout or stderr, where PHP's output buffering can not capture it. The callable
+         * is called with the message and whether it was printed to stderr, for example to echo it or
+         * to collect it in a buffer.
+         * @var callable|null
+         */
+        public $print;
+
+        /**
+         * Record the number of calls to each op and the time spent in their PHP functions, which can
+         * be retrieved with `JsRuntime::get_op_metrics()`. Defaults to `false`, as recording adds a
+         * little overhead to every op call.
+         * @var bool
+         */
+        public $op_metrics;
+
+        /**
+         * A callable that maps exceptions thrown by ops to the class of the JavaScript error they are
+         * thrown as, so JavaScript can check for them with `instanceof`, in the same way as deno_core's
+         * `get_error_class_fn` does for Rust errors. The callable is called with the exception and
+         * should return a class name, such as "TypeError", "RangeError" or one of the `Deno.errors`
+         * classes (such as "NotFound") of a MainWorker, or null for the default. Other class names
+         * create an `Error` with the class name as its `name`.
+         *
+         * By default, a `Deno\Core\JsException` (such as one thrown by a nested call into JavaScript)
+         * is thrown as its original JavaScript error class, and other exceptions as an `Error`.
+         * @var callable|null
+         */
+        public $get_error_class;
+
+        /**
+         * The maximum depth of nested calls between PHP and JavaScript, where each op call into PHP and
+         * each `call_function()` or `call_global_function()` into JavaScript is one level. Ops can call
+         * PHP functions that call back into JavaScript, so deeply reentrant code could otherwise
+         * exhaust the stack and crash the process. A call that exceeds the limit throws an error
+         * instead. Defaults to `null`, which does not limit the depth.
+         * @var int|null
+         */
+        public $max_call_depth;
+
+        /**
+         * Convert JavaScript strings exactly. JavaScript strings are sequences of UTF-16 code units
+         * that may contain lone surrogates, which can't be represented in UTF-8, so they are replaced
+         * with U+FFFD (�) when strings are converted to PHP. When true, strings with lone surrogates
+         * are converted to a `Deno\Core\Utf16String` instead, which holds the exact code units and is
+         * converted back to the identical JavaScript string. Other strings are converted to PHP strings
+         * as usual, and object keys are always converted to PHP strings. Defaults to `false`.
+         * @var bool
+         */
+        public $lossless_strings;
+
+        /**
+         * A callable that is called with a `Deno\Core\JsException` for each unhandled Promise
+         * rejection, with the message and stack of the rejection value, for example to log them.
+         * Without it, an unhandled rejection stops the event loop and is thrown from
+         * `run_event_loop()` (or `mod_evaluate()` etc.) as a `Deno\Core\JsException`. With it, the
+         * event loop continues running the remaining async actions. Defaults to `null`.
+         * @var callable|null
+         */
+        public $on_unhandled_rejection;
+
+        /**
+         * Allow high resolution time from `performance.now()`. When false, the time is coarsened
+         * to 2ms in the same way as Deno without the `--allow-hrtime` permission, which makes timing
+         * attacks harder for untrusted code. Defaults to `false`.
+         * @var bool
+         */
+        public $allow_hrtime;
+
+        /**
+         * A callable that formats JavaScript errors, such as uncaught exceptions and Promise rejections,
+         * for example to match an application's logging format. The callable is called with the
+         * `Deno\Core\JsException` and should return the message for the exception that is thrown to PHP.
+         * When not set, or when the callable throws or doesn't return a string, the message is the
+         * JavaScript error's message.
+         * @var callable|null
+         */
+        public $format_js_error;
+
+        /**
+         * The store for the memory of `SharedArrayBuffer`s serialized with `Deno.core.serialize()`. When
+         * runtimes are given the same store, a `SharedArrayBuffer` serialized in one can be deserialized
+         * with `Deno.core.deserialize()` in another, and both share its memory. Without a store,
+         * `SharedArrayBuffer`s can not be serialized.
+         * @var \Deno\Core\SharedArrayBufferStore|null
+         */
+        public $shared_array_buffer_store;
+
+        /**
+         * The store for WebAssembly modules serialized with `Deno.core.serialize()`. When runtimes are
+         * given the same store, a compiled `WebAssembly.Module` serialized in one can be deserialized
+         * with `Deno.core.deserialize()` in another without compiling it again. Without a store,
+         * `WebAssembly.Module`s can not be serialized.
+         * @var \Deno\Core\CompiledWasmModuleStore|null
+         */
+        public $compiled_wasm_module_store;
+
         public function __construct() {}
+
+        /**
+         * Load the V8 startup snapshot from a file, such as one written by `JsRuntime::snapshot()`.
+         * This avoids reading the snapshot into a PHP string first, and replaces any `startup_snapshot`
+         * that has been set.
+         */
+        public function set_startup_snapshot_from_file(string $path): mixed {}
+    }
+}
+
+namespace Deno\Errors {
+    /**
+     * Thrown for Deno `NotFound` errors, such as reading a file that does not exist.
+     */
+    class NotFound extends \Deno\Core\JsException {
+    }
+
+    /**
+     * Thrown when an operation is not allowed by the runtime's permissions.
+     */
+    class PermissionDenied extends \Deno\Core\JsException {
+    }
+
+    /**
+     * Thrown for JavaScript `TypeError`s.
+     */
+    class TypeError extends \Deno\Core\JsException {
     }
 }
 
@@ -340,15 +1553,123 @@ namespace Deno\Runtime {
 
         public function execute_main_module(): mixed {}
 
-        public function run_event_loop(): mixed {}
+        /**
+         * Execute the main module and then run the event loop until all pending timers, Promises
+         * and other async actions have completed, which is how the `deno run` command runs a program.
+         * This is equivalent to calling `execute_main_module()` and then `run_event_loop()`.
+         */
+        public function run(): mixed {}
+
+        /**
+         * Wait for the event loop to run all pending async actions, or until `timeout_ms` has passed.
+         * See `Deno\Core\JsRuntime::run_event_loop()`.
+         *
+         * @return bool
+         */
+        public function run_event_loop(?int $timeout_ms = null): mixed {}
+
+        /**
+         * Whether the event loop has pending work. See `Deno\Core\JsRuntime::has_pending_work()`.
+         */
+        public function has_pending_work(): bool {}
 
         /**
          * Execute JavaSscript inside the V8 Isolate.
          *
-         * This does not support top level await for Es6 imports. use `load_main_module`
+         * This does not support top level await for Es6 imports. use `execute_module`
          * to execute JavaScript in modules.
+         *
+         * See `Deno\Core\JsRuntime::execute_script()` for how `name` and source maps are used in
+         * stack traces.
          */
         public function execute_script(string $name, string $source_code): mixed {}
+
+        /**
+         * Execute a script, returning its completion value converted to a PHP value. See
+         * `Deno\Core\JsRuntime::evaluate()`.
+         *
+         * @return mixed
+         */
+        public function evaluate(string $name, string $source_code, ?int $timeout_ms = null): mixed {}
+
+        /**
+         * Add the source map for a script or module. See `Deno\Core\JsRuntime::add_source_map()`.
+         */
+        public function add_source_map(string $file_name, string $source_map): mixed {}
+
+        /**
+         * Invalidate a loaded module, so it is loaded again the next time it is imported. See
+         * `Deno\Core\JsRuntime::invalidate_module()`.
+         */
+        public function invalidate_module(string $specifier): mixed {}
+
+        /**
+         * Run the tests registered with `Deno.test()`, such as by a test module executed with
+         * `execute_main_module()` or `execute_module()`, and return their results. This requires
+         * `BootstrapOptions::enable_testing_features`.
+         *
+         * Tests run one after another, in the order they were registered, and the event loop is run
+         * until each test has finished. The options `ignore` and `only` are supported, as is running
+         * steps with `t.step()`, where a failing step fails its test. The `status` of each test is
+         * one of "passed", "failed" or "ignored", and `error` is the stack of the error of a failed test.
+         *
+         * @return array<int, array{name: string, status: string, duration_ms: float, error: string|null}>
+         */
+        public function run_tests(): array {}
+
+        /**
+         * Execute source code as an ES module. See `Deno\Core\JsRuntime::execute_module()`.
+         *
+         * @return mixed
+         */
+        public function execute_module(string $specifier, string $source_code): mixed {}
+
+        /**
+         * Evaluate a line of REPL input. See `Deno\Core\JsRuntime::execute_repl()`.
+         *
+         * @return mixed
+         */
+        public function execute_repl(string $source_code): mixed {}
+
+        /**
+         * Call a global JavaScript function with the given arguments, returning its return value
+         * converted to a PHP value. If the function returns a Promise, the event loop is run until
+         * the Promise settles.
+         *
+         * @param mixed[] $args
+         * @return mixed
+         */
+        public function call_global_function(string $name, array $args): mixed {}
+
+        /**
+         * Call a `Deno\Core\JsFunction` that was returned by this worker. See
+         * `Deno\Core\JsRuntime::call_function()`.
+         *
+         * @param mixed[] $args
+         * @return mixed
+         */
+        public function call_function(\Deno\Core\JsFunction $function, array $args): mixed {}
+
+        /**
+         * Get the V8 heap statistics for the worker's isolate, such as `used_heap_size` and `heap_size_limit`.
+         * All sizes are in bytes.
+         *
+         * @return array<string, int>
+         */
+        public function get_heap_statistics(): array {}
+
+        /**
+         * Run a full garbage collection. See `Deno\Core\JsRuntime::request_garbage_collection()`.
+         */
+        public function request_garbage_collection(): mixed {}
+
+        /**
+         * Get the names of the ops that are available to JavaScript in `Deno.core.ops`, sorted. See
+         * `Deno\Core\JsRuntime::get_op_names()`.
+         *
+         * @return string[]
+         */
+        public function get_op_names(): array {}
     }
 
     /**
@@ -373,11 +1694,153 @@ namespace Deno\Runtime {
         /**
          * The module loader accepts a callable which is responsible for loading
          * ES6 modules from a given name. See `Deno\Core\ModuleLoader` for methods that should be implemented.
+         * Use `Deno\Core\FsModuleLoader` to load modules from the filesystem.
          *
-         * @var Deno\Core\ModuleLoader
+         * @var Deno\Core\ModuleLoader|Deno\Core\FsModuleLoader
          */
         public $module_loader;
 
+        /**
+         * A callable that receives all `console` calls from JavaScript instead of them being written to
+         * stdout. The callable is called with the log level (one of "log", "info", "warn", "error" or
+         * "debug") and an array of the arguments passed to the console method.
+         *
+         * @var callable|null
+         */
+        public $console;
+
+        /**
+         * A callable that handles all `fetch()` requests from JavaScript instead of Deno's own HTTP client,
+         * for example to send them through the application's HTTP client and proxy configuration.
+         *
+         * The callable is called with the request URL, the method, a list of `[name, value]` header pairs
+         * and the request body (or null), and should return an array with the response `status`, `headers`
+         * (an array of header names to values) and `body`. The response is buffered, not streamed.
+         *
+         * @var callable|null
+         */
+        public $fetch;
+
+        /**
+         * The environment variables seen by `Deno.env`, instead of the process environment. Set to
+         * `true` to use PHP's `getenv()` and `putenv()`, so JavaScript sees the same environment as
+         * PHP (including variables set by the web server), or to an array of variable names to values,
+         * which `Deno.env.set()` and `Deno.env.delete()` only change for the worker. Access still
+         * requires the `allow_env` permission, as with Deno's own `Deno.env`.
+         *
+         * @var array<string, string>|bool|null
+         */
+        public $env;
+
+        /**
+         * The working directory for the worker, which `Deno.cwd()` returns and relative paths (including
+         * the main module) are resolved against. Defaults to PHP's working directory.
+         *
+         * As the working directory is per-process, it is changed while the worker runs JavaScript and
+         * changed back afterwards, so PHP code (such as op callbacks) called from JavaScript also sees
+         * the worker's working directory. Other threads of the process see it too, so `cwd` must not be
+         * used with threaded SAPIs (ZTS builds, such as FrankenPHP or Apache's worker MPM), where
+         * concurrent requests would run in the worker's working directory.
+         *
+         * @var string|null
+         */
+        public $cwd;
+
+        /**
+         * Additional CA certificates to trust, as PEM encoded strings, such as the contents of an
+         * application's CA bundle. These are trusted in addition to the default (Mozilla) root
+         * certificates by Deno's TLS connections, including `fetch()` and `WebSocket`.
+         *
+         * @var string[]
+         */
+        public $ca_certificates;
+
+        /**
+         * Hosts for which TLS certificate errors are ignored by `fetch()` and `WebSocket`, or an empty
+         * array to ignore them for all hosts, the same as Deno's `--unsafely-ignore-certificate-errors`.
+         * This should only be used for development, such as with self-signed certificates.
+         *
+         * @var string[]|null
+         */
+        public $unsafely_ignore_certificate_errors;
+
+        /**
+         * The store for the memory of `SharedArrayBuffer`s serialized with `Deno.core.serialize()`. When
+         * workers are given the same store, a `SharedArrayBuffer` serialized in one can be deserialized
+         * with `Deno.core.deserialize()` in another, and both share its memory. Without a store,
+         * `SharedArrayBuffer`s can not be serialized.
+         *
+         * @var \Deno\Core\SharedArrayBufferStore|null
+         */
+        public $shared_array_buffer_store;
+
+        /**
+         * The store for WebAssembly modules serialized with `Deno.core.serialize()`. When workers are
+         * given the same store, a compiled `WebAssembly.Module` serialized in one can be deserialized
+         * with `Deno.core.deserialize()` in another without compiling it again. Without a store,
+         * `WebAssembly.Module`s can not be serialized.
+         *
+         * @var \Deno\Core\CompiledWasmModuleStore|null
+         */
+        public $compiled_wasm_module_store;
+
+        /**
+         * A callable that formats JavaScript errors, such as uncaught exceptions and Promise rejections,
+         * for example to match an application's logging format. The callable is called with the
+         * `Deno\Core\JsException` and should return the message for the exception that is thrown to PHP.
+         * When not set, or when the callable throws or doesn't return a string, the message is the
+         * JavaScript error's message.
+         *
+         * @var callable|null
+         */
+        public $format_js_error;
+
+        /**
+         * Allow importing Node's built-in modules with `node:` specifiers, such as
+         * `import { Buffer } from "node:buffer"`, for libraries written for Node. Only `node:buffer`,
+         * `node:events` and `node:path` are supported, with partial stand-ins for their commonly used
+         * APIs that are built in to the extension, rather than Deno's std/node polyfills, so other APIs
+         * are missing. Other `node:` specifiers can not be imported. Defaults to `false`.
+         *
+         * @var bool
+         */
+        public $node_builtins;
+
+        /**
+         * The maximum depth of nested calls between PHP and JavaScript. See
+         * `Deno\Core\RuntimeOptions::max_call_depth`. Defaults to `null`, which does not limit the depth.
+         *
+         * @var int|null
+         */
+        public $max_call_depth;
+
+        /**
+         * A callable that maps exceptions thrown by ops to the class of the JavaScript error they are
+         * thrown as. See `Deno\Core\RuntimeOptions::get_error_class`.
+         *
+         * @var callable|null
+         */
+        public $get_error_class;
+
+        /**
+         * Convert JavaScript strings that are not valid UTF-16 to `Deno\Core\Utf16String` rather
+         * than replacing their lone surrogates. See `Deno\Core\RuntimeOptions::lossless_strings`.
+         * Defaults to `false`.
+         *
+         * @var bool
+         */
+        public $lossless_strings;
+
+        /**
+         * A callable that is called with a `Deno\Core\JsException` for each unhandled Promise
+         * rejection, rather than stopping the event loop. See
+         * `Deno\Core\RuntimeOptions::on_unhandled_rejection`. Rejections that are prevented by an
+         * `unhandledrejection` event listener are not passed to it.
+         *
+         * @var callable|null
+         */
+        public $on_unhandled_rejection;
+
         public function __construct(\Deno\Runtime\BootstrapOptions $bootstrap, array $extensions, mixed $module_loader) {}
     }
 
@@ -393,6 +1856,9 @@ namespace Deno\Runtime {
         public $runtime_version;
 
         /**
+         * Enables Deno's testing features, and replaces `Deno.test()` with one that registers tests to
+         * be run with `Deno\Runtime\MainWorker::run_tests()`.
+         *
          * @param bool
          */
         public $enable_testing_features;
@@ -407,6 +1873,15 @@ namespace Deno\Runtime {
          */
         public $args;
 
+        /**
+         * Sets `globalThis.__config` in JS runtime to the value converted to JavaScript, in the same
+         * way as `Deno\Core\JsRuntime::set_global()`. Use it to pass structured parameters, such as
+         * request data, to scripts rather than parsing `Deno.args`. The global is not set when null.
+         *
+         * @param mixed
+         */
+        public $config;
+
         /**
          * @param int
          */
@@ -11,6 +11,12 @@ use std::collections::HashMap;
 struct MainWorker {
     deno_main_worker: deno_runtime::worker::MainWorker,
     main_module: deno_core::ModuleSpecifier,
+    tokio_runtime: std::rc::Rc<tokio::runtime::Runtime>,
+    source_maps: SourceMaps,
+    invalidated_modules: InvalidatedModules,
+    format_js_error: Option<CloneableZval>,
+    cwd: Option<std::path::PathBuf>,
+    termination_reason: std::sync::Arc<std::sync::Mutex<Option<String>>>,
 }
 
 fn get_error_class_name(e: &deno_core::error::AnyError) -> &'static str {
@@ -25,59 +31,204 @@ impl MainWorker {
         permissions: &PermissionsOptions,
         options: &WorkerOptions,
     ) -> PhpResult<Self> {
-        let main_module = deno_core::resolve_path(main_module).unwrap();
+        let cwd = match &options.cwd {
+            Some(cwd) => match std::fs::canonicalize(cwd) {
+                Ok(cwd) if cwd.is_dir() => Some(cwd),
+                Ok(_) => {
+                    return Err(format!("Working directory {} is not a directory.", cwd).into())
+                }
+                Err(error) => {
+                    return Err(
+                        format!("Unable to resolve working directory {}: {}", cwd, error).into(),
+                    )
+                }
+            },
+            None => None,
+        };
+        // The main module is resolved relative to the working directory, and bootstrapping runs
+        // JavaScript, so the worker's working directory is used for the rest of the constructor.
+        let _cwd = CwdGuard::enter(&cwd)?;
+
+        let main_module = match deno_core::resolve_path(main_module) {
+            Ok(main_module) => main_module,
+            Err(error) => {
+                return Err(format!("Unable to resolve main module {}: {}", main_module, error).into())
+            }
+        };
         let permissions =
             match deno_runtime::permissions::Permissions::from_options(&permissions.into()) {
                 Ok(p) => p,
-                Err(_) => return Err("Unable to parse permissions.".into()),
+                Err(error) => return Err(format!("Unable to parse permissions: {}", error).into()),
             };
 
-        let worker = deno_runtime::worker::MainWorker::bootstrap_from_options(
+        let env = options.env()?;
+        V8_INITIALIZED.store(true, std::sync::atomic::Ordering::SeqCst);
+        let source_maps = SourceMaps::new(Some(options.module_loader.clone()));
+        let mut worker_options: deno_runtime::worker::WorkerOptions = options.into();
+        worker_options.root_cert_store = options.root_cert_store()?;
+        worker_options.source_map_getter = Some(Box::new(source_maps.clone()));
+        let worker_op_state = WorkerOpState::default();
+        let invalidated_modules = InvalidatedModules::default();
+        worker_options.module_loader =
+            invalidated_modules.loader(options.module_loader(&worker_op_state));
+        let has_env = env.is_some();
+        if let Some(env) = env {
+            worker_options.extensions.push(env_extension(env));
+        }
+        let mut worker = deno_runtime::worker::MainWorker::bootstrap_from_options(
             main_module.clone(),
             permissions,
-            options.into(),
+            worker_options,
         );
+        worker_op_state.set(worker.js_runtime.op_state());
+
+        // The bindings have to be installed after bootstrapping, as bootstrapping installs Deno's
+        // own `console`, `fetch` etc.
+        if let Err(error) = init_ops(
+            &mut worker.js_runtime,
+            &options.extensions,
+            &options.php_bindings(),
+        ) {
+            return Err(error.to_string().into());
+        }
+        if let Some(get_error_class) = &options.get_error_class {
+            worker
+                .js_runtime
+                .v8_isolate()
+                .set_slot(GetErrorClass(get_error_class.clone()));
+        }
+        if let Some(max_call_depth) = options.max_call_depth {
+            worker
+                .js_runtime
+                .v8_isolate()
+                .set_slot(CallDepth::new(max_call_depth));
+        }
+        if options.lossless_strings {
+            worker.js_runtime.v8_isolate().set_slot(LosslessStrings);
+        }
+        if let Some(on_unhandled_rejection) = &options.on_unhandled_rejection {
+            worker
+                .js_runtime
+                .v8_isolate()
+                .set_slot(OnUnhandledRejection(on_unhandled_rejection.clone()));
+        }
+        if let Some(config) = &options.bootstrap.config {
+            if let Err(error) = set_global(&mut worker.js_runtime, "__config", &config.0) {
+                return Err(error.to_string().into());
+            }
+        }
+        if has_env {
+            let result = worker
+                .js_runtime
+                .execute_script("ext:php_env/env.js", ENV_JS);
+            if let Err(error) = result {
+                return Err(error.to_string().into());
+            }
+        }
+        if options.bootstrap.enable_testing_features {
+            let result = worker
+                .js_runtime
+                .execute_script("ext:php_testing/testing.js", TESTING_JS);
+            if let Err(error) = result {
+                return Err(error.to_string().into());
+            }
+        }
+
         Ok(Self {
             deno_main_worker: worker,
             main_module: main_module,
+            tokio_runtime: new_tokio_runtime(),
+            source_maps,
+            invalidated_modules,
+            format_js_error: options.format_js_error.clone(),
+            cwd,
+            termination_reason: Default::default(),
         })
     }
 
     pub fn execute_main_module(&mut self) -> PhpResult<()> {
-        // todo switch all to use tokio
-        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        let rt = self.tokio_runtime.clone();
         let local = tokio::task::LocalSet::new();
-        local.block_on(&mut rt, async {
+        local.block_on(&rt, async {
             match self
                 .deno_main_worker
                 .execute_main_module(&self.main_module)
                 .await
             {
                 Ok(()) => Ok(()),
-                Err(error) => return Err(error.to_string().into()),
+                Err(error) => return Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Wait for the event loop to run all pending async actions, or until `timeout_ms` has passed.
+    /// See `Deno\Core\JsRuntime::run_event_loop()`.
+    ///
+    /// @return bool
+    #[optional(timeout_ms)]
+    fn run_event_loop(&mut self, timeout_ms: Option<u64>) -> PhpResult<bool> {
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            let event_loop = run_event_loop(&mut self.deno_main_worker.js_runtime);
+            match run_event_loop_with_timeout(event_loop, timeout_ms).await {
+                Ok(completed) => Ok(completed),
+                Err(error) => return Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Whether the event loop has pending work. See `Deno\Core\JsRuntime::has_pending_work()`.
+    fn has_pending_work(&mut self) -> PhpResult<bool> {
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match has_pending_work(&mut self.deno_main_worker.js_runtime).await {
+                Ok(has_pending_work) => Ok(has_pending_work),
+                Err(error) => Err(self.php_exception(error)),
             }
         })
     }
 
-    fn run_event_loop(&mut self) -> PhpResult<()> {
-        let mut rt = tokio::runtime::Runtime::new().unwrap();
+    /// Execute the main module and then run the event loop until all pending timers, Promises
+    /// and other async actions have completed, which is how the `deno run` command runs a program.
+    /// This is equivalent to calling `execute_main_module()` and then `run_event_loop()`.
+    fn run(&mut self) -> PhpResult<()> {
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        let rt = self.tokio_runtime.clone();
         let local = tokio::task::LocalSet::new();
-        local.block_on(&mut rt, async {
-            match self.deno_main_worker.run_event_loop(false).await {
+        local.block_on(&rt, async {
+            match self
+                .deno_main_worker
+                .execute_main_module(&self.main_module)
+                .await
+            {
+                Ok(()) => (),
+                Err(error) => return Err(self.php_exception(error)),
+            };
+            match run_event_loop(&mut self.deno_main_worker.js_runtime).await {
                 Ok(()) => Ok(()),
-                Err(error) => return Err(error.to_string().into()),
+                Err(error) => Err(self.php_exception(error)),
             }
         })
     }
 
     /// Execute JavaSscript inside the V8 Isolate.
     ///
-    /// This does not support top level await for Es6 imports. use `load_main_module`
+    /// This does not support top level await for Es6 imports. use `execute_module`
     /// to execute JavaScript in modules.
+    ///
+    /// See `Deno\Core\JsRuntime::execute_script()` for how `name` and source maps are used in
+    /// stack traces.
     fn execute_script(&mut self, name: &str, source_code: &str) -> PhpResult<String> {
-        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        self.source_maps.add_inline(name, source_code);
+        let rt = self.tokio_runtime.clone();
         let local = tokio::task::LocalSet::new();
-        local.block_on(&mut rt, async {
+        local.block_on(&rt, async {
             match self.deno_main_worker.js_runtime.execute_script(name, source_code) {
                 Ok(return_value) => {
                     let mut scope = self.deno_main_worker.js_runtime.handle_scope();
@@ -88,15 +239,222 @@ impl MainWorker {
                         .to_rust_string_lossy(&mut scope);
                     Ok(String::from(value_str))
                 },
-                Err(error) => match error.downcast::<deno_core::error::JsError>() {
-                    Ok(error) => {
-                        Err(JsException::from(error).into())
-                    },
-                    Err(error) => Err(error.to_string().into()),
-                },
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Execute a script, returning its completion value converted to a PHP value. See
+    /// `Deno\Core\JsRuntime::evaluate()`.
+    ///
+    /// @return mixed
+    #[optional(timeout_ms)]
+    fn evaluate(
+        &mut self,
+        name: &str,
+        source_code: &str,
+        timeout_ms: Option<u64>,
+    ) -> PhpResult<Zval> {
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        self.source_maps.add_inline(name, source_code);
+        let termination_reason = self.termination_reason.clone();
+        with_timeout(
+            self,
+            |worker| &mut worker.deno_main_worker.js_runtime,
+            &termination_reason,
+            timeout_ms,
+            |worker| match evaluate(&mut worker.deno_main_worker.js_runtime, name, source_code) {
+                Ok(completion_value) => Ok(completion_value),
+                Err(error) => Err(worker.php_exception(error)),
+            },
+        )
+    }
+
+    /// Call a global JavaScript function with the given arguments, returning its return value
+    /// converted to a PHP value. If the function returns a Promise, the event loop is run until
+    /// the Promise settles.
+    ///
+    /// @param mixed[] $args
+    /// @return mixed
+    fn call_global_function(&mut self, name: &str, args: Vec<CloneableZval>) -> PhpResult<Zval> {
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match call_global_function(&mut self.deno_main_worker.js_runtime, name, &args).await {
+                Ok(return_value) => Ok(return_value),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Call a `Deno\Core\JsFunction` that was returned by this worker. See
+    /// `Deno\Core\JsRuntime::call_function()`.
+    ///
+    /// @param mixed[] $args
+    /// @return mixed
+    fn call_function(
+        &mut self,
+        function: &JsFunction,
+        args: Vec<CloneableZval>,
+    ) -> PhpResult<Zval> {
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        let function = function.function_in(self.deno_main_worker.js_runtime.v8_isolate())?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match call_function(&mut self.deno_main_worker.js_runtime, function, &args).await {
+                Ok(return_value) => Ok(return_value),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Execute source code as an ES module. See `Deno\Core\JsRuntime::execute_module()`.
+    ///
+    /// @return mixed
+    fn execute_module(&mut self, specifier: &str, source_code: &str) -> PhpResult<Zval> {
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        self.source_maps.add_inline(specifier, source_code);
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            let js_runtime = &mut self.deno_main_worker.js_runtime;
+            match execute_module(js_runtime, specifier, source_code).await {
+                Ok(return_value) => Ok(return_value),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Evaluate a line of REPL input. See `Deno\Core\JsRuntime::execute_repl()`.
+    ///
+    /// @return mixed
+    fn execute_repl(&mut self, source_code: &str) -> PhpResult<Zval> {
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        let script = repl_script(source_code)?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match execute_repl(&mut self.deno_main_worker.js_runtime, &script).await {
+                Ok(return_value) => Ok(return_value),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Add the source map for a script or module. See `Deno\Core\JsRuntime::add_source_map()`.
+    fn add_source_map(&mut self, file_name: &str, source_map: &str) {
+        self.source_maps
+            .add(file_name, source_map.as_bytes().to_vec());
+    }
+
+    /// Invalidate a loaded module, so it is loaded again the next time it is imported. See
+    /// `Deno\Core\JsRuntime::invalidate_module()`.
+    fn invalidate_module(&mut self, specifier: &str) -> PhpResult<()> {
+        match self.invalidated_modules.invalidate(specifier) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.to_string().into()),
+        }
+    }
+
+    /// Run the tests registered with `Deno.test()`, such as by a test module executed with
+    /// `execute_main_module()` or `execute_module()`, and return their results. This requires
+    /// `BootstrapOptions::enable_testing_features`.
+    ///
+    /// Tests run one after another, in the order they were registered, and the event loop is run
+    /// until each test has finished. The options `ignore` and `only` are supported, as is running
+    /// steps with `t.step()`, where a failing step fails its test. The `status` of each test is
+    /// one of "passed", "failed" or "ignored", and `error` is the stack of the error of a failed test.
+    ///
+    /// @return array<int, array{name: string, status: string, duration_ms: float, error: string|null}>
+    fn run_tests(&mut self) -> PhpResult<Zval> {
+        let _cwd = CwdGuard::enter(&self.cwd)?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match run_tests(&mut self.deno_main_worker.js_runtime).await {
+                Ok(results) => Ok(results),
+                Err(error) => Err(self.php_exception(error)),
             }
         })
     }
+
+    /// Get the V8 heap statistics for the worker's isolate, such as `used_heap_size` and `heap_size_limit`.
+    /// All sizes are in bytes.
+    ///
+    /// @return array<string, int>
+    fn get_heap_statistics(&mut self) -> HashMap<&'static str, usize> {
+        get_heap_statistics(self.deno_main_worker.js_runtime.v8_isolate())
+    }
+
+    /// Run a full garbage collection. See `Deno\Core\JsRuntime::request_garbage_collection()`.
+    fn request_garbage_collection(&mut self) {
+        self.deno_main_worker
+            .js_runtime
+            .v8_isolate()
+            .low_memory_notification();
+    }
+
+    /// Get the names of the ops that are available to JavaScript in `Deno.core.ops`, sorted. See
+    /// `Deno\Core\JsRuntime::get_op_names()`.
+    ///
+    /// @return string[]
+    fn get_op_names(&mut self) -> PhpResult<Vec<String>> {
+        match op_names(&mut self.deno_main_worker.js_runtime) {
+            Ok(names) => Ok(names),
+            Err(error) => Err(self.php_exception(error)),
+        }
+    }
+}
+
+/// Changes the process's working directory to a worker's `cwd` while it runs JavaScript, and
+/// changes it back to PHP's working directory when dropped. This is process-wide, so it is not
+/// safe with threaded SAPIs, see `WorkerOptions::cwd`.
+struct CwdGuard(Option<std::path::PathBuf>);
+
+impl CwdGuard {
+    fn enter(cwd: &Option<std::path::PathBuf>) -> PhpResult<Self> {
+        let cwd = match cwd {
+            Some(cwd) => cwd,
+            None => return Ok(Self(None)),
+        };
+        let previous_cwd = match std::env::current_dir() {
+            Ok(previous_cwd) => previous_cwd,
+            Err(error) => return Err(format!("Unable to get working directory: {}", error).into()),
+        };
+        if let Err(error) = std::env::set_current_dir(cwd) {
+            return Err(format!(
+                "Unable to change working directory to {}: {}",
+                cwd.display(),
+                error
+            )
+            .into());
+        }
+        Ok(Self(Some(previous_cwd)))
+    }
+}
+
+impl Drop for CwdGuard {
+    fn drop(&mut self) {
+        if let Some(previous_cwd) = self.0.take() {
+            std::env::set_current_dir(previous_cwd).ok();
+        }
+    }
+}
+
+impl MainWorker {
+    /// Convert an error returned from the worker into a PHP exception.
+    fn php_exception(&self, error: Error) -> PhpException {
+        if let Some(message) = self.termination_reason.lock().unwrap().take() {
+            return JsException {
+                message,
+                ..Default::default()
+            }
+            .into();
+        }
+        php_exception_from_error(error, self.format_js_error.as_ref())
+    }
 }
 
 #[php_class(name = "Deno\\Core\\JsException")]
@@ -112,7 +470,21 @@ pub struct JsException {
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Protected)]
     line: i64,
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Protected)]
+    column: i64,
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Protected)]
     trace: Vec<String>,
+    /// The JavaScript stack frames, the first being where the exception was thrown.
+    /// @var \Deno\Core\JsStackFrame[]
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    frames: Vec<JsStackFrame>,
+    /// The errors of an `AggregateError`, such as the rejections of `Promise.any()`.
+    /// @var \Deno\Core\JsException[]
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    errors: Vec<JsException>,
+    /// The JavaScript / Deno error class, such as "TypeError" or "NotFound".
+    class_name: String,
+    /// The error's `cause`, which becomes the exception's previous exception.
+    cause: Option<Box<JsException>>,
 }
 
 impl From<JsException> for PhpException {
@@ -120,48 +492,317 @@ impl From<JsException> for PhpException {
         use ext_php_rs::class::RegisteredClass;
         let code = js_exception.code.clone();
         let message = js_exception.message.clone();
-        let zval = js_exception.into_zval(true).unwrap();
+        let zval = js_exception.into_exception_zval();
         let mut php_exception = PhpException::new( message, code, JsException::get_metadata().ce() );
         php_exception.set_object(Some(zval.into()));
         php_exception
     }
 }
 
+impl JsException {
+    /// Convert to the PHP exception object, with the error's `cause` chained as the previous
+    /// exception, so `getPrevious()` walks the chain of causes.
+    fn into_exception_zval(self) -> Zval {
+        let cause = self.cause.clone();
+        let mut zval = match JsException::error_class_entry(&self.class_name) {
+            Some(class_entry) => self
+                .into_error_object(class_entry)
+                .into_zval(false)
+                .unwrap(),
+            None => self.into_zval(true).unwrap(),
+        };
+        if let (Some(cause), Some(object)) = (cause, zval.object_mut()) {
+            // `previous` is private to `Exception`, so it is written in the scope of `Exception`, in
+            // the same way as PHP's own `zend_update_property()`.
+            let exception_class_entry =
+                ext_php_rs::zend::ce::exception() as *const ext_php_rs::zend::ClassEntry as *mut _;
+            let fake_scope = std::mem::replace(
+                &mut ext_php_rs::zend::ExecutorGlobals::get_mut().fake_scope,
+                exception_class_entry,
+            );
+            object
+                .set_property("previous", cause.into_exception_zval())
+                .ok();
+            ext_php_rs::zend::ExecutorGlobals::get_mut().fake_scope = fake_scope;
+        }
+        zval
+    }
+
+    /// Create the exception as an instance of one of the `Deno\Errors\*` subclasses. The subclasses
+    /// add no state of their own, so the object is allocated as a JsException with the subclass's
+    /// class entry, which keeps the inherited methods and properties backed by the JsException.
+    fn into_error_object(
+        self,
+        class_entry: &'static ext_php_rs::zend::ClassEntry,
+    ) -> ext_php_rs::boxed::ZBox<ext_php_rs::types::ZendClassObject<JsException>> {
+        use ext_php_rs::class::RegisteredClass;
+        let ce = class_entry as *const ext_php_rs::zend::ClassEntry as *mut _;
+        unsafe {
+            let object = ext_php_rs::ffi::ext_php_rs_zend_object_alloc(
+                std::mem::size_of::<ext_php_rs::types::ZendClassObject<JsException>>() as _,
+                ce,
+            ) as *mut ext_php_rs::types::ZendClassObject<JsException>;
+            let object = object
+                .as_mut()
+                .expect("Failed to allocate the exception object");
+            ext_php_rs::ffi::zend_object_std_init(&mut object.std, ce);
+            ext_php_rs::ffi::object_properties_init(&mut object.std, ce);
+            std::ptr::write(&mut object.obj, Some(self));
+            object.std.handlers = JsException::get_metadata().handlers();
+            ext_php_rs::boxed::ZBox::from_raw(object)
+        }
+    }
+}
+
+/// Convert an error returned from deno_core into a PHP exception. JavaScript errors become a
+/// `Deno\Core\JsException`, and errors with a known Deno error class (such as `NotFound`) become
+/// the matching `Deno\Errors\*` subclass of it.
+///
+/// When a `format_js_error` callable is given, it is called with the `Deno\Core\JsException` for
+/// JavaScript errors and its return value is used as the exception message.
+fn php_exception_from_error(error: Error, format_js_error: Option<&CloneableZval>) -> PhpException {
+    let class_name = get_error_class_name(&error);
+    match error.downcast::<deno_core::error::JsError>() {
+        Ok(error) => {
+            let mut js_exception = JsException::from(error);
+            if let Some(format_js_error) = format_js_error {
+                let result = format_js_error.0.try_call(vec![&js_exception.clone()]);
+                // When the callable fails, the error is thrown with its own message instead.
+                if let Some(message) = result.ok().as_ref().and_then(|message| message.string()) {
+                    js_exception.message = message;
+                }
+            }
+            js_exception.into()
+        }
+        Err(error) => {
+            let os_error = error
+                .downcast_ref::<std::io::Error>()
+                .and_then(|error| error.raw_os_error());
+            let code =
+                os_error.unwrap_or_else(|| JsException::error_code(class_name, &error.to_string()));
+            if JsException::error_class_entry(class_name).is_none() {
+                // The alternate format includes the context of the error, such as which module
+                // failed to load.
+                return PhpException::new(
+                    format!("{:#}", error),
+                    code,
+                    ext_php_rs::zend::ce::exception(),
+                );
+            }
+            JsException {
+                message: format!("{:#}", error),
+                code,
+                class_name: class_name.to_string(),
+                ..Default::default()
+            }
+            .into()
+        }
+    }
+}
+
+/// Thrown for Deno `NotFound` errors, such as reading a file that does not exist.
+#[php_class(name = "Deno\\Errors\\NotFound")]
+#[extends(<JsException as ext_php_rs::class::RegisteredClass>::get_metadata().ce())]
+pub struct NotFound {}
+
+/// Thrown when an operation is not allowed by the runtime's permissions.
+#[php_class(name = "Deno\\Errors\\PermissionDenied")]
+#[extends(<JsException as ext_php_rs::class::RegisteredClass>::get_metadata().ce())]
+pub struct PermissionDenied {}
+
+/// Thrown for JavaScript `TypeError`s.
+#[php_class(name = "Deno\\Errors\\TypeError")]
+#[extends(<JsException as ext_php_rs::class::RegisteredClass>::get_metadata().ce())]
+pub struct TypeError {}
+
 impl From<deno_core::error::JsError> for JsException {
     fn from(error: deno_core::error::JsError) -> Self {
         let source = match error.frames.get(0) {
-            Some(frame) => (frame.file_name.clone().unwrap_or("unknown".to_string()),frame.line_number.unwrap_or(0)),
-            None => ("unknown".to_string(),0)
+            Some(frame) => (frame.file_name.clone().unwrap_or("unknown".to_string()),frame.line_number.unwrap_or(0),frame.column_number.unwrap_or(0)),
+            None => ("unknown".to_string(),0,0)
         };
 
+        let frames = error.frames.iter().map(|frame| frame.into()).collect::<Vec<JsStackFrame>>();
+        let errors = error
+            .aggregated
+            .unwrap_or_default()
+            .into_iter()
+            .map(JsException::from)
+            .collect();
+        let cause = error.cause.map(|cause| Box::new(JsException::from(*cause)));
+
         let stack = error.frames.into_iter().map( |frame| {
-            format!("{}:{}", frame.file_name.unwrap_or("unknown".to_string()), frame.line_number.unwrap_or(0) )
+            format!("{}:{}:{}", frame.file_name.unwrap_or("unknown".to_string()), frame.line_number.unwrap_or(0), frame.column_number.unwrap_or(0) )
 
         } ).collect::<Vec<String>>();
 
+        let class_name = error.name.clone().unwrap_or_default();
+        let message = error.message.unwrap_or("Unknown JavaScript error.".to_string());
         JsException {
-            message: error.message.unwrap_or("Unknown JavaScript error.".to_string()),
-            code: 0,
+            code: JsException::error_code(&class_name, &message),
+            class_name,
+            message,
             file: source.0,
             line: source.1,
+            column: source.2,
             trace: stack,
+            frames,
+            errors,
+            cause,
         }
     }
 }
 
 #[php_impl]
 impl JsException {
+    /// Exception codes for Deno error classes. System errors use the OS errno instead, so these
+    /// start at 1000 to not overlap with errno values.
+    const NOT_FOUND: i32 = 1000;
+    const PERMISSION_DENIED: i32 = 1001;
+    const CONNECTION_REFUSED: i32 = 1002;
+    const CONNECTION_RESET: i32 = 1003;
+    const CONNECTION_ABORTED: i32 = 1004;
+    const NOT_CONNECTED: i32 = 1005;
+    const ADDR_IN_USE: i32 = 1006;
+    const ADDR_NOT_AVAILABLE: i32 = 1007;
+    const BROKEN_PIPE: i32 = 1008;
+    const ALREADY_EXISTS: i32 = 1009;
+    const INVALID_DATA: i32 = 1010;
+    const TIMED_OUT: i32 = 1011;
+    const INTERRUPTED: i32 = 1012;
+    const WRITE_ZERO: i32 = 1013;
+    const UNEXPECTED_EOF: i32 = 1014;
+    const BAD_RESOURCE: i32 = 1015;
+    const BUSY: i32 = 1016;
+    const NOT_SUPPORTED: i32 = 1017;
+    const HTTP: i32 = 1018;
+
     fn __construct() -> Self {
         Self {
             message: "JSError happened".to_owned(),
             code: 0,
             file: "".to_owned(),
             line: 0,
+            column: 0,
             trace: vec![],
+            frames: vec![],
+            errors: vec![],
+            class_name: "".to_owned(),
+            cause: None,
+        }
+    }
+
+    /// Get the column number where the exception was thrown.
+    fn get_column(&self) -> i64 {
+        self.column
+    }
+}
+
+impl JsException {
+    /// The `Deno\Errors\*` class for a Deno error class name, if there is one.
+    fn error_class_entry(class_name: &str) -> Option<&'static ext_php_rs::zend::ClassEntry> {
+        use ext_php_rs::class::RegisteredClass;
+        match class_name {
+            "NotFound" => Some(NotFound::get_metadata().ce()),
+            "PermissionDenied" => Some(PermissionDenied::get_metadata().ce()),
+            "TypeError" => Some(TypeError::get_metadata().ce()),
+            _ => None,
+        }
+    }
+
+    /// The exception code for an error. Errors caused by a system call carry the OS errno in their
+    /// message, such as "No such file or directory (os error 2)", which is used when present.
+    /// Otherwise Deno error classes map to the class's constant, and any other error is `0`.
+    fn error_code(class_name: &str, message: &str) -> i32 {
+        let os_error = message
+            .split_once("(os error ")
+            .and_then(|(_, rest)| rest.split_once(')'))
+            .and_then(|(errno, _)| errno.parse::<i32>().ok());
+        if let Some(errno) = os_error {
+            return errno;
+        }
+        match class_name {
+            "NotFound" => Self::NOT_FOUND,
+            "PermissionDenied" => Self::PERMISSION_DENIED,
+            "ConnectionRefused" => Self::CONNECTION_REFUSED,
+            "ConnectionReset" => Self::CONNECTION_RESET,
+            "ConnectionAborted" => Self::CONNECTION_ABORTED,
+            "NotConnected" => Self::NOT_CONNECTED,
+            "AddrInUse" => Self::ADDR_IN_USE,
+            "AddrNotAvailable" => Self::ADDR_NOT_AVAILABLE,
+            "BrokenPipe" => Self::BROKEN_PIPE,
+            "AlreadyExists" => Self::ALREADY_EXISTS,
+            "InvalidData" => Self::INVALID_DATA,
+            "TimedOut" => Self::TIMED_OUT,
+            "Interrupted" => Self::INTERRUPTED,
+            "WriteZero" => Self::WRITE_ZERO,
+            "UnexpectedEof" => Self::UNEXPECTED_EOF,
+            "BadResource" => Self::BAD_RESOURCE,
+            "Busy" => Self::BUSY,
+            "NotSupported" => Self::NOT_SUPPORTED,
+            "Http" => Self::HTTP,
+            _ => 0,
+        }
+    }
+}
+
+/// A frame of a JavaScript stack trace, see `Deno\Core\JsException::frames`.
+#[php_class(name = "Deno\\Core\\JsStackFrame")]
+#[derive(Default, Clone, Debug)]
+pub struct JsStackFrame {
+    /// The file name (or URL) of the frame's script.
+    /// @var string|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    file: Option<String>,
+    /// @var int|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    line: Option<i64>,
+    /// @var int|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    column: Option<i64>,
+    /// The function name, or null for anonymous functions and top-level code.
+    /// @var string|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    function_name: Option<String>,
+    /// Whether the frame is code run via `eval()`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    is_eval: bool,
+    /// Whether the frame is an async function call.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    is_async: bool,
+}
+
+impl From<&deno_core::error::JsStackFrame> for JsStackFrame {
+    fn from(frame: &deno_core::error::JsStackFrame) -> Self {
+        Self {
+            file: frame.file_name.clone(),
+            line: frame.line_number,
+            column: frame.column_number,
+            function_name: frame.function_name.clone(),
+            is_eval: frame.is_eval,
+            is_async: frame.is_async,
         }
     }
 }
 
+impl FromZval<'_> for JsException {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn from_zval(zval: &'_ Zval) -> Option<Self> {
+        let exception: &JsException = zval.extract()?;
+        Some(exception.to_owned())
+    }
+}
+
+impl FromZval<'_> for JsStackFrame {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn from_zval(zval: &'_ Zval) -> Option<Self> {
+        let frame: &JsStackFrame = zval.extract()?;
+        Some(frame.to_owned())
+    }
+}
+
 /// The options to provide to Deno\Runtime\MainWorker.
 #[php_class(name = "Deno\\Runtime\\WorkerOptions")]
 #[derive(Debug)]
@@ -179,10 +820,124 @@ struct WorkerOptions {
     extensions: Vec<Extension>,
     /// The module loader accepts a callable which is responsible for loading
     /// ES6 modules from a given name. See `Deno\Core\ModuleLoader` for methods that should be implemented.
+    /// Use `Deno\Core\FsModuleLoader` to load modules from the filesystem.
     ///
-    /// @var Deno\Core\ModuleLoader
+    /// @var Deno\Core\ModuleLoader|Deno\Core\FsModuleLoader
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
     module_loader: CloneableZval,
+    /// A callable that receives all `console` calls from JavaScript instead of them being written to
+    /// stdout. The callable is called with the log level (one of "log", "info", "warn", "error" or
+    /// "debug") and an array of the arguments passed to the console method.
+    ///
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    console: Option<CloneableZval>,
+    /// A callable that handles all `fetch()` requests from JavaScript instead of Deno's own HTTP client,
+    /// for example to send them through the application's HTTP client and proxy configuration.
+    ///
+    /// The callable is called with the request URL, the method, a list of `[name, value]` header pairs
+    /// and the request body (or null), and should return an array with the response `status`, `headers`
+    /// (an array of header names to values) and `body`. The response is buffered, not streamed.
+    ///
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    fetch: Option<CloneableZval>,
+    /// The environment variables seen by `Deno.env`, instead of the process environment. Set to
+    /// `true` to use PHP's `getenv()` and `putenv()`, so JavaScript sees the same environment as
+    /// PHP (including variables set by the web server), or to an array of variable names to values,
+    /// which `Deno.env.set()` and `Deno.env.delete()` only change for the worker. Access still
+    /// requires the `allow_env` permission, as with Deno's own `Deno.env`.
+    ///
+    /// @var array<string, string>|bool|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    env: Option<CloneableZval>,
+    /// The working directory for the worker, which `Deno.cwd()` returns and relative paths (including
+    /// the main module) are resolved against. Defaults to PHP's working directory.
+    ///
+    /// As the working directory is per-process, it is changed while the worker runs JavaScript and
+    /// changed back afterwards, so PHP code (such as op callbacks) called from JavaScript also sees
+    /// the worker's working directory. Other threads of the process see it too, so `cwd` must not be
+    /// used with threaded SAPIs (ZTS builds, such as FrankenPHP or Apache's worker MPM), where
+    /// concurrent requests would run in the worker's working directory.
+    ///
+    /// @var string|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    cwd: Option<String>,
+    /// Additional CA certificates to trust, as PEM encoded strings, such as the contents of an
+    /// application's CA bundle. These are trusted in addition to the default (Mozilla) root
+    /// certificates by Deno's TLS connections, including `fetch()` and `WebSocket`.
+    ///
+    /// @var string[]
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    ca_certificates: Vec<String>,
+    /// Hosts for which TLS certificate errors are ignored by `fetch()` and `WebSocket`, or an empty
+    /// array to ignore them for all hosts, the same as Deno's `--unsafely-ignore-certificate-errors`.
+    /// This should only be used for development, such as with self-signed certificates.
+    ///
+    /// @var string[]|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    unsafely_ignore_certificate_errors: Option<Vec<String>>,
+    /// The store for the memory of `SharedArrayBuffer`s serialized with `Deno.core.serialize()`. When
+    /// workers are given the same store, a `SharedArrayBuffer` serialized in one can be deserialized
+    /// with `Deno.core.deserialize()` in another, and both share its memory. Without a store,
+    /// `SharedArrayBuffer`s can not be serialized.
+    ///
+    /// @var \Deno\Core\SharedArrayBufferStore|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    shared_array_buffer_store: Option<SharedArrayBufferStore>,
+    /// The store for WebAssembly modules serialized with `Deno.core.serialize()`. When workers are
+    /// given the same store, a compiled `WebAssembly.Module` serialized in one can be deserialized
+    /// with `Deno.core.deserialize()` in another without compiling it again. Without a store,
+    /// `WebAssembly.Module`s can not be serialized.
+    ///
+    /// @var \Deno\Core\CompiledWasmModuleStore|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    compiled_wasm_module_store: Option<CompiledWasmModuleStore>,
+    /// A callable that formats JavaScript errors, such as uncaught exceptions and Promise rejections,
+    /// for example to match an application's logging format. The callable is called with the
+    /// `Deno\Core\JsException` and should return the message for the exception that is thrown to PHP.
+    /// When not set, or when the callable throws or doesn't return a string, the message is the
+    /// JavaScript error's message.
+    ///
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    format_js_error: Option<CloneableZval>,
+    /// Allow importing Node's built-in modules with `node:` specifiers, such as
+    /// `import { Buffer } from "node:buffer"`, for libraries written for Node. Only `node:buffer`,
+    /// `node:events` and `node:path` are supported, with partial stand-ins for their commonly used
+    /// APIs that are built in to the extension, rather than Deno's std/node polyfills, so other APIs
+    /// are missing. Other `node:` specifiers can not be imported. Defaults to `false`.
+    ///
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    node_builtins: bool,
+    /// The maximum depth of nested calls between PHP and JavaScript. See
+    /// `Deno\Core\RuntimeOptions::max_call_depth`. Defaults to `null`, which does not limit the depth.
+    ///
+    /// @var int|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    max_call_depth: Option<u32>,
+    /// A callable that maps exceptions thrown by ops to the class of the JavaScript error they are
+    /// thrown as. See `Deno\Core\RuntimeOptions::get_error_class`.
+    ///
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    get_error_class: Option<CloneableZval>,
+    /// Convert JavaScript strings that are not valid UTF-16 to `Deno\Core\Utf16String` rather
+    /// than replacing their lone surrogates. See `Deno\Core\RuntimeOptions::lossless_strings`.
+    /// Defaults to `false`.
+    ///
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    lossless_strings: bool,
+    /// A callable that is called with a `Deno\Core\JsException` for each unhandled Promise
+    /// rejection, rather than stopping the event loop. See
+    /// `Deno\Core\RuntimeOptions::on_unhandled_rejection`. Rejections that are prevented by an
+    /// `unhandledrejection` event listener are not passed to it.
+    ///
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    on_unhandled_rejection: Option<CloneableZval>,
 }
 
 #[php_impl(rename_methods = "none")]
@@ -196,6 +951,99 @@ impl WorkerOptions {
             bootstrap: bootstrap.clone(),
             extensions,
             module_loader,
+            console: None,
+            fetch: None,
+            env: None,
+            cwd: None,
+            ca_certificates: vec![],
+            unsafely_ignore_certificate_errors: None,
+            shared_array_buffer_store: None,
+            compiled_wasm_module_store: None,
+            format_js_error: None,
+            node_builtins: false,
+            max_call_depth: None,
+            get_error_class: None,
+            lossless_strings: false,
+            on_unhandled_rejection: None,
+        }
+    }
+}
+
+impl WorkerOptions {
+    fn php_bindings(&self) -> Vec<(&'static PhpBinding, CloneableZval)> {
+        let mut php_bindings = vec![];
+        if let Some(console) = &self.console {
+            php_bindings.push((&PHP_CONSOLE, console.clone()));
+        }
+        if let Some(fetch) = &self.fetch {
+            php_bindings.push((&PHP_FETCH, fetch.clone()));
+        }
+        php_bindings
+    }
+
+    fn env(&self) -> Result<Option<Env>, String> {
+        let env = match &self.env {
+            Some(env) => &env.0,
+            None => return Ok(None),
+        };
+        if env.is_null() || env.bool() == Some(false) {
+            Ok(None)
+        } else if env.bool() == Some(true) {
+            Ok(Some(Env::Php))
+        } else {
+            match env.extract::<HashMap<String, String>>() {
+                Some(vars) => Ok(Some(Env::Vars(vars))),
+                None => Err("WorkerOptions::env must be true or an array of strings.".to_string()),
+            }
+        }
+    }
+
+    /// The root certificate store with the default root certificates and `ca_certificates`.
+    fn root_cert_store(
+        &self,
+    ) -> Result<Option<deno_runtime::deno_tls::rustls::RootCertStore>, String> {
+        if self.ca_certificates.is_empty() {
+            return Ok(None);
+        }
+        let mut root_cert_store = deno_runtime::deno_tls::create_default_root_cert_store();
+        for ca_certificate in &self.ca_certificates {
+            let mut reader = std::io::BufReader::new(ca_certificate.as_bytes());
+            let certs = match deno_runtime::deno_tls::rustls_pemfile::certs(&mut reader) {
+                Ok(certs) if !certs.is_empty() => certs,
+                Ok(_) => {
+                    return Err(
+                        "No certificates found in WorkerOptions::ca_certificates.".to_string()
+                    )
+                }
+                Err(error) => return Err(format!("Unable to parse CA certificate: {}", error)),
+            };
+            let (_added, ignored) = root_cert_store.add_parsable_certificates(&certs);
+            if ignored > 0 {
+                return Err("Unable to add invalid CA certificate.".to_string());
+            }
+        }
+        Ok(Some(root_cert_store))
+    }
+}
+
+impl WorkerOptions {
+    /// The module loader for the worker, where `worker_op_state` is set to the worker's OpState
+    /// once it has been created, so a `Deno\Core\FsModuleLoader` checks the worker's permissions.
+    fn module_loader(
+        &self,
+        worker_op_state: &WorkerOpState,
+    ) -> std::rc::Rc<dyn deno_core::ModuleLoader> {
+        let module_loader: std::rc::Rc<dyn deno_core::ModuleLoader> =
+            match self.module_loader.0.extract::<&FsModuleLoader>() {
+                Some(fs_module_loader) => std::rc::Rc::new(
+                    FsLoader::new(fs_module_loader).with_worker_op_state(worker_op_state.clone()),
+                ),
+                None => module_loader_from_zval(self.module_loader.clone()),
+            };
+        if self.node_builtins {
+            std::rc::Rc::new(NodeBuiltinsLoader { module_loader })
+        } else {
+            module_loader
         }
     }
 }
@@ -209,12 +1057,20 @@ impl From<&WorkerOptions> for deno_runtime::worker::WorkerOptions {
             todo!("Web workers are not supported in the example");
         });
 
-        let module_loader: CloneableZval = options.module_loader.clone();
+        let module_loader = options.module_loader(&WorkerOpState::default());
+
+        let mut extensions: Vec<deno_core::Extension> =
+            options.extensions.iter().map(|e| e.into()).collect();
+        let php_bindings = options.php_bindings();
+        if !php_bindings.is_empty() {
+            extensions.push(php_bindings_extension(&php_bindings));
+        }
+        extensions.push(php_streams_extension());
 
         deno_runtime::worker::WorkerOptions {
             bootstrap: (&options.bootstrap).try_into().unwrap(),
-            extensions: options.extensions.iter().map(|e| e.into()).collect(),
-            unsafely_ignore_certificate_errors: None,
+            extensions,
+            unsafely_ignore_certificate_errors: options.unsafely_ignore_certificate_errors.clone(),
             root_cert_store: None,
             seed: None,
             source_map_getter: None,
@@ -224,14 +1080,20 @@ impl From<&WorkerOptions> for deno_runtime::worker::WorkerOptions {
             create_web_worker_cb,
             maybe_inspector_server: None,
             should_break_on_first_statement: false,
-            module_loader: std::rc::Rc::new(ModuleLoader::new(module_loader)),
+            module_loader,
             npm_resolver: None,
             get_error_class_fn: Some(&get_error_class_name),
             origin_storage_dir: None,
             blob_store: deno_runtime::deno_web::BlobStore::default(),
             broadcast_channel: deno_broadcast_channel::InMemoryBroadcastChannel::default(),
-            shared_array_buffer_store: None,
-            compiled_wasm_module_store: None,
+            shared_array_buffer_store: options
+                .shared_array_buffer_store
+                .as_ref()
+                .map(|store| store.store.clone()),
+            compiled_wasm_module_store: options
+                .compiled_wasm_module_store
+                .as_ref()
+                .map(|store| store.store.clone()),
             stdio: Default::default(),
         }
     }
@@ -244,12 +1106,22 @@ struct BootstrapOptions {
     /// Sets `Deno.args` in JS runtime.
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
     args: Vec<String>,
-    /// @var int
+    /// Sets `globalThis.__config` in JS runtime to the value converted to JavaScript, in the same
+    /// way as `Deno\Core\JsRuntime::set_global()`. Use it to pass structured parameters, such as
+    /// request data, to scripts rather than parsing `Deno.args`. The global is not set when null.
+    ///
+    /// @var mixed
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    config: Option<CloneableZval>,
+    /// @var int
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
     cpu_count: usize,
     /// @var bool
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
     debug_flag: bool,
+    /// Enables Deno's testing features, and replaces `Deno.test()` with one that registers tests to
+    /// be run with `Deno\Runtime\MainWorker::run_tests()`.
+    ///
     /// @var bool
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
     enable_testing_features: bool,
@@ -287,6 +1159,7 @@ impl BootstrapOptions {
     fn __construct() -> Self {
         BootstrapOptions {
             args: vec![],
+            config: None,
             cpu_count: 1,
             debug_flag: false,
             enable_testing_features: false,
@@ -405,11 +1278,12 @@ impl From<&PermissionsOptions> for deno_runtime::permissions::PermissionsOptions
 /// to Deno\Core\JsRuntime.
 ///
 #[php_class(name = "Deno\\Core\\RuntimeOptions")]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct RuntimeOptions {
     /// The module loader accepts a callable which is responsible for loading
     /// ES6 modules from a given name. See `Deno\Core\ModuleLoader` for methods that should be implemented.
-    /// @var Deno\Core\ModuleLoader
+    /// Use `Deno\Core\FsModuleLoader` to load modules from the filesystem.
+    /// @var Deno\Core\ModuleLoader|Deno\Core\FsModuleLoader
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
     module_loader: Option<CloneableZval>,
     /// Extensions allow you to add additional functionality via Deno "ops" to the JsRuntime. `extensions` takes an array of
@@ -419,16 +1293,112 @@ struct RuntimeOptions {
     extensions: Vec<Extension>,
     /// Prepare runtime to take snapshot of loaded code. The snapshot is determinstic and uses predictable random numbers.
     ///
-    /// Currently can’t be used with startup_snapshot.
+    /// Can’t be used with startup_snapshot, the JsRuntime constructor throws an exception if both are set.
     /// @var bool
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
     will_snapshot: bool,
-    /// V8 snapshot that should be loaded on startup.
+    /// V8 snapshot that should be loaded on startup: either the binary string returned by
+    /// `JsRuntime::snapshot()` or `SnapshotBuilder::build()` (not base64 encoded), the path of a file
+    /// containing such a snapshot, or a `Deno\Core\StaticSnapshot`. The constructor throws an exception
+    /// if the value isn’t snapshot data created by this version of the extension. A string is copied
+    /// for each runtime that is constructed, so use a `Deno\Core\StaticSnapshot` to share one snapshot
+    /// between many runtimes.
     ///
-    /// Currently can’t be used with will_snapshot.
-    /// @var string
+    /// Can’t be used with will_snapshot, the JsRuntime constructor throws an exception if both are set.
+    /// @var string|\Deno\Core\StaticSnapshot
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
     startup_snapshot: Option<CloneableZval>,
+    /// The maximum size of the V8 heap in megabytes. When the limit is reached, the running
+    /// script is terminated and a `Deno\Core\JsException` is thrown. The runtime should be
+    /// discarded after the heap limit has been exceeded.
+    /// @var int|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    heap_limit_mb: Option<usize>,
+    /// A callable that receives all `console` calls from JavaScript, replacing the runtime's
+    /// `console`. The callable is called with the log level (one of "log", "info", "warn", "error"
+    /// or "debug") and an array of the arguments passed to the console method.
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    console: Option<CloneableZval>,
+    /// A callable that receives the output of `Deno.core.print()`, instead of it being written to
+    /// the process's stdout or stderr, where PHP's output buffering can not capture it. The callable
+    /// is called with the message and whether it was printed to stderr, for example to echo it or
+    /// to collect it in a buffer.
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    print: Option<CloneableZval>,
+    /// Record the number of calls to each op and the time spent in their PHP functions, which can
+    /// be retrieved with `JsRuntime::get_op_metrics()`. Defaults to `false`, as recording adds a
+    /// little overhead to every op call.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    op_metrics: bool,
+    /// A callable that maps exceptions thrown by ops to the class of the JavaScript error they are
+    /// thrown as, so JavaScript can check for them with `instanceof`, in the same way as deno_core's
+    /// `get_error_class_fn` does for Rust errors. The callable is called with the exception and
+    /// should return a class name, such as "TypeError", "RangeError" or one of the `Deno.errors`
+    /// classes (such as "NotFound") of a MainWorker, or null for the default. Other class names
+    /// create an `Error` with the class name as its `name`.
+    ///
+    /// By default, a `Deno\Core\JsException` (such as one thrown by a nested call into JavaScript)
+    /// is thrown as its original JavaScript error class, and other exceptions as an `Error`.
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    get_error_class: Option<CloneableZval>,
+    /// The maximum depth of nested calls between PHP and JavaScript, where each op call into PHP and
+    /// each `call_function()` or `call_global_function()` into JavaScript is one level. Ops can call
+    /// PHP functions that call back into JavaScript, so deeply reentrant code could otherwise
+    /// exhaust the stack and crash the process. A call that exceeds the limit throws an error
+    /// instead. Defaults to `null`, which does not limit the depth.
+    /// @var int|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    max_call_depth: Option<u32>,
+    /// Convert JavaScript strings exactly. JavaScript strings are sequences of UTF-16 code units
+    /// that may contain lone surrogates, which can't be represented in UTF-8, so they are replaced
+    /// with U+FFFD (�) when strings are converted to PHP. When true, strings with lone surrogates
+    /// are converted to a `Deno\Core\Utf16String` instead, which holds the exact code units and is
+    /// converted back to the identical JavaScript string. Other strings are converted to PHP strings
+    /// as usual, and object keys are always converted to PHP strings. Defaults to `false`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    lossless_strings: bool,
+    /// A callable that is called with a `Deno\Core\JsException` for each unhandled Promise
+    /// rejection, with the message and stack of the rejection value, for example to log them.
+    /// Without it, an unhandled rejection stops the event loop and is thrown from
+    /// `run_event_loop()` (or `mod_evaluate()` etc.) as a `Deno\Core\JsException`. With it, the
+    /// event loop continues running the remaining async actions. Defaults to `null`.
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    on_unhandled_rejection: Option<CloneableZval>,
+    /// Allow high resolution time from `performance.now()`. When false, the time is coarsened
+    /// to 2ms in the same way as Deno without the `--allow-hrtime` permission, which makes timing
+    /// attacks harder for untrusted code. Defaults to `false`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    allow_hrtime: bool,
+    /// A callable that formats JavaScript errors, such as uncaught exceptions and Promise rejections,
+    /// for example to match an application's logging format. The callable is called with the
+    /// `Deno\Core\JsException` and should return the message for the exception that is thrown to PHP.
+    /// When not set, or when the callable throws or doesn't return a string, the message is the
+    /// JavaScript error's message.
+    /// @var callable|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    format_js_error: Option<CloneableZval>,
+    /// The store for the memory of `SharedArrayBuffer`s serialized with `Deno.core.serialize()`. When
+    /// runtimes are given the same store, a `SharedArrayBuffer` serialized in one can be deserialized
+    /// with `Deno.core.deserialize()` in another, and both share its memory. Without a store,
+    /// `SharedArrayBuffer`s can not be serialized.
+    /// @var \Deno\Core\SharedArrayBufferStore|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    shared_array_buffer_store: Option<SharedArrayBufferStore>,
+    /// The store for WebAssembly modules serialized with `Deno.core.serialize()`. When runtimes are
+    /// given the same store, a compiled `WebAssembly.Module` serialized in one can be deserialized
+    /// with `Deno.core.deserialize()` in another without compiling it again. Without a store,
+    /// `WebAssembly.Module`s can not be serialized.
+    /// @var \Deno\Core\CompiledWasmModuleStore|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    compiled_wasm_module_store: Option<CompiledWasmModuleStore>,
+    startup_snapshot_from_file: Option<Box<[u8]>>,
 }
 
 #[php_impl(rename_methods = "none")]
@@ -440,44 +1410,267 @@ impl RuntimeOptions {
             extensions: vec![],
             will_snapshot: false,
             startup_snapshot: None,
+            heap_limit_mb: None,
+            console: None,
+            print: None,
+            op_metrics: false,
+            get_error_class: None,
+            max_call_depth: None,
+            lossless_strings: false,
+            on_unhandled_rejection: None,
+            allow_hrtime: false,
+            format_js_error: None,
+            shared_array_buffer_store: None,
+            compiled_wasm_module_store: None,
+            startup_snapshot_from_file: None,
+        }
+    }
+
+    /// Load the V8 startup snapshot from a file, such as one written by `JsRuntime::snapshot()`.
+    /// This avoids reading the snapshot into a PHP string first, and replaces any `startup_snapshot`
+    /// that has been set.
+    fn set_startup_snapshot_from_file(&mut self, path: &str) -> PhpResult<()> {
+        let snapshot = read_snapshot_file(path)?;
+        self.startup_snapshot = None;
+        self.startup_snapshot_from_file = Some(snapshot.into_boxed_slice());
+        Ok(())
+    }
+}
+
+impl RuntimeOptions {
+    fn php_bindings(&self) -> Vec<(&'static PhpBinding, CloneableZval)> {
+        let mut php_bindings = vec![];
+        if let Some(console) = &self.console {
+            php_bindings.push((&PHP_CONSOLE, console.clone()));
+        }
+        if let Some(print) = &self.print {
+            php_bindings.push((&PHP_PRINT, print.clone()));
+        }
+        php_bindings
+    }
+
+    /// The startup snapshot from `startup_snapshot`, which may also be the path of a snapshot file,
+    /// or from `set_startup_snapshot_from_file()`.
+    fn startup_snapshot(&self) -> Result<Option<deno_core::Snapshot>, String> {
+        let snapshot = match &self.startup_snapshot {
+            Some(snapshot) => snapshot,
+            None => {
+                return Ok(self
+                    .startup_snapshot_from_file
+                    .clone()
+                    .map(deno_core::Snapshot::Boxed))
+            }
+        };
+        if let Some(snapshot) = snapshot.0.extract::<&StaticSnapshot>() {
+            return Ok(Some(deno_core::Snapshot::Static(snapshot.data)));
+        }
+        let snapshot: Vec<u8> = match snapshot.0.binary() {
+            Some(snapshot) => snapshot,
+            None => return Err(
+                "RuntimeOptions.startup_snapshot must be a string or a Deno\\Core\\StaticSnapshot."
+                    .to_string(),
+            ),
+        };
+        if is_snapshot(&snapshot) {
+            return Ok(Some(deno_core::Snapshot::Boxed(
+                snapshot.into_boxed_slice(),
+            )));
+        }
+        // A string that is not snapshot data may be the path of a snapshot file.
+        let path = String::from_utf8(snapshot)
+            .ok()
+            .filter(|path| !path.contains('\0') && std::path::Path::new(path).is_file());
+        match path {
+            Some(path) => Ok(Some(deno_core::Snapshot::Boxed(
+                read_snapshot_file(&path)?.into_boxed_slice(),
+            ))),
+            None => Err(format!(
+                "RuntimeOptions.startup_snapshot is neither valid snapshot data nor the path of a snapshot file. {}",
+                SNAPSHOT_HINT
+            )),
         }
     }
 }
 
-impl From<&RuntimeOptions> for deno_core::RuntimeOptions {
-    fn from(options: &RuntimeOptions) -> Self {
-        let extensions: Vec<deno_core::Extension> = options
+/// Explains which snapshots can be used, as the same snapshot must work with the running V8.
+const SNAPSHOT_HINT: &str = "Snapshots must be the binary string returned by JsRuntime::snapshot() or SnapshotBuilder::build(), and be created by the same version of the extension.";
+
+/// Whether the data is a V8 snapshot created by the running version of V8. The header of a snapshot
+/// contains the V8 version, which V8 checks when the snapshot is loaded by aborting the process on a
+/// mismatch, so other data (such as a base64 encoded snapshot) must not be passed to V8.
+fn is_snapshot(snapshot: &[u8]) -> bool {
+    let version = v8::V8::get_version().as_bytes();
+    snapshot[..snapshot.len().min(128)]
+        .windows(version.len())
+        .any(|window| window == version)
+}
+
+/// Read a snapshot file, checking that it contains a V8 snapshot.
+fn read_snapshot_file(path: &str) -> Result<Vec<u8>, String> {
+    let snapshot = match std::fs::read(path) {
+        Ok(snapshot) => snapshot,
+        Err(error) => return Err(format!("Unable to read snapshot file {}: {}", path, error)),
+    };
+    if !is_snapshot(&snapshot) {
+        return Err(format!(
+            "The file {} does not contain valid snapshot data. {}",
+            path, SNAPSHOT_HINT
+        ));
+    }
+    Ok(snapshot)
+}
+
+impl TryFrom<&RuntimeOptions> for deno_core::RuntimeOptions {
+    type Error = String;
+    fn try_from(options: &RuntimeOptions) -> Result<deno_core::RuntimeOptions, String> {
+        let mut extensions: Vec<deno_core::Extension> = options
             .extensions
             .iter()
             .map(|extension| extension.into())
             .collect();
+        let php_bindings = options.php_bindings();
+        if !php_bindings.is_empty() {
+            extensions.push(php_bindings_extension(&php_bindings));
+        }
+        extensions.push(php_streams_extension());
+        extensions.push(performance_extension(options.allow_hrtime));
 
         let module_loader: Option<CloneableZval> = match options.module_loader.as_ref() {
             Some(module_loader) => Some(module_loader.clone()),
             None => None,
         };
 
-        deno_core::RuntimeOptions {
+        Ok(deno_core::RuntimeOptions {
             module_loader: match module_loader {
-                Some(module_loader) => Some(std::rc::Rc::new(ModuleLoader::new(module_loader))),
+                Some(module_loader) => Some(module_loader_from_zval(module_loader)),
                 None => None,
             },
             extensions,
             will_snapshot: options.will_snapshot,
-            startup_snapshot: match &options.startup_snapshot {
-                Some(snapshot) => {
-                    let snapshot = snapshot.clone().into_zval(false).unwrap().binary().unwrap();
-                    Some(deno_core::Snapshot::Boxed(
-                        snapshot.as_slice().to_vec().into_boxed_slice(),
-                    ))
-                }
-                None => None,
-            },
+            startup_snapshot: options.startup_snapshot()?,
+            shared_array_buffer_store: options
+                .shared_array_buffer_store
+                .as_ref()
+                .map(|store| store.store.clone()),
+            compiled_wasm_module_store: options
+                .compiled_wasm_module_store
+                .as_ref()
+                .map(|store| store.store.clone()),
+            create_params: options
+                .heap_limit_mb
+                .map(|mb| v8::CreateParams::default().heaps(0, mb * 1024 * 1024)),
             ..Default::default()
+        })
+    }
+}
+
+/// A V8 startup snapshot that is loaded into memory once and shared by all the runtimes it is used
+/// for with `RuntimeOptions::startup_snapshot`, rather than being copied for each runtime. This
+/// saves copying large snapshots when many runtimes are constructed, such as one per request.
+///
+/// The snapshot's memory is never freed, as V8 may read it for as long as the process runs, so
+/// create each snapshot once (for example in a static variable) rather than for every runtime.
+#[php_class(name = "Deno\\Core\\StaticSnapshot")]
+#[derive(Clone, Copy)]
+struct StaticSnapshot {
+    data: &'static [u8],
+}
+
+#[php_impl(rename_methods = "none")]
+impl StaticSnapshot {
+    /// @param string $snapshot
+    #[constructor]
+    fn __construct(snapshot: &Zval) -> PhpResult<Self> {
+        match snapshot.binary() {
+            Some(snapshot) if is_snapshot(&snapshot) => Ok(Self::new(snapshot)),
+            Some(_) => {
+                Err(format!("The snapshot is not valid snapshot data. {}", SNAPSHOT_HINT).into())
+            }
+            None => Err("The snapshot must be a string.".into()),
+        }
+    }
+
+    /// Load the snapshot from a file, such as one written by `SnapshotBuilder::build_to_file()`.
+    fn from_file(path: &str) -> PhpResult<Self> {
+        Ok(Self::new(read_snapshot_file(path)?))
+    }
+
+    /// The size of the snapshot in bytes.
+    fn length(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl StaticSnapshot {
+    fn new(snapshot: Vec<u8>) -> Self {
+        Self {
+            data: Box::leak(snapshot.into_boxed_slice()),
         }
     }
 }
 
+/// A store for the memory of `SharedArrayBuffer`s, which lets runtimes and workers that are given the
+/// same store share `SharedArrayBuffer`s. See `RuntimeOptions::shared_array_buffer_store` and
+/// `Deno\Runtime\WorkerOptions::shared_array_buffer_store`.
+#[php_class(name = "Deno\\Core\\SharedArrayBufferStore")]
+#[derive(Clone, Default)]
+struct SharedArrayBufferStore {
+    store: deno_core::SharedArrayBufferStore,
+}
+
+#[php_impl(rename_methods = "none")]
+impl SharedArrayBufferStore {
+    #[constructor]
+    fn __construct() -> Self {
+        Self::default()
+    }
+}
+
+impl std::fmt::Debug for SharedArrayBufferStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedArrayBufferStore").finish()
+    }
+}
+
+impl FromZval<'_> for SharedArrayBufferStore {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn from_zval(zval: &'_ Zval) -> Option<Self> {
+        let store: &SharedArrayBufferStore = zval.extract()?;
+        Some(store.clone())
+    }
+}
+
+/// A store for compiled WebAssembly modules, which lets runtimes and workers that are given the same
+/// store share compiled `WebAssembly.Module`s. See `RuntimeOptions::compiled_wasm_module_store` and
+/// `Deno\Runtime\WorkerOptions::compiled_wasm_module_store`.
+#[php_class(name = "Deno\\Core\\CompiledWasmModuleStore")]
+#[derive(Clone, Default)]
+struct CompiledWasmModuleStore {
+    store: deno_core::CompiledWasmModuleStore,
+}
+
+#[php_impl(rename_methods = "none")]
+impl CompiledWasmModuleStore {
+    #[constructor]
+    fn __construct() -> Self {
+        Self::default()
+    }
+}
+
+impl std::fmt::Debug for CompiledWasmModuleStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledWasmModuleStore").finish()
+    }
+}
+
+impl FromZval<'_> for CompiledWasmModuleStore {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn from_zval(zval: &'_ Zval) -> Option<Self> {
+        let store: &CompiledWasmModuleStore = zval.extract()?;
+        Some(store.clone())
+    }
+}
+
 #[php_class(name = "Deno\\Core\\JsRuntime")]
 /// The JsRuntime is a wrapper around a V8 isolate. It can execute ES6 including ES6 modules. The JsRuntime
 /// does not include any of the Deno.core.* ops, and does not provide implementations for web apis, such as
@@ -485,61 +1678,206 @@ impl From<&RuntimeOptions> for deno_core::RuntimeOptions {
 /// functionality such as local storage, remote requests etc.
 struct JsRuntime {
     deno_jsruntime: deno_core::JsRuntime,
+    tokio_runtime: std::rc::Rc<tokio::runtime::Runtime>,
+    source_maps: SourceMaps,
+    invalidated_modules: InvalidatedModules,
+    format_js_error: Option<CloneableZval>,
     will_snapshot: bool,
     has_snapshotted: bool,
+    termination_reason: std::sync::Arc<std::sync::Mutex<Option<String>>>,
+    heap_limit_exceeded: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    options: RuntimeOptions,
+    /// The ops registered with `register_op()` and the like, to register again after `reset()`.
+    registered_ops: Vec<(String, OpCallback)>,
 }
 
 #[php_impl(rename_methods = "none")]
 impl JsRuntime {
     #[constructor]
-    fn __construct(options: &RuntimeOptions) -> Self {
-        let mut deno_jsruntime = deno_core::JsRuntime::new(options.into());
-        let mut callbacks: HashMap<String, CloneableZval> = HashMap::new();
+    fn __construct(options: &RuntimeOptions) -> PhpResult<Self> {
+        let has_startup_snapshot =
+            options.startup_snapshot.is_some() || options.startup_snapshot_from_file.is_some();
+        if options.will_snapshot && has_startup_snapshot {
+            return Err(
+                "RuntimeOptions.will_snapshot can not be used together with a startup snapshot."
+                    .into(),
+            );
+        }
 
+        let mut extension_names = std::collections::HashSet::new();
         for extension in &options.extensions {
-            for (name, op) in &extension.ops {
-                callbacks.insert(name.to_string(), op.clone().into());
+            if !extension.name.is_empty() && !extension_names.insert(extension.name.as_str()) {
+                return Err(format!("Duplicate extension name \"{}\".", extension.name).into());
             }
         }
 
-        deno_jsruntime
-            .v8_isolate()
-            .set_slot(std::rc::Rc::new(std::cell::RefCell::new(callbacks)));
+        V8_INITIALIZED.store(true, std::sync::atomic::Ordering::SeqCst);
+        let source_maps = SourceMaps::new(options.module_loader.clone());
+        let mut runtime_options = deno_core::RuntimeOptions::try_from(options)?;
+        runtime_options.source_map_getter = Some(Box::new(source_maps.clone()));
+        let invalidated_modules = InvalidatedModules::default();
+        runtime_options.module_loader = runtime_options
+            .module_loader
+            .map(|module_loader| invalidated_modules.loader(module_loader));
 
-        Self {
+        // deno_core panics when an extension's js files throw during initialization.
+        let mut deno_jsruntime =
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                deno_core::JsRuntime::new(runtime_options)
+            })) {
+                Ok(deno_jsruntime) => deno_jsruntime,
+                Err(panic) => {
+                    return Err(format!(
+                        "Unable to initialize extensions: {}",
+                        panic_message(panic.as_ref())
+                    )
+                    .into())
+                }
+            };
+        if let Err(error) = init_ops(
+            &mut deno_jsruntime,
+            &options.extensions,
+            &options.php_bindings(),
+        ) {
+            return Err(error.to_string().into());
+        }
+
+        if options.op_metrics {
+            deno_jsruntime.v8_isolate().set_slot(OpMetrics::default());
+        }
+        if let Some(get_error_class) = &options.get_error_class {
+            deno_jsruntime
+                .v8_isolate()
+                .set_slot(GetErrorClass(get_error_class.clone()));
+        }
+        if let Some(max_call_depth) = options.max_call_depth {
+            deno_jsruntime
+                .v8_isolate()
+                .set_slot(CallDepth::new(max_call_depth));
+        }
+        if options.lossless_strings {
+            deno_jsruntime.v8_isolate().set_slot(LosslessStrings);
+        }
+        if let Some(on_unhandled_rejection) = &options.on_unhandled_rejection {
+            deno_jsruntime
+                .v8_isolate()
+                .set_slot(OnUnhandledRejection(on_unhandled_rejection.clone()));
+        }
+
+        let termination_reason = std::sync::Arc::new(std::sync::Mutex::new(None));
+        let heap_limit_exceeded = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+        if options.heap_limit_mb.is_some() {
+            let isolate_handle = deno_jsruntime.v8_isolate().thread_safe_handle();
+            let termination_reason = termination_reason.clone();
+            let heap_limit_exceeded = heap_limit_exceeded.clone();
+            deno_jsruntime.add_near_heap_limit_callback(move |current_limit, _initial_limit| {
+                *termination_reason.lock().unwrap() = Some("Heap limit exceeded.".to_string());
+                heap_limit_exceeded.store(true, std::sync::atomic::Ordering::SeqCst);
+                isolate_handle.terminate_execution();
+                // Give V8 enough headroom to unwind the terminated execution instead of
+                // aborting the process with an OOM.
+                current_limit * 2
+            });
+        }
+
+        Ok(Self {
             deno_jsruntime: deno_jsruntime,
+            tokio_runtime: new_tokio_runtime(),
+            source_maps,
+            invalidated_modules,
+            format_js_error: options.format_js_error.clone(),
             will_snapshot: options.will_snapshot,
             has_snapshotted: false,
+            termination_reason,
+            heap_limit_exceeded,
+            options: options.clone(),
+            registered_ops: vec![],
+        })
+    }
+
+    /// Discard all of the runtime's JavaScript state, such as globals, loaded modules and pending
+    /// timers, by replacing its isolate with a fresh one created from the same `RuntimeOptions`, for
+    /// example to isolate requests from one another. The extensions are initialized again, and ops
+    /// registered with `register_op()` are registered in the fresh isolate. Values and resources in
+    /// the `OpState`, source maps added with `add_source_map()` and op metrics are discarded.
+    ///
+    /// deno_core 0.149 can't give a runtime a new global context, so this costs about as much as
+    /// creating a new `JsRuntime`; it saves setting up the options and ops again in PHP.
+    fn reset(&mut self) -> PhpResult<()> {
+        self.ensure_not_snapshotted()?;
+        let mut runtime = Self::__construct(&self.options)?;
+        for (name, op_callback) in &self.registered_ops {
+            runtime.add_op(name, op_callback.clone())?;
         }
+        *self = runtime;
+        Ok(())
     }
 
     /// Execute JavaSscript inside the V8 Isolate.
     ///
-    /// This does not support top level await for Es6 imports. use `load_main_module`
+    /// This does not support top level await for Es6 imports. use `execute_module`
     /// to execute JavaScript in modules.
-    fn execute_script(&mut self, name: &str, source_code: &str) -> PhpResult<String> {
-        if self.has_snapshotted {
-            return Err("Scripts can not be executed after JsRuntime has been snapshotted.".into());
-        }
-        let mut rt = tokio::runtime::Runtime::new().unwrap();
-        let local = tokio::task::LocalSet::new();
-        local.block_on(&mut rt, async {
-            match self.deno_jsruntime.execute_script(name, source_code) {
-                Ok(return_value) => {
-                    let mut scope = self.deno_jsruntime.handle_scope();
-                    let value = return_value.open(&mut scope);
-                    let value_str = value
-                        .to_string(&mut scope)
-                        .unwrap()
-                        .to_rust_string_lossy(&mut scope);
-                    Ok(String::from(value_str))
-                },
-                Err(error) => match error.downcast::<deno_core::error::JsError>() {
-                    Ok(error) => {
-                        Err(JsException::from(error).into())
+    ///
+    /// When `timeout_ms` is given, the script is terminated once it has run for that many
+    /// milliseconds and a `Deno\Core\JsException` is thrown. The runtime can still be used
+    /// after a timeout, however any global state the script modified before it was terminated
+    /// is left as-is, so consider discarding the runtime when running untrusted code.
+    ///
+    /// `name` is the file name used for the script in stack traces, so use a URL such as
+    /// `file:///app/index.js` for the frames of a `Deno\Core\JsException` to point at a resolvable
+    /// file. If the source code has an inline source map, such as transpiled code with
+    /// `EmitOptions::inline_source_map` set, the frames are mapped back to the original source.
+    /// Use `add_source_map()` for source maps that are not inline.
+    #[optional(timeout_ms)]
+    fn execute_script(
+        &mut self,
+        name: &str,
+        source_code: &str,
+        timeout_ms: Option<u64>,
+    ) -> PhpResult<String> {
+        self.ensure_not_snapshotted()?;
+        self.source_maps.add_inline(name, source_code);
+        self.with_timeout(timeout_ms, |runtime| {
+            let rt = runtime.tokio_runtime.clone();
+            let local = tokio::task::LocalSet::new();
+            local.block_on(&rt, async {
+                match runtime.deno_jsruntime.execute_script(name, source_code) {
+                    Ok(return_value) => {
+                        let mut scope = runtime.deno_jsruntime.handle_scope();
+                        let value = return_value.open(&mut scope);
+                        let value_str = value
+                            .to_string(&mut scope)
+                            .unwrap()
+                            .to_rust_string_lossy(&mut scope);
+                        Ok(String::from(value_str))
                     },
-                    Err(error) => Err(error.to_string().into()),
-                },
+                    Err(error) => Err(runtime.php_exception(error)),
+                }
+            })
+        })
+    }
+
+    /// Execute a script like `execute_script()`, but return its completion value (the value of
+    /// the last evaluated expression statement) converted to a PHP value, rather than converted to
+    /// a string. This is useful to use the runtime as an expression evaluator, for example
+    /// `evaluate('calc.js', '1 + 2')` returns the int 3. A returned Promise is not awaited, use
+    /// `execute_repl()` for that. Modules have no completion value, use `get_default_export()`
+    /// or `execute_module()` to get a value from a module.
+    ///
+    /// @return mixed
+    #[optional(timeout_ms)]
+    fn evaluate(
+        &mut self,
+        name: &str,
+        source_code: &str,
+        timeout_ms: Option<u64>,
+    ) -> PhpResult<Zval> {
+        self.ensure_not_snapshotted()?;
+        self.source_maps.add_inline(name, source_code);
+        self.with_timeout(timeout_ms, |runtime| {
+            match evaluate(&mut runtime.deno_jsruntime, name, source_code) {
+                Ok(completion_value) => Ok(completion_value),
+                Err(error) => Err(runtime.php_exception(error)),
             }
         })
     }
@@ -554,17 +1892,18 @@ impl JsRuntime {
         specifier: &str,
         code: Option<String>,
     ) -> PhpResult<deno_core::ModuleId> {
+        self.ensure_not_snapshotted()?;
         let specifier = match url::Url::parse(specifier) {
             Ok(specifier) => specifier,
             Err(err) => return Err(err.to_string().into()),
         };
 
-        let mut rt = tokio::runtime::Runtime::new().unwrap();
+        let rt = self.tokio_runtime.clone();
         let local = tokio::task::LocalSet::new();
-        local.block_on(&mut rt, async {
+        local.block_on(&rt, async {
             match self.deno_jsruntime.load_main_module(&specifier, code).await {
                 Ok(module_id) => Ok(module_id),
-                Err(error) => return Err(error.to_string().into()),
+                Err(error) => return Err(self.php_exception(error)),
             }
         })
     }
@@ -573,580 +1912,5182 @@ impl JsRuntime {
     /// If there are pending Promises or async axtions, use `run_event_loop()` to
     /// wait until all async actions complete.
     fn mod_evaluate(&mut self, id: deno_core::ModuleId) -> PhpResult<()> {
-        let result = self.deno_jsruntime.mod_evaluate(id);
-        match futures::executor::block_on(self.deno_jsruntime.run_event_loop(false)) {
-            Ok(()) => (),
-            Err(error) => return Err(error.to_string().into()),
-        };
+        self.ensure_not_snapshotted()?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            let result = self.deno_jsruntime.mod_evaluate(id);
+            match run_event_loop(&mut self.deno_jsruntime).await {
+                Ok(()) => (),
+                Err(error) => return Err(self.php_exception(error)),
+            };
 
-        match futures::executor::block_on(result).unwrap() {
-            Ok(()) => Ok(()),
-            Err(error) => Err(error.to_string().into()),
+            match result.await {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(error)) => Err(self.php_exception(error)),
+                // The evaluation is canceled when the runtime drops it, such as when the module
+                // errors while the event loop is running.
+                Err(_canceled) => Err(JsException {
+                    message: format!("Evaluation of module {} was canceled.", id),
+                    ..Default::default()
+                }
+                .into()),
+            }
+        })
+    }
+
+    /// Get the default export of a module that has been evaluated with `mod_evaluate()`, converted
+    /// to a PHP value, or null if it has no default export. A function is returned as a
+    /// `Deno\Core\JsFunction`, so a module can be loaded once and its default export called many
+    /// times with `call_function()`.
+    ///
+    /// @return mixed
+    fn get_default_export(&mut self, id: deno_core::ModuleId) -> PhpResult<Zval> {
+        self.ensure_not_snapshotted()?;
+        match default_export(&mut self.deno_jsruntime, id) {
+            Ok(default_export) => Ok(default_export),
+            Err(error) => Err(self.php_exception(error)),
         }
     }
 
     /// Wait for the event loop to run all pending async actions.
-    fn run_event_loop(&mut self) -> PhpResult<()> {
-        match futures::executor::block_on(self.deno_jsruntime.run_event_loop(false)) {
-            Ok(()) => Ok(()),
-            Err(error) => Err(error.to_string().into()),
-        }
+    ///
+    /// When `timeout_ms` is given, the event loop is stopped once it has run for that many
+    /// milliseconds, for example to cap the background work of a request when a `setInterval()`
+    /// would otherwise keep it running forever. Returns true when all pending async actions have
+    /// completed, or false when the timeout was reached while some were still pending. Call
+    /// `run_event_loop()` again to continue them. The timeout is checked between tasks, so use
+    /// the timeout of `execute_script()` to stop a single long running script.
+    ///
+    /// @return bool
+    #[optional(timeout_ms)]
+    fn run_event_loop(&mut self, timeout_ms: Option<u64>) -> PhpResult<bool> {
+        self.ensure_not_snapshotted()?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            let event_loop = run_event_loop(&mut self.deno_jsruntime);
+            match run_event_loop_with_timeout(event_loop, timeout_ms).await {
+                Ok(completed) => Ok(completed),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Whether the event loop has pending work, such as timers, unresolved async ops or a `fetch()`
+    /// that has not completed, so a scheduler can decide whether to call `run_event_loop()` again.
+    /// This polls the event loop once without waiting, so any work that is ready runs first.
+    fn has_pending_work(&mut self) -> PhpResult<bool> {
+        self.ensure_not_snapshotted()?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match has_pending_work(&mut self.deno_jsruntime).await {
+                Ok(has_pending_work) => Ok(has_pending_work),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
     }
 
     /// Takes a snapshot. The isolate should have been created with will_snapshot set to true.
     ///
+    /// Taking a snapshot consumes the isolate, so any method that runs code or accesses the isolate
+    /// throws an exception afterwards. Create a new JsRuntime with the snapshot as
+    /// `RuntimeOptions::startup_snapshot` to continue running code.
+    ///
     /// @return string
     fn snapshot(&mut self) -> PhpResult<Zval> {
-        if self.will_snapshot == false {
-            return Err(
-                "Unable to shapshot JsRuntime when RuntimeOptions.will_snapshot is not true."
-                    .into(),
-            );
-        }
-        let startup_data = self.deno_jsruntime.snapshot();
+        let startup_data = self.take_snapshot()?;
         let snapshot_slice: &[u8] = &*startup_data;
         let mut zval = Zval::new();
         zval.set_binary(snapshot_slice.to_vec());
-        self.has_snapshotted = true;
         Ok(zval)
     }
-}
-/// The module loader interface (don't trust the docs, this is an interface not a class!)
-/// Pass an instance of your class that implements `Deno\Core\ModuleLoader` to the `module_loader`
-/// property of `Deno\Runtime\WorkerOptions` or `Deno\Core\RuntimeOptions`
-#[php_class(name = "Deno\\Core\\ModuleLoader", flags = "Interface")]
-#[derive(Clone, Debug)]
-struct ModuleLoaderInterface {}
 
-#[php_impl(rename_methods = "none")]
-impl ModuleLoaderInterface {
-    /// The `resolve` method should take a module specifier and normalize it to a canonical URL.
-    /// @return string
-    #[php_method]
-    #[abstract_method]
-    fn resolve(&self, _specifier: &str, _referrer: &str) -> &str {
-        ""
+    /// Takes a snapshot and writes it to a file, without copying it in to a PHP string. The
+    /// isolate should have been created with will_snapshot set to true.
+    fn snapshot_to_file(&mut self, path: &str) -> PhpResult<()> {
+        let startup_data = self.take_snapshot()?;
+        match std::fs::write(path, &*startup_data) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(format!("Unable to write snapshot file {}: {}", path, error).into()),
+        }
     }
 
-    /// The `load` method takes a module specifier and should return the contents for a module.
-    /// See `Deno\Core\ModuleSource` for the specifics.
-    /// @return \Deno\Core\ModuleSource
-    #[php_method]
-    #[abstract_method]
-    fn load(&self, _specifier: &str) -> Option<ModuleSource> {
-        None
+    /// Get the V8 heap statistics for the isolate, such as `used_heap_size` and `heap_size_limit`.
+    /// All sizes are in bytes.
+    ///
+    /// @return array<string, int>
+    fn get_heap_statistics(&mut self) -> PhpResult<HashMap<&'static str, usize>> {
+        self.ensure_not_snapshotted()?;
+        Ok(get_heap_statistics(self.deno_jsruntime.v8_isolate()))
     }
-}
 
-#[derive(Clone)]
-struct ModuleLoader(CloneableZval);
+    /// Run a full garbage collection, for example between requests to keep memory usage flat.
+    /// This sends V8 a low memory notification, which collects all garbage it can, so it does not
+    /// need V8 to be started with `--expose-gc`.
+    fn request_garbage_collection(&mut self) -> PhpResult<()> {
+        self.ensure_not_snapshotted()?;
+        self.deno_jsruntime.v8_isolate().low_memory_notification();
+        Ok(())
+    }
 
-impl ModuleLoader {
-    fn new(loader: CloneableZval) -> Self {
-        Self(loader)
+    /// Whether the isolate's execution is being terminated, such as by a timeout or the heap limit,
+    /// and the termination has not been cancelled yet.
+    fn is_terminating(&mut self) -> bool {
+        !self.has_snapshotted && self.deno_jsruntime.v8_isolate().is_execution_terminating()
     }
-}
 
-impl deno_core::ModuleLoader for ModuleLoader {
-    fn resolve(
-        &self,
-        specifier: &str,
-        referrer: &str,
-        _is_main: bool,
-    ) -> Result<deno_core::ModuleSpecifier, Error> {
-        let result = call_user_method!(
-            (&self.0).clone().into_zval(false).unwrap(),
-            "resolve",
-            specifier,
-            referrer,
-            _is_main
-        );
+    /// Whether the runtime is still safe to use, for example for a pool of runtimes to decide
+    /// whether to discard a runtime and create a new one (or `reset()` it) after an error. A runtime
+    /// is not healthy once it has been snapshotted, while its execution is being terminated, or after
+    /// it has exceeded `RuntimeOptions::heap_limit_mb`, as the heap limit is raised to let V8 unwind
+    /// the terminated script, so the limit no longer applies. Scripts terminated by a timeout leave
+    /// the runtime healthy, although any global state they modified is left as-is.
+    fn is_healthy(&mut self) -> bool {
+        !self.has_snapshotted
+            && !self.is_terminating()
+            && !self
+                .heap_limit_exceeded
+                .load(std::sync::atomic::Ordering::SeqCst)
+    }
 
-        match result {
-            Some(result) => match result.string() {
-                Some(result) => match url::Url::parse(result.as_str()) {
-                    Ok(result) => Ok(result),
-                    Err(err) => anyhow::bail!(err.to_string()),
-                },
-                None => anyhow::bail!("resolve() did not return a valid string."),
-            },
-            None => {
-                anyhow::bail!("resolve() did not return a valid string.")
-            }
+    /// Get the names of the ops that are available to JavaScript in `Deno.core.ops`, sorted. This
+    /// includes the ops of the extensions, ops registered with `register_op()` and deno_core's
+    /// built-in ops, which helps to find mismatches between op names in JavaScript and PHP.
+    ///
+    /// @return string[]
+    fn get_op_names(&mut self) -> PhpResult<Vec<String>> {
+        self.ensure_not_snapshotted()?;
+        match op_names(&mut self.deno_jsruntime) {
+            Ok(names) => Ok(names),
+            Err(error) => Err(self.php_exception(error)),
         }
     }
 
-    fn load(
-        &self,
-        _module_specifier: &deno_core::ModuleSpecifier,
-        _maybe_referrer: Option<deno_core::ModuleSpecifier>,
-        _is_dyn_import: bool,
-    ) -> core::pin::Pin<Box<deno_core::ModuleSourceFuture>> {
-        let result = call_user_method!(
-            (&self.0).clone().into_zval(false).unwrap(),
-            "load",
-            _module_specifier.to_string().clone()
-        );
-
-        let result = match result {
-            Some(result) => result,
-            None => {
-                return async {
-                    Err(deno_core::error::generic_error(
-                        "Error calling load() function on ModuleLoader",
-                    ))
-                }
-                .boxed_local()
+    /// Call a global JavaScript function with the given arguments, returning its return value
+    /// converted to a PHP value. If the function returns a Promise, the event loop is run until
+    /// the Promise settles.
+    ///
+    /// @param mixed[] $args
+    /// @return mixed
+    fn call_global_function(&mut self, name: &str, args: Vec<CloneableZval>) -> PhpResult<Zval> {
+        self.ensure_not_snapshotted()?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match call_global_function(&mut self.deno_jsruntime, name, &args).await {
+                Ok(return_value) => Ok(return_value),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Call a `Deno\Core\JsFunction` that was returned by this runtime with the given arguments,
+    /// returning its return value converted to a PHP value. If the function returns a Promise, the
+    /// event loop is run until the Promise settles.
+    ///
+    /// @param mixed[] $args
+    /// @return mixed
+    fn call_function(
+        &mut self,
+        function: &JsFunction,
+        args: Vec<CloneableZval>,
+    ) -> PhpResult<Zval> {
+        self.ensure_not_snapshotted()?;
+        let function = function.function_in(self.deno_jsruntime.v8_isolate())?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match call_function(&mut self.deno_jsruntime, function, &args).await {
+                Ok(return_value) => Ok(return_value),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Execute source code as an ES module, returning its default export converted to a PHP value,
+    /// or null if it has no default export. Unlike `execute_script()`, the source may use `import`
+    /// and top level `await`, and the event loop is run until the module has finished evaluating.
+    ///
+    /// The specifier must be a URL, which imports are resolved relative to. Modules are only
+    /// evaluated once, so use a different specifier for each call.
+    ///
+    /// @return mixed
+    fn execute_module(&mut self, specifier: &str, source_code: &str) -> PhpResult<Zval> {
+        self.ensure_not_snapshotted()?;
+        self.source_maps.add_inline(specifier, source_code);
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match execute_module(&mut self.deno_jsruntime, specifier, source_code).await {
+                Ok(return_value) => Ok(return_value),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Evaluate a line of REPL input, returning the value of the last expression statement.
+    ///
+    /// The input may be TypeScript and may contain `import` statements, which are transpiled
+    /// to dynamic imports (see `EmitOptions::var_decl_imports`) and loaded via the runtime's
+    /// module loader. Top level declarations are made global so they are visible to later
+    /// inputs, and may be redeclared by later inputs as they can in the Deno REPL.
+    ///
+    /// @return mixed
+    fn execute_repl(&mut self, source_code: &str) -> PhpResult<Zval> {
+        self.ensure_not_snapshotted()?;
+        let script = repl_script(source_code)?;
+        let rt = self.tokio_runtime.clone();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            match execute_repl(&mut self.deno_jsruntime, &script).await {
+                Ok(return_value) => Ok(return_value),
+                Err(error) => Err(self.php_exception(error)),
+            }
+        })
+    }
+
+    /// Add the source map for a script or module, such as `TranspiledSource::source_map` when the
+    /// code was transpiled without an inline source map. `file_name` is the script name passed
+    /// to `execute_script()`, or the module specifier. Stack frames in the script are then mapped
+    /// back to the original source in a `Deno\Core\JsException`.
+    fn add_source_map(&mut self, file_name: &str, source_map: &str) {
+        self.source_maps
+            .add(file_name, source_map.as_bytes().to_vec());
+    }
+
+    /// Invalidate a loaded module, so the next import of `specifier` calls the module loader again
+    /// and evaluates the module's new source, such as when a development server sees the module's
+    /// file change. Modules that already imported it keep the instance they were linked to, so
+    /// re-import the module with a dynamic `import()`, or invalidate its importers as well. The
+    /// module's own imports are only loaded again if they are invalidated too.
+    ///
+    /// deno_core can't remove a module from its module map, so the module is loaded again under
+    /// its specifier with a `#reload=N` fragment, which shows in `import.meta.url` and stack traces.
+    /// The module loader is called with the specifier without the fragment.
+    fn invalidate_module(&mut self, specifier: &str) -> PhpResult<()> {
+        self.ensure_not_snapshotted()?;
+        match self.invalidated_modules.invalidate(specifier) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.to_string().into()),
+        }
+    }
+
+    /// Set a global variable in the runtime, such as request data for a script to use. The PHP value
+    /// is converted to a JavaScript value. Arrays that are lists become JavaScript arrays, and other
+    /// arrays become objects, which order integer keys before string keys.
+    fn set_global(&mut self, name: &str, value: &Zval) -> PhpResult<()> {
+        self.ensure_not_snapshotted()?;
+        match set_global(&mut self.deno_jsruntime, name, value) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(self.php_exception(error)),
+        }
+    }
+
+    /// Get a global variable from the runtime, converted to a PHP value. Returns null if the global
+    /// is not set. Objects with a null prototype, such as those created from PHP arrays by
+    /// `set_global()`, are converted to arrays, and other objects are converted to objects.
+    ///
+    /// @return mixed
+    fn get_global(&mut self, name: &str) -> PhpResult<Zval> {
+        self.ensure_not_snapshotted()?;
+        let context = self.deno_jsruntime.global_context();
+        let mut scope = self.deno_jsruntime.handle_scope();
+        let global = context.open(&mut scope).global(&mut scope);
+        let key = v8::String::new(&mut scope, name).unwrap();
+        match global.get(&mut scope, key.into()) {
+            Some(value) => Ok(zval_from_jsvalue(value, &mut scope)),
+            None => {
+                let mut zval = Zval::new();
+                zval.set_null();
+                Ok(zval)
+            }
+        }
+    }
+
+    /// Serialize a global variable with V8's structured clone algorithm (the algorithm used by
+    /// `postMessage()`), returning it as a binary string. Unlike `get_global()`, this preserves
+    /// types such as Map, Set, Date and typed arrays, as well as cyclic references. Pass the result
+    /// to `deserialize_value()` of this or another runtime to restore the value.
+    ///
+    /// @return string
+    fn serialize_value(&mut self, name: &str) -> PhpResult<Zval> {
+        self.ensure_not_snapshotted()?;
+        match serialize_value(&mut self.deno_jsruntime, name) {
+            Ok(data) => {
+                let mut zval = Zval::new();
+                zval.set_binary(data);
+                Ok(zval)
             }
+            Err(error) => Err(self.php_exception(error)),
+        }
+    }
+
+    /// Deserialize a value serialized with `serialize_value()` and set it as the global variable `name`.
+    ///
+    /// @param string $data
+    fn deserialize_value(&mut self, name: &str, data: &Zval) -> PhpResult<()> {
+        self.ensure_not_snapshotted()?;
+        let data: Vec<u8> = match data.binary() {
+            Some(data) => data,
+            None => return Err("Serialized data must be a string.".into()),
         };
+        match deserialize_value(&mut self.deno_jsruntime, name, &data) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(self.php_exception(error)),
+        }
+    }
 
-        let source: &ModuleSource = match result.extract() {
-            Some(source) => source,
+    /// Get the number of calls to each op and the total time spent in its PHP function, keyed by
+    /// op name. Requires `RuntimeOptions::op_metrics` to be enabled. The time of async ops is the
+    /// time spent in their PHP function, not the time until their Promise settled.
+    ///
+    /// @return array<string, array{calls: int, total_time_ms: float}>
+    fn get_op_metrics(&mut self) -> PhpResult<Zval> {
+        self.ensure_not_snapshotted()?;
+        let op_metrics = match self.deno_jsruntime.v8_isolate().get_slot::<OpMetrics>() {
+            Some(op_metrics) => op_metrics.clone(),
             None => {
-                return async {
-                    Err(deno_core::error::generic_error(
-                        "Error converting return value of load() to ModuleSource",
-                    ))
-                }
-                .boxed_local()
+                return Err(
+                    "Op metrics are not enabled, set RuntimeOptions.op_metrics to true.".into(),
+                )
             }
         };
+        let mut metrics = ext_php_rs::types::ZendHashTable::new();
+        for (name, op_metric) in op_metrics.borrow().iter() {
+            let mut metric = ext_php_rs::types::ZendHashTable::new();
+            metric.insert("calls", op_metric.calls as i64)?;
+            metric.insert("total_time_ms", op_metric.total_time.as_secs_f64() * 1000.0)?;
+            metrics.insert(name.as_str(), metric)?;
+        }
+        let mut zval = Zval::new();
+        zval.set_hashtable(metrics);
+        Ok(zval)
+    }
 
-        let module_source = deno_core::ModuleSource {
-            code: source.code.clone().as_bytes().to_owned().into_boxed_slice(),
-            module_type: if source.module_type == "json" {
-                deno_core::ModuleType::Json
-            } else {
-                deno_core::ModuleType::JavaScript
+    /// Register an op after the runtime has been constructed, for example when a feature that needs
+    /// it is enabled. The op is added to `Deno.core.ops` in the same way as the ops of an Extension.
+    ///
+    /// JavaScript that has already copied the functions from `Deno.core.ops` (such as an Extension's
+    /// `js_files`) will not see the new op, so any bindings for it have to be exposed again, for
+    /// example with `execute_script()`.
+    ///
+    /// @param callable $callback
+    #[optional(is_async)]
+    fn register_op(
+        &mut self,
+        name: &str,
+        callback: CloneableZval,
+        is_async: Option<bool>,
+    ) -> PhpResult<()> {
+        self.ensure_not_snapshotted()?;
+        self.add_op(
+            name,
+            OpCallback {
+                callback,
+                is_async: is_async.unwrap_or(false),
+                is_void: false,
+                is_stream: false,
             },
-            module_url_specified: source.module_url_specified.clone(),
-            module_url_found: source.module_url_found.clone(),
-        };
+        )
+    }
+
+    /// Register a sync op whose return value is discarded, like the `void_ops` of an Extension,
+    /// after the runtime has been constructed. See `register_op()`.
+    ///
+    /// @param callable $callback
+    fn register_void_op(&mut self, name: &str, callback: CloneableZval) -> PhpResult<()> {
+        self.ensure_not_snapshotted()?;
+        self.add_op(
+            name,
+            OpCallback {
+                callback,
+                is_async: false,
+                is_void: true,
+                is_stream: false,
+            },
+        )
+    }
 
-        return async { Ok(module_source) }.boxed_local();
+    /// Get the runtime's op state, which is shared by all ops of the runtime. This can be used to
+    /// provide values to ops before any JavaScript is executed.
+    ///
+    /// @return \Deno\Core\OpState
+    fn op_state(&mut self) -> PhpResult<OpState> {
+        self.ensure_not_snapshotted()?;
+        Ok(OpState {
+            deno_op_state: self.deno_jsruntime.op_state(),
+        })
     }
 }
 
-/// Attempts to call a given PHP callable.
-///
-/// # Parameters
-///
-/// * `$fn` - The 'function' to call. Can be an [`Arg`] or a [`Zval`].
-/// * ...`$param` - The parameters to pass to the function. Must be able to be
-///   converted into a [`Zval`].
-///
-/// [`Arg`]: crate::args::Arg
-/// [`Zval`]: crate::types::Zval
-#[macro_export]
-macro_rules! call_user_method {
-    ($class: expr, $method: expr, $($param: expr),*) => {
-        {
-        let mut hashtable = ext_php_rs::types::ZendHashTable::new();
-        hashtable.insert_at_index(0, $class).ok();
-        hashtable.insert_at_index(1, $method).ok();
+impl JsRuntime {
+    fn take_snapshot(&mut self) -> PhpResult<v8::StartupData> {
+        self.ensure_not_snapshotted()?;
+        if self.will_snapshot == false {
+            return Err(
+                "Unable to shapshot JsRuntime when RuntimeOptions.will_snapshot is not true."
+                    .into(),
+            );
+        }
+        let startup_data = self.deno_jsruntime.snapshot();
+        self.has_snapshotted = true;
+        Ok(startup_data)
+    }
 
-        let result = hashtable.into_zval(false).unwrap().try_call(vec![$(&$param),*]);
+    /// Add an op to `Deno.core.ops`, calling the PHP function of `op_callback`.
+    fn add_op(&mut self, name: &str, op_callback: OpCallback) -> PhpResult<()> {
+        if !op_callback.callback.0.is_callable() {
+            return Err(format!("The callback for op \"{}\" is not callable.", name).into());
+        }
+        let op_state = self.deno_jsruntime.op_state();
+        let isolate = self.deno_jsruntime.v8_isolate();
+        let callbacks_slot = isolate
+            .get_slot::<std::rc::Rc<std::cell::RefCell<HashMap<String, OpCallback>>>>()
+            .unwrap()
+            .clone();
+        if callbacks_slot.borrow().contains_key(name) {
+            return Err(format!("Op \"{}\" is already registered.", name).into());
+        }
+        isolate.set_slot(RuntimeOpState(op_state));
 
-        // let result = ext_php_rs::call_user_func!(
-        //     hashtable.into_zval(false).unwrap(),
-        //     $(&$param),*
-        // );
+        let context = self.deno_jsruntime.global_context();
+        let mut scope = self.deno_jsruntime.handle_scope();
+        let ops = match deno_core_ops(&mut scope, &context) {
+            Some(ops) => ops,
+            None => return Err("Deno.core.ops is not available in this runtime.".into()),
+        };
+        let key = v8::String::new(&mut scope, name).unwrap();
+        let function = v8::Function::builder(registered_op_callback)
+            .data(key.into())
+            .build(&mut scope)
+            .unwrap();
+        ops.set(&mut scope, key.into(), function.into());
+        drop(scope);
 
-        result.ok()
+        self.registered_ops
+            .push((name.to_string(), op_callback.clone()));
+        callbacks_slot
+            .borrow_mut()
+            .insert(name.to_string(), op_callback);
+        match add_op_async_names(&mut self.deno_jsruntime, &[name]) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(self.php_exception(error)),
+        }
     }
-    };
-}
 
-/// JsFile is a descriptor for JavaScript files that are loaded as
-/// part of the Extension->js_files array. The `code` of `JsFile` is
-/// executed when the JsRuntime is initiated.
-#[php_class(name = "Deno\\Core\\JsFile")]
-#[derive(Clone, Debug)]
-struct JsFile {
-    /// The filename for the JS file
-    /// @var string
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    filename: String,
-    /// The code for the javascript file
-    /// @var string
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    code: String,
-}
+    /// Taking a snapshot consumes the isolate, so the runtime can not be used afterwards.
+    fn ensure_not_snapshotted(&self) -> PhpResult<()> {
+        if self.has_snapshotted {
+            return Err(
+                "The JsRuntime can not be used after it has been snapshotted, create a new JsRuntime from the snapshot instead."
+                    .into(),
+            );
+        }
+        Ok(())
+    }
 
-#[php_impl(rename_methods = "none")]
-impl JsFile {
-    #[constructor]
-    fn __construct(filename: String, code: String) -> Self {
-        Self { filename, code }
+    /// Convert an error returned from the deno_core runtime into a PHP exception.
+    fn php_exception(&mut self, error: Error) -> PhpException {
+        let termination_reason = self.termination_reason.lock().unwrap().take();
+        if let Some(message) = termination_reason {
+            self.deno_jsruntime.v8_isolate().cancel_terminate_execution();
+            return JsException {
+                message,
+                ..Default::default()
+            }
+            .into();
+        }
+
+        php_exception_from_error(error, self.format_js_error.as_ref())
+    }
+
+    /// Run `f`, terminating the isolate's execution if it has not completed after `timeout_ms`.
+    fn with_timeout<T>(
+        &mut self,
+        timeout_ms: Option<u64>,
+        f: impl FnOnce(&mut Self) -> PhpResult<T>,
+    ) -> PhpResult<T> {
+        let termination_reason = self.termination_reason.clone();
+        with_timeout(
+            self,
+            |runtime| &mut runtime.deno_jsruntime,
+            &termination_reason,
+            timeout_ms,
+            f,
+        )
     }
 }
 
-/// Extension contains PHP functions (ops) and associated js files which are
-/// exposed to JavaScript via the JsRuntime. PHP functions can be called from JavaScript
-/// via `Deno.core.$name` where `$name` is the array key string from the `ops` property.
+/// Run `f`, terminating the isolate's execution if it has not completed after `timeout_ms`, with
+/// the reason in `termination_reason` for the runtime's `php_exception()` to report.
+fn with_timeout<R, T>(
+    runtime: &mut R,
+    js_runtime: fn(&mut R) -> &mut deno_core::JsRuntime,
+    termination_reason: &std::sync::Arc<std::sync::Mutex<Option<String>>>,
+    timeout_ms: Option<u64>,
+    f: impl FnOnce(&mut R) -> PhpResult<T>,
+) -> PhpResult<T> {
+    let timeout_ms = match timeout_ms {
+        Some(timeout_ms) => timeout_ms,
+        None => return f(runtime),
+    };
+
+    let isolate_handle = js_runtime(runtime).v8_isolate().thread_safe_handle();
+    let watchdog_termination_reason = termination_reason.clone();
+    let (done_sender, done_receiver) = std::sync::mpsc::channel::<()>();
+    let watchdog = std::thread::spawn(move || {
+        let timeout = std::time::Duration::from_millis(timeout_ms);
+        if let Err(std::sync::mpsc::RecvTimeoutError::Timeout) =
+            done_receiver.recv_timeout(timeout)
+        {
+            *watchdog_termination_reason.lock().unwrap() =
+                Some(format!("Script execution timed out after {}ms.", timeout_ms));
+            isolate_handle.terminate_execution();
+        }
+    });
+
+    let result = f(runtime);
+    drop(done_sender);
+    watchdog.join().ok();
+
+    // The watchdog may have fired after `f` returned, but before it was stopped. Whether `f`
+    // succeeded or failed, the pending termination would otherwise kill the next call.
+    termination_reason.lock().unwrap().take();
+    js_runtime(runtime).v8_isolate().cancel_terminate_execution();
+    result
+}
+
+/// Builds a V8 snapshot with extensions and setup scripts in one call, for example to bake an
+/// application's extensions and libraries into a snapshot that runtimes start from quickly. This
+/// takes care of creating a runtime with `RuntimeOptions::will_snapshot` and running the scripts.
 ///
-/// It's common to provide `ops` and also more user-friendly accessible functions for those
-/// `ops` via the `js_files` property.
-#[php_class(name = "Deno\\Core\\Extension")]
-#[derive(Clone, Debug)]
-struct Extension {
-    /// The JS files that should be loaded into the V8 Isolate.
-    /// @var Deno\Core\JsFile[]
+/// The snapshot does not contain the extensions' PHP ops, so runtimes created from it must be
+/// given the same `extensions`, in the same order. Only scripts can be snapshotted, not ES modules;
+/// use `Deno\AST\bundle()` and `Deno\AST\ParsedSource::strip_types()` to prepare them as needed.
+#[php_class(name = "Deno\\Core\\SnapshotBuilder")]
+#[derive(Debug)]
+struct SnapshotBuilder {
+    /// The extensions to initialize before the scripts are run.
+    /// @var Deno\Core\Extension[]
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    js_files: Vec<JsFile>,
-    /// The ops for the extension (bridged to PHP functions)
-    /// @var array<string, callable>
+    extensions: Vec<Extension>,
+    /// The scripts to run, in order, before the snapshot is taken.
+    /// @var Deno\Core\JsFile[]
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    ops: HashMap<String, CloneableZval>,
+    scripts: Vec<JsFile>,
 }
 
 #[php_impl(rename_methods = "none")]
-impl Extension {
+impl SnapshotBuilder {
+    /// @param Deno\Core\Extension[] $extensions
+    /// @param Deno\Core\JsFile[] $scripts
     #[constructor]
-    fn __construct() -> Self {
+    fn __construct(extensions: Vec<Extension>, scripts: Vec<JsFile>) -> Self {
         Self {
-            js_files: vec![],
-            ops: HashMap::new(),
+            extensions,
+            scripts,
         }
     }
-}
 
-impl From<Extension> for deno_core::Extension {
-    fn from(extension: Extension) -> Self {
-        use deno_core::v8::MapFnTo;
-        let js_files = extension
-            .js_files
-            .iter()
-            .map(|js_file| -> (&str, &str) {
-                // This causes a memory leak, but the js-files exntesion requires static strings so there's not much we can do.
-                let filename: &'static str = Box::leak(js_file.filename.clone().into_boxed_str());
-                let code: &'static str = Box::leak(js_file.code.clone().into_boxed_str());
-                (filename, code)
-            })
-            .collect();
-        let mut ops: Vec<deno_core::OpDecl> = vec![];
-        for (name, _op) in &extension.ops {
-            let static_name: &'static str = Box::leak(name.clone().into_boxed_str());
-            let op_decl = deno_core::OpDecl {
-                name: static_name,
-                v8_fn_ptr: op_callback.map_fn_to(),
-                enabled: true,
-                fast_fn: None,
-                is_async: false,
-                is_unstable: false,
-                is_v8: false,
-            };
+    /// Build the snapshot, which can be used for `RuntimeOptions::startup_snapshot`.
+    ///
+    /// @return string
+    fn build(&self) -> PhpResult<Zval> {
+        let startup_data = self.build_startup_data()?;
+        let mut zval = Zval::new();
+        zval.set_binary(startup_data.to_vec());
+        Ok(zval)
+    }
 
-            ops.push(op_decl);
+    /// Build the snapshot and write it to a file, which can be loaded with
+    /// `RuntimeOptions::set_startup_snapshot_from_file()`.
+    fn build_to_file(&self, path: &str) -> PhpResult<()> {
+        let startup_data = self.build_startup_data()?;
+        match std::fs::write(path, &*startup_data) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(format!("Unable to write snapshot file {}: {}", path, error).into()),
         }
-        deno_core::Extension::builder()
-            .js(js_files)
-            .ops(ops)
-            .build()
     }
 }
 
-impl From<&Extension> for deno_core::Extension {
-    fn from(extension: &Extension) -> Self {
-        extension.clone().into()
+impl SnapshotBuilder {
+    fn build_startup_data(&self) -> PhpResult<v8::StartupData> {
+        let mut options = RuntimeOptions::__construct();
+        options.extensions = self.extensions.clone();
+        options.will_snapshot = true;
+        let mut runtime = JsRuntime::__construct(&options)?;
+        for script in &self.scripts {
+            runtime.execute_script(&script.filename, &script.code, None)?;
+        }
+        runtime.take_snapshot()
     }
 }
 
-impl FromZval<'_> for Extension {
-    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
-    fn from_zval(zval: &'_ Zval) -> Option<Self> {
-        let extension: &Extension = zval.extract().unwrap();
-        let new_extension = extension.to_owned();
-        Some(new_extension)
-    }
+/// Runs an ES module on a dedicated OS thread, with its own isolate and event loop, so PHP can
+/// start JavaScript and poll for its completion rather than being blocked until it finishes. For
+/// example, a web server can bound the time a request spends in JavaScript with `wait()` and
+/// `terminate()`, where the other runtimes' methods block the PHP worker for as long as it runs.
+///
+/// A V8 isolate is not `Send`: it can only be used by the thread that created it. PHP values can
+/// only be used by PHP's thread too, so the thread's runtime is entirely separate from PHP. It has
+/// no PHP ops, no PHP module loader and no access to the PHP objects of other runtimes; only
+/// `Deno.core` is available to the module, and modules can only be imported when a
+/// `Deno\Core\FsModuleLoader` is given. The module's default export is passed back to PHP as JSON,
+/// so it must be JSON serializable, and is converted to PHP values in the same way as the return
+/// values of the other runtimes.
+#[php_class(name = "Deno\\Core\\JsThread")]
+struct JsThread {
+    isolate_handle: v8::IsolateHandle,
+    /// Stops the thread's event loop, which `terminate_execution()` doesn't while it is waiting.
+    cancel_sender: Option<tokio::sync::oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+    result_receiver: std::sync::mpsc::Receiver<Result<deno_core::serde_json::Value, Error>>,
+    result: Option<Result<deno_core::serde_json::Value, Error>>,
+    is_finished: bool,
+    is_terminated: bool,
 }
 
-impl FromZval<'_> for BootstrapOptions {
-    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
-    fn from_zval(zval: &'_ Zval) -> Option<Self> {
-        let bootstrap: &BootstrapOptions = zval.extract().unwrap();
-        let new_bootstrap = bootstrap.to_owned();
-        Some(new_bootstrap)
+#[php_impl(rename_methods = "none")]
+impl JsThread {
+    /// Start executing `source_code` as the main module `specifier` on a new thread.
+    #[constructor]
+    #[optional(module_loader)]
+    fn __construct(
+        specifier: &str,
+        source_code: &str,
+        module_loader: Option<&FsModuleLoader>,
+    ) -> PhpResult<Self> {
+        let specifier = match url::Url::parse(specifier) {
+            Ok(specifier) => specifier,
+            Err(error) => return Err(format!("Invalid specifier {}: {}", specifier, error).into()),
+        };
+        let source_code = source_code.to_string();
+        let module_loader = module_loader.cloned();
+        let (isolate_handle_sender, isolate_handle_receiver) = std::sync::mpsc::sync_channel(1);
+        let (result_sender, result_receiver) = std::sync::mpsc::sync_channel(1);
+        let (cancel_sender, cancel_receiver) = tokio::sync::oneshot::channel();
+
+        V8_INITIALIZED.store(true, std::sync::atomic::Ordering::SeqCst);
+        let thread = std::thread::spawn(move || {
+            let result = run_js_thread(
+                &specifier,
+                source_code,
+                module_loader,
+                isolate_handle_sender,
+                cancel_receiver,
+            );
+            result_sender.send(result).ok();
+        });
+        // The isolate is created on the thread, so wait for it, to be able to terminate it.
+        let isolate_handle = match isolate_handle_receiver.recv() {
+            Ok(isolate_handle) => isolate_handle,
+            Err(_) => {
+                let message = match thread.join() {
+                    Err(panic) => panic_message(&*panic),
+                    Ok(()) => "Unknown error.".to_string(),
+                };
+                return Err(format!("Unable to start JsThread: {}", message).into());
+            }
+        };
+
+        Ok(Self {
+            isolate_handle,
+            cancel_sender: Some(cancel_sender),
+            thread: Some(thread),
+            result_receiver,
+            result: None,
+            is_finished: false,
+            is_terminated: false,
+        })
     }
-}
 
-impl FromZval<'_> for JsFile {
-    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
-    fn from_zval(zval: &'_ Zval) -> Option<Self> {
-        let file: &JsFile = zval.extract().unwrap();
-        let new_file = file.to_owned();
-        Some(new_file)
+    /// Whether the module has finished executing, including its event loop. This does not block.
+    fn is_finished(&mut self) -> bool {
+        self.receive_result(Some(std::time::Duration::ZERO))
+    }
+
+    /// Wait up to `timeout_ms` milliseconds for the module to finish executing, returning whether
+    /// it has finished.
+    fn wait(&mut self, timeout_ms: u64) -> bool {
+        self.receive_result(Some(std::time::Duration::from_millis(timeout_ms)))
+    }
+
+    /// Terminate the execution of the module, whether it is running JavaScript or waiting in the
+    /// event loop, after which `join()` throws a `Deno\Core\JsException`. This does nothing when
+    /// the module has already finished.
+    fn terminate(&mut self) {
+        if !self.receive_result(Some(std::time::Duration::ZERO)) {
+            self.is_terminated = true;
+            self.stop();
+        }
+    }
+
+    /// Wait for the module to finish executing, and return its default export. Throws a
+    /// `Deno\Core\JsException` when the module throws or was terminated. The result can only be
+    /// taken once.
+    ///
+    /// @return mixed
+    fn join(&mut self) -> PhpResult<Zval> {
+        self.receive_result(None);
+        match self.result.take() {
+            Some(Ok(value)) => Ok(zval_from_json(value)),
+            Some(Err(_)) if self.is_terminated => Err(JsException {
+                message: "Execution of the JsThread was terminated.".to_string(),
+                ..Default::default()
+            }
+            .into()),
+            Some(Err(error)) => Err(php_exception_from_error(error, None)),
+            None => Err("The result of the JsThread has already been taken.".into()),
+        }
     }
 }
 
-/// ModuleSource represents an ES6 module, including the source code and type. An ModuleSource should
-/// be returned from your module loader passed to JsRuntime's RuntimeOptions::module_loader property.
+impl JsThread {
+    /// Stop the thread, both the JavaScript it is running and its event loop.
+    fn stop(&mut self) {
+        if let Some(cancel_sender) = self.cancel_sender.take() {
+            cancel_sender.send(()).ok();
+        }
+        self.isolate_handle.terminate_execution();
+    }
+
+    /// Receive the result from the thread, waiting up to `timeout`, or until the thread has
+    /// finished when it is `None`. Returns whether the thread has finished.
+    fn receive_result(&mut self, timeout: Option<std::time::Duration>) -> bool {
+        if self.is_finished {
+            return true;
+        }
+        let result = match timeout {
+            Some(timeout) => match self.result_receiver.recv_timeout(timeout) {
+                Ok(result) => Ok(result),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => return false,
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => Err(()),
+            },
+            None => self.result_receiver.recv().map_err(|_| ()),
+        };
+        // The thread only disconnects without sending a result when it panics.
+        let result = result.unwrap_or_else(|()| {
+            let message = match self.thread.take().map(|thread| thread.join()) {
+                Some(Err(panic)) => panic_message(&*panic),
+                _ => "Unknown error.".to_string(),
+            };
+            Err(anyhow::anyhow!("The JsThread panicked: {}", message))
+        });
+        self.result = Some(result);
+        self.is_finished = true;
+        true
+    }
+}
+
+impl Drop for JsThread {
+    /// An unfinished thread is terminated before it is joined, so that dropping it does not block
+    /// PHP for longer than it takes the thread to stop.
+    fn drop(&mut self) {
+        if !self.receive_result(Some(std::time::Duration::ZERO)) {
+            self.stop();
+        }
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+/// Execute the main module of a `JsThread` on the current thread, returning its default export.
+fn run_js_thread(
+    specifier: &deno_core::ModuleSpecifier,
+    source_code: String,
+    module_loader: Option<FsModuleLoader>,
+    isolate_handle_sender: std::sync::mpsc::SyncSender<v8::IsolateHandle>,
+    cancel_receiver: tokio::sync::oneshot::Receiver<()>,
+) -> Result<deno_core::serde_json::Value, Error> {
+    let module_loader: std::rc::Rc<dyn deno_core::ModuleLoader> = match &module_loader {
+        Some(module_loader) => std::rc::Rc::new(FsLoader::new(module_loader)),
+        None => std::rc::Rc::new(deno_core::NoopModuleLoader),
+    };
+    let mut js_runtime = deno_core::JsRuntime::new(deno_core::RuntimeOptions {
+        module_loader: Some(module_loader),
+        ..Default::default()
+    });
+    isolate_handle_sender
+        .send(js_runtime.v8_isolate().thread_safe_handle())
+        .ok();
+
+    let tokio_runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    tokio_runtime.block_on(async {
+        tokio::select! {
+            result = evaluate_js_thread_module(&mut js_runtime, specifier, source_code) => result,
+            _ = cancel_receiver => anyhow::bail!("Execution of the JsThread was terminated."),
+        }
+    })
+}
+
+async fn evaluate_js_thread_module(
+    js_runtime: &mut deno_core::JsRuntime,
+    specifier: &deno_core::ModuleSpecifier,
+    source_code: String,
+) -> Result<deno_core::serde_json::Value, Error> {
+    let id = js_runtime
+        .load_main_module(specifier, Some(source_code))
+        .await?;
+    let result = js_runtime.mod_evaluate(id);
+    js_runtime.run_event_loop(false).await?;
+    match result.await {
+        Ok(result) => result?,
+        Err(_canceled) => anyhow::bail!("Evaluation of module {} was canceled.", specifier),
+    };
+
+    let namespace = js_runtime.get_module_namespace(id)?;
+    let scope = &mut js_runtime.handle_scope();
+    let namespace = v8::Local::new(scope, namespace);
+    let key = v8::String::new(scope, "default").unwrap();
+    match namespace.get(scope, key.into()) {
+        Some(value) if !value.is_undefined() => Ok(deno_core::serde_v8::from_v8(scope, value)?),
+        _ => Ok(deno_core::serde_json::Value::Null),
+    }
+}
+
+/// Whether a JsRuntime or MainWorker has been created, after which V8 flags can no longer be set.
+static V8_INITIALIZED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Set V8 flags, such as `--expose-gc`, `--jitless` or `--max-old-space-size=512`. V8 flags apply
+/// to all isolates in the process, so they must be set before the first `Deno\Core\JsRuntime` or
+/// `Deno\Runtime\MainWorker` is created. Throws an exception if any flag is not recognized by V8.
+///
+/// @param string[] $flags
+#[php_function(ignore_module, name = "Deno\\Core\\set_v8_flags")]
+fn set_v8_flags(flags: Vec<String>) -> PhpResult<()> {
+    if V8_INITIALIZED.load(std::sync::atomic::Ordering::SeqCst) {
+        return Err(
+            "V8 flags must be set before the first JsRuntime or MainWorker is created.".into(),
+        );
+    }
+    // V8 parses the flags like a command line, so the first element is the program name, which
+    // is always returned as unrecognized.
+    let argv = std::iter::once("php".to_string()).chain(flags).collect();
+    let unrecognized_flags: Vec<String> =
+        deno_core::v8_set_flags(argv).into_iter().skip(1).collect();
+    if !unrecognized_flags.is_empty() {
+        return Err(format!("Unrecognized V8 flags: {}", unrecognized_flags.join(" ")).into());
+    }
+    Ok(())
+}
+
+/// Create the Tokio runtime that drives a runtime's event loop. The same Tokio runtime has to be
+/// used for every call, as timers and other async resources are registered with the Tokio runtime
+/// that was current when they were created, and would never complete under a different one.
+fn new_tokio_runtime() -> std::rc::Rc<tokio::runtime::Runtime> {
+    std::rc::Rc::new(tokio::runtime::Runtime::new().unwrap())
+}
+
+/// The source maps of the scripts and modules executed in a runtime, keyed by their file name.
+/// deno_core uses these to map the frames of a `JsError` back to the original source. Source maps
+/// that have not been added are requested from the PHP module loader's `get_source_map()` method.
+///
+/// Only the decoded source maps are kept, not the source code. Inline source maps are added for
+/// every script, so only the most recent `MAX_INLINE_SOURCE_MAPS` of them are kept, which bounds
+/// the memory of a long-lived runtime that executes many differently named scripts.
+#[derive(Clone, Default)]
+struct SourceMaps {
+    source_maps: std::rc::Rc<std::cell::RefCell<HashMap<String, Vec<u8>>>>,
+    /// The file names of the inline source maps, oldest first.
+    inline_file_names: std::rc::Rc<std::cell::RefCell<std::collections::VecDeque<String>>>,
+    module_loader: Option<CloneableZval>,
+}
+
+const MAX_INLINE_SOURCE_MAPS: usize = 1000;
+
+impl SourceMaps {
+    fn new(module_loader: Option<CloneableZval>) -> Self {
+        Self {
+            source_maps: Default::default(),
+            inline_file_names: Default::default(),
+            module_loader,
+        }
+    }
+
+    fn add(&self, file_name: &str, source_map: Vec<u8>) {
+        self.remove_inline(file_name);
+        self.source_maps
+            .borrow_mut()
+            .insert(file_name.to_string(), source_map);
+    }
+
+    /// Add the source map from a `//# sourceMappingURL=data:...` comment in the source code. A
+    /// previous inline source map for the same file name is removed when the code has none.
+    fn add_inline(&self, file_name: &str, source_code: &str) {
+        let source_map = source_code
+            .rsplit_once("//# sourceMappingURL=")
+            .map(|(_, url)| url.lines().next().unwrap_or_default().trim())
+            .and_then(|url| data_url::DataUrl::process(url).ok())
+            .and_then(|data_url| data_url.decode_to_vec().ok());
+        if self.remove_inline(file_name) {
+            self.source_maps.borrow_mut().remove(file_name);
+        }
+        let (source_map, _) = match source_map {
+            Some(source_map) => source_map,
+            None => return,
+        };
+        let mut inline_file_names = self.inline_file_names.borrow_mut();
+        let mut source_maps = self.source_maps.borrow_mut();
+        if inline_file_names.len() >= MAX_INLINE_SOURCE_MAPS {
+            if let Some(oldest) = inline_file_names.pop_front() {
+                source_maps.remove(&oldest);
+            }
+        }
+        inline_file_names.push_back(file_name.to_string());
+        source_maps.insert(file_name.to_string(), source_map);
+    }
+
+    /// Stop tracking the inline source map of a file name, returning whether there was one.
+    fn remove_inline(&self, file_name: &str) -> bool {
+        let mut inline_file_names = self.inline_file_names.borrow_mut();
+        match inline_file_names.iter().position(|name| name == file_name) {
+            Some(index) => {
+                inline_file_names.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl deno_core::SourceMapGetter for SourceMaps {
+    fn get_source_map(&self, file_name: &str) -> Option<Vec<u8>> {
+        // Reloaded modules have the source map of the module they are reloaded from.
+        let file_name = &match deno_core::ModuleSpecifier::parse(file_name) {
+            Ok(specifier) => original_specifier(&specifier).to_string(),
+            Err(_) => file_name.to_string(),
+        };
+        if let Some(source_map) = self.source_maps.borrow().get(file_name) {
+            return Some(source_map.clone());
+        }
+
+        // get_source_map() is optional for module loaders, so it not being callable is the same
+        // as there being no source map.
+        let module_loader = self.module_loader.as_ref()?;
+        let result = call_user_method!(
+            module_loader.clone().into_zval(false).unwrap(),
+            "get_source_map",
+            file_name
+        )?;
+        result.string().map(|source_map| source_map.into_bytes())
+    }
+
+    fn get_source_line(&self, _file_name: &str, _line_number: usize) -> Option<String> {
+        None
+    }
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Unknown error.".to_string()
+    }
+}
+
+async fn call_global_function(
+    js_runtime: &mut deno_core::JsRuntime,
+    name: &str,
+    args: &[CloneableZval],
+) -> Result<Zval, Error> {
+    let function = {
+        let context = js_runtime.global_context();
+        let scope = &mut js_runtime.handle_scope();
+        let global = context.open(scope).global(scope);
+        let key = v8::String::new(scope, name).unwrap();
+        match global
+            .get(scope, key.into())
+            .and_then(|function| v8::Local::<v8::Function>::try_from(function).ok())
+        {
+            Some(function) => v8::Global::new(scope, function),
+            None => anyhow::bail!("{} is not a global function.", name),
+        }
+    };
+    call_function(js_runtime, &function, args).await
+}
+
+/// Call a JavaScript function with `undefined` as `this`, running the event loop until a returned
+/// Promise settles.
+async fn call_function(
+    js_runtime: &mut deno_core::JsRuntime,
+    function: &v8::Global<v8::Function>,
+    args: &[CloneableZval],
+) -> Result<Zval, Error> {
+    let _call_depth = CallDepthGuard::enter(js_runtime.v8_isolate()).map_err(Error::msg)?;
+    let return_value = {
+        let scope = &mut js_runtime.handle_scope();
+        let function = v8::Local::new(scope, function);
+        let args: Vec<v8::Local<v8::Value>> = args
+            .iter()
+            .map(|arg| js_value_from_zval(scope, &arg.0))
+            .collect();
+
+        let tc_scope = &mut v8::TryCatch::new(scope);
+        let this = v8::undefined(tc_scope).into();
+        match function.call(tc_scope, this, &args) {
+            Some(return_value) => v8::Global::new(tc_scope, return_value),
+            // A terminated call, such as one that timed out, has no exception.
+            None => match tc_scope.exception() {
+                Some(exception) => {
+                    return Err(
+                        deno_core::error::JsError::from_v8_exception(tc_scope, exception).into(),
+                    )
+                }
+                None => anyhow::bail!("Execution of the function was terminated."),
+            },
+        }
+    };
+
+    // Runs the event loop (and microtasks) until a returned Promise settles.
+    let return_value = js_runtime.resolve_value(return_value).await?;
+    let scope = &mut js_runtime.handle_scope();
+    let return_value = v8::Local::new(scope, return_value);
+    Ok(zval_from_jsvalue(return_value, scope))
+}
+
+/// Run the event loop until all pending async actions have completed. An unhandled Promise
+/// rejection stops the event loop with an error, unless the runtime has an `on_unhandled_rejection`
+/// callable, which is called with the rejection instead so the event loop can continue.
+async fn run_event_loop(js_runtime: &mut deno_core::JsRuntime) -> Result<(), Error> {
+    loop {
+        match js_runtime.run_event_loop(false).await {
+            Ok(()) => return Ok(()),
+            Err(error) => report_unhandled_rejection(js_runtime, error)?,
+        }
+    }
+}
+
+/// The callable of `RuntimeOptions::on_unhandled_rejection`, stored in the isolate's slot.
+struct OnUnhandledRejection(CloneableZval);
+
+/// Pass an unhandled Promise rejection to the runtime's `on_unhandled_rejection` callable. Other
+/// errors, and rejections when there is no callable, are returned.
+fn report_unhandled_rejection(
+    js_runtime: &mut deno_core::JsRuntime,
+    error: Error,
+) -> Result<(), Error> {
+    let on_unhandled_rejection = match js_runtime.v8_isolate().get_slot::<OnUnhandledRejection>() {
+        Some(on_unhandled_rejection) => on_unhandled_rejection.0.clone(),
+        None => return Err(error),
+    };
+    // deno_core reports unhandled rejections as a JsError with this prefix.
+    let js_error = match error.downcast::<deno_core::error::JsError>() {
+        Ok(js_error)
+            if js_error
+                .exception_message
+                .starts_with("Uncaught (in promise)") =>
+        {
+            js_error
+        }
+        Ok(js_error) => return Err(js_error.into()),
+        Err(error) => return Err(error),
+    };
+    // Converted like thrown exceptions, with the `Deno\Errors\*` class and the chain of causes.
+    let js_exception = CloneableZval(JsException::from(js_error.clone()).into_exception_zval());
+    match on_unhandled_rejection.0.try_call(vec![&js_exception]) {
+        Ok(_) => Ok(()),
+        // The rejection is thrown to PHP if the callable fails, so it isn't lost.
+        Err(_) => Err(js_error.into()),
+    }
+}
+
+/// Run an event loop until it has no more pending work, or stop it once `timeout_ms` has passed.
+/// Returns whether the event loop completed, rather than timing out with work still pending.
+async fn run_event_loop_with_timeout(
+    event_loop: impl std::future::Future<Output = Result<(), Error>>,
+    timeout_ms: Option<u64>,
+) -> Result<bool, Error> {
+    let timeout_ms = match timeout_ms {
+        Some(timeout_ms) => timeout_ms,
+        None => return event_loop.await.map(|()| true),
+    };
+    let timeout = std::time::Duration::from_millis(timeout_ms);
+    match tokio::time::timeout(timeout, event_loop).await {
+        Ok(result) => result.map(|()| true),
+        Err(_elapsed) => Ok(false),
+    }
+}
+
+/// Poll the event loop once without waiting, returning whether it still has pending work.
+async fn has_pending_work(js_runtime: &mut deno_core::JsRuntime) -> Result<bool, Error> {
+    let poll = futures::future::poll_fn(|cx| {
+        std::task::Poll::Ready(js_runtime.poll_event_loop(cx, false))
+    })
+    .await;
+    match poll {
+        std::task::Poll::Ready(result) => result.map(|()| false),
+        std::task::Poll::Pending => Ok(true),
+    }
+}
+
+/// Run the tests registered with the `Deno.test()` of `TESTING_JS`, returning their results.
+async fn run_tests(js_runtime: &mut deno_core::JsRuntime) -> Result<Zval, Error> {
+    let results = js_runtime.execute_script("ext:php_testing/run.js", RUN_TESTS_JS)?;
+    let results = js_runtime.resolve_value(results).await?;
+    let scope = &mut js_runtime.handle_scope();
+    let results = v8::Local::new(scope, results);
+    let results = match v8::Local::<v8::Array>::try_from(results) {
+        Ok(results) => results,
+        Err(_) => anyhow::bail!("The test results are not an array."),
+    };
+
+    // The results are converted to arrays, rather than to objects as JavaScript objects usually are.
+    let mut php_results = ext_php_rs::types::ZendHashTable::new();
+    for index in 0..results.length() {
+        let result = match results
+            .get_index(scope, index)
+            .and_then(|result| v8::Local::<v8::Object>::try_from(result).ok())
+        {
+            Some(result) => result,
+            None => anyhow::bail!("The test result {} is not an object.", index),
+        };
+        let mut php_result = ext_php_rs::types::ZendHashTable::new();
+        for key in ["name", "status", "duration_ms", "error"] {
+            let js_key = v8::String::new(scope, key).unwrap();
+            let value = match result.get(scope, js_key.into()) {
+                Some(value) => value,
+                None => v8::null(scope).into(),
+            };
+            php_result
+                .insert(key, zval_from_jsvalue(value, scope))
+                .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+        }
+        php_results
+            .push(php_result)
+            .map_err(|error| anyhow::anyhow!(error.to_string()))?;
+    }
+    let mut zval = Zval::new();
+    zval.set_hashtable(php_results);
+    Ok(zval)
+}
+
+/// Execute a script, returning its completion value converted to a PHP value.
+fn evaluate(
+    js_runtime: &mut deno_core::JsRuntime,
+    name: &str,
+    source_code: &str,
+) -> Result<Zval, Error> {
+    let completion_value = js_runtime.execute_script(name, source_code)?;
+    let scope = &mut js_runtime.handle_scope();
+    let completion_value = v8::Local::new(scope, completion_value);
+    Ok(zval_from_jsvalue(completion_value, scope))
+}
+
+async fn execute_repl(js_runtime: &mut deno_core::JsRuntime, script: &str) -> Result<Zval, Error> {
+    let return_value = js_runtime.execute_script("repl", script)?;
+    let return_value = js_runtime.resolve_value(return_value).await?;
+    let scope = &mut js_runtime.handle_scope();
+    let return_value = v8::Local::new(scope, return_value);
+    Ok(zval_from_jsvalue(return_value, scope))
+}
+
+async fn execute_module(
+    js_runtime: &mut deno_core::JsRuntime,
+    specifier: &str,
+    source_code: &str,
+) -> Result<Zval, Error> {
+    let specifier = url::Url::parse(specifier)?;
+    let id = js_runtime
+        .load_side_module(&specifier, Some(source_code.to_string()))
+        .await?;
+    let result = js_runtime.mod_evaluate(id);
+    run_event_loop(js_runtime).await?;
+    match result.await {
+        Ok(result) => result?,
+        Err(_canceled) => anyhow::bail!("Evaluation of module {} was canceled.", specifier),
+    };
+    default_export(js_runtime, id)
+}
+
+/// Get the default export of an evaluated module converted to a PHP value, or null if it has none.
+fn default_export(
+    js_runtime: &mut deno_core::JsRuntime,
+    id: deno_core::ModuleId,
+) -> Result<Zval, Error> {
+    let namespace = js_runtime.get_module_namespace(id)?;
+    let scope = &mut js_runtime.handle_scope();
+    let namespace = v8::Local::new(scope, namespace);
+    let key = v8::String::new(scope, "default").unwrap();
+    match namespace.get(scope, key.into()) {
+        Some(value) if !value.is_undefined() => Ok(zval_from_jsvalue(value, scope)),
+        _ => {
+            let mut zval = Zval::new();
+            zval.set_null();
+            Ok(zval)
+        }
+    }
+}
+
+struct ValueSerializerDelegate;
+
+impl v8::ValueSerializerImpl for ValueSerializerDelegate {
+    fn throw_data_clone_error<'s>(
+        &mut self,
+        scope: &mut v8::HandleScope<'s>,
+        message: v8::Local<'s, v8::String>,
+    ) {
+        let error = v8::Exception::error(scope, message);
+        scope.throw_exception(error);
+    }
+}
+
+struct ValueDeserializerDelegate;
+
+impl v8::ValueDeserializerImpl for ValueDeserializerDelegate {}
+
+fn set_global(
+    js_runtime: &mut deno_core::JsRuntime,
+    name: &str,
+    value: &Zval,
+) -> Result<(), Error> {
+    let context = js_runtime.global_context();
+    let scope = &mut js_runtime.handle_scope();
+    let global = context.open(scope).global(scope);
+    let key = v8::String::new(scope, name).unwrap();
+    let value = js_value_from_zval(scope, value);
+    match global.set(scope, key.into(), value) {
+        Some(_) => Ok(()),
+        None => anyhow::bail!("Unable to set the global {}.", name),
+    }
+}
+
+fn serialize_value(js_runtime: &mut deno_core::JsRuntime, name: &str) -> Result<Vec<u8>, Error> {
+    let context = js_runtime.global_context();
+    let scope = &mut js_runtime.handle_scope();
+    let context = v8::Local::new(scope, context);
+    let global = context.global(scope);
+    let key = v8::String::new(scope, name).unwrap();
+    let value = match global.get(scope, key.into()) {
+        Some(value) => value,
+        None => v8::undefined(scope).into(),
+    };
+
+    let tc_scope = &mut v8::TryCatch::new(scope);
+    let mut serializer = v8::ValueSerializer::new(tc_scope, Box::new(ValueSerializerDelegate));
+    serializer.write_header();
+    match serializer.write_value(context, value) {
+        Some(true) => Ok(serializer.release()),
+        _ => match tc_scope.exception() {
+            Some(exception) => {
+                Err(deno_core::error::JsError::from_v8_exception(tc_scope, exception).into())
+            }
+            None => Err(anyhow::anyhow!("Unable to serialize {}.", name)),
+        },
+    }
+}
+
+fn deserialize_value(
+    js_runtime: &mut deno_core::JsRuntime,
+    name: &str,
+    data: &[u8],
+) -> Result<(), Error> {
+    let context = js_runtime.global_context();
+    let scope = &mut js_runtime.handle_scope();
+    let context = v8::Local::new(scope, context);
+    let global = context.global(scope);
+    let key = v8::String::new(scope, name).unwrap();
+
+    let tc_scope = &mut v8::TryCatch::new(scope);
+    let mut deserializer =
+        v8::ValueDeserializer::new(tc_scope, Box::new(ValueDeserializerDelegate), data);
+    let value = match deserializer.read_header(context) {
+        Some(true) => deserializer.read_value(context),
+        _ => None,
+    };
+    match value {
+        Some(value) => {
+            global.set(tc_scope, key.into(), value);
+            Ok(())
+        }
+        None => match tc_scope.exception() {
+            Some(exception) => {
+                Err(deno_core::error::JsError::from_v8_exception(tc_scope, exception).into())
+            }
+            None => Err(anyhow::anyhow!("Unable to deserialize {}.", name)),
+        },
+    }
+}
+
+fn get_heap_statistics(isolate: &mut v8::Isolate) -> HashMap<&'static str, usize> {
+    let mut stats = v8::HeapStatistics::default();
+    isolate.get_heap_statistics(&mut stats);
+
+    HashMap::from([
+        ("total_heap_size", stats.total_heap_size()),
+        ("total_heap_size_executable", stats.total_heap_size_executable()),
+        ("total_physical_size", stats.total_physical_size()),
+        ("total_available_size", stats.total_available_size()),
+        ("used_heap_size", stats.used_heap_size()),
+        ("heap_size_limit", stats.heap_size_limit()),
+        ("malloced_memory", stats.malloced_memory()),
+        ("external_memory", stats.external_memory()),
+        ("peak_malloced_memory", stats.peak_malloced_memory()),
+        ("number_of_native_contexts", stats.number_of_native_contexts()),
+        ("number_of_detached_contexts", stats.number_of_detached_contexts()),
+    ])
+}
+/// The `Deno.core.ops` object of a runtime's global context, if it is available.
+fn deno_core_ops<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    context: &v8::Global<v8::Context>,
+) -> Option<v8::Local<'s, v8::Object>> {
+    let global = context.open(scope).global(scope);
+    let mut ops: v8::Local<v8::Value> = global.into();
+    for key in ["Deno", "core", "ops"] {
+        let key = v8::String::new(scope, key).unwrap();
+        ops = v8::Local::<v8::Object>::try_from(ops)
+            .ok()?
+            .get(scope, key.into())?;
+    }
+    v8::Local::<v8::Object>::try_from(ops).ok()
+}
+
+/// The names of the ops in a runtime's `Deno.core.ops`, sorted.
+fn op_names(js_runtime: &mut deno_core::JsRuntime) -> Result<Vec<String>, Error> {
+    let context = js_runtime.global_context();
+    let scope = &mut js_runtime.handle_scope();
+    let ops = match deno_core_ops(scope, &context) {
+        Some(ops) => ops,
+        None => anyhow::bail!("Deno.core.ops is not available in this runtime."),
+    };
+    let mut names = vec![];
+    if let Some(keys) = ops.get_own_property_names(scope) {
+        for index in 0..keys.length() {
+            let key = keys.get_index(scope, index).unwrap();
+            names.push(key.to_rust_string_lossy(scope));
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// The module loader interface (don't trust the docs, this is an interface not a class!)
+/// Pass an instance of your class that implements `Deno\Core\ModuleLoader` to the `module_loader`
+/// property of `Deno\Runtime\WorkerOptions` or `Deno\Core\RuntimeOptions`
+///
+/// `data:` URL modules, such as `data:application/javascript,export default 1`, are resolved and
+/// loaded without calling the module loader.
+///
+/// Module loaders that transpile modules can also define a `get_source_map(string $specifier): ?string`
+/// method, which is not part of the interface so existing module loaders keep working. It is
+/// called with the specifier of a module when a stack trace includes one of its frames, and should
+/// return the module's source map, so the frames point at the original source (such as a `.ts` file)
+/// instead of the transpiled code.
+#[php_class(name = "Deno\\Core\\ModuleLoader", flags = "Interface")]
+#[derive(Clone, Debug)]
+struct ModuleLoaderInterface {}
+
+#[php_impl(rename_methods = "none")]
+impl ModuleLoaderInterface {
+    /// The `resolve` method should take a module specifier and normalize it to a canonical URL.
+    /// @return string
+    #[php_method]
+    #[abstract_method]
+    fn resolve(&self, _specifier: &str, _referrer: &str) -> &str {
+        ""
+    }
+
+    /// The `load` method takes a module specifier and should return the contents for a module.
+    /// See `Deno\Core\ModuleSource` for the specifics.
+    ///
+    /// `load` is also passed whether the module is loaded by a dynamic `import()` as a second
+    /// argument, which implementations can accept as `bool $is_dyn_import = false`. To refuse to
+    /// load a module, such as one a security policy disallows, throw an exception: its message is
+    /// the error of the import, so a refused `import()` rejects with it.
+    /// @return \Deno\Core\ModuleSource
+    #[php_method]
+    #[abstract_method]
+    fn load(&self, _specifier: &str) -> Option<ModuleSource> {
+        None
+    }
+}
+
+#[derive(Clone)]
+struct ModuleLoader {
+    loader: CloneableZval,
+    import_chain: ImportChain,
+}
+
+impl ModuleLoader {
+    fn new(loader: CloneableZval) -> Self {
+        Self {
+            loader,
+            import_chain: ImportChain::default(),
+        }
+    }
+
+    fn load_module(
+        &self,
+        module_specifier: &deno_core::ModuleSpecifier,
+        is_dyn_import: bool,
+    ) -> Result<deno_core::ModuleSource, Error> {
+        // data: URLs are loaded without calling the PHP module loader.
+        if module_specifier.scheme() == "data" {
+            return load_data_url(module_specifier);
+        }
+
+        let mut load = ext_php_rs::types::ZendHashTable::new();
+        load.insert_at_index(0, (&self.loader).clone()).ok();
+        load.insert_at_index(1, "load").ok();
+        let result = load
+            .into_zval(false)
+            .unwrap()
+            .try_call(vec![&module_specifier.to_string(), &is_dyn_import]);
+
+        let result = match result {
+            Ok(result) => result,
+            // An exception refuses the module, with the exception's message as the reason.
+            Err(error @ ext_php_rs::error::Error::Exception(_)) => {
+                anyhow::bail!(php_error_message(error))
+            }
+            Err(_) => anyhow::bail!("Error calling load() function on ModuleLoader"),
+        };
+
+        let source: &ModuleSource = match result.extract() {
+            Some(source) => source,
+            None => anyhow::bail!("Error converting return value of load() to ModuleSource"),
+        };
+        if let Err(error) = deno_core::ModuleSpecifier::parse(&source.module_url_found) {
+            anyhow::bail!(
+                "The module_url_found of ModuleSource {} is not a valid URL: {}",
+                source.module_url_found,
+                error
+            );
+        }
+
+        Ok(deno_core::ModuleSource {
+            code: source.code_bytes()?.into_boxed_slice(),
+            module_type: source.deno_module_type(),
+            module_url_specified: source.module_url_specified.clone(),
+            module_url_found: source.module_url_found.clone(),
+        })
+    }
+}
+
+impl deno_core::ModuleLoader for ModuleLoader {
+    fn resolve(
+        &self,
+        specifier: &str,
+        referrer: &str,
+        _is_main: bool,
+    ) -> Result<deno_core::ModuleSpecifier, Error> {
+        // data: URLs are resolved and loaded without calling the PHP module loader.
+        if specifier.starts_with("data:") {
+            return Ok(deno_core::resolve_url(specifier)?);
+        }
+
+        let result = call_user_method!(
+            (&self.loader).clone().into_zval(false).unwrap(),
+            "resolve",
+            specifier,
+            referrer,
+            _is_main
+        );
+
+        let result = match result {
+            Some(result) => match result.string() {
+                Some(result) => match url::Url::parse(result.as_str()) {
+                    Ok(result) => Ok(result),
+                    Err(err) => Err(anyhow::anyhow!(err.to_string())),
+                },
+                None => Err(anyhow::anyhow!("resolve() did not return a valid string.")),
+            },
+            None => Err(anyhow::anyhow!("resolve() did not return a valid string.")),
+        };
+        result.map_err(|error| self.import_chain.resolve_error(error, specifier, referrer))
+    }
+
+    fn load(
+        &self,
+        _module_specifier: &deno_core::ModuleSpecifier,
+        _maybe_referrer: Option<deno_core::ModuleSpecifier>,
+        _is_dyn_import: bool,
+    ) -> core::pin::Pin<Box<deno_core::ModuleSourceFuture>> {
+        self.import_chain.record(_module_specifier, _maybe_referrer);
+        let module_source = self
+            .load_module(_module_specifier, _is_dyn_import)
+            .map_err(|error| self.import_chain.load_error(error, _module_specifier));
+        async { module_source }.boxed_local()
+    }
+}
+
+/// Records which module first imported each module, so errors loading a module deep in the module
+/// graph can say how it was imported, such as "imported from file:///b.js, imported from file:///main.js".
+#[derive(Clone, Default)]
+struct ImportChain(std::rc::Rc<std::cell::RefCell<HashMap<String, String>>>);
+
+impl ImportChain {
+    fn record(
+        &self,
+        specifier: &deno_core::ModuleSpecifier,
+        maybe_referrer: Option<deno_core::ModuleSpecifier>,
+    ) {
+        if let Some(referrer) = maybe_referrer {
+            self.0
+                .borrow_mut()
+                .entry(specifier.to_string())
+                .or_insert_with(|| referrer.to_string());
+        }
+    }
+
+    /// Describe how `specifier` was imported, starting with the module that imported it.
+    fn describe(&self, specifier: &str) -> String {
+        let referrers = self.0.borrow();
+        let mut chain = vec![];
+        let mut specifier = specifier;
+        while let Some(referrer) = referrers.get(specifier) {
+            // Cyclic imports would otherwise repeat forever.
+            if chain.contains(&referrer.as_str()) {
+                break;
+            }
+            chain.push(referrer.as_str());
+            specifier = referrer;
+        }
+        chain
+            .iter()
+            .map(|referrer| format!(", imported from {}", referrer))
+            .collect()
+    }
+
+    fn load_error(&self, error: Error, specifier: &deno_core::ModuleSpecifier) -> Error {
+        let chain = self.describe(specifier.as_str());
+        error.context(format!("Unable to load module {}{}", specifier, chain))
+    }
+
+    fn resolve_error(&self, error: Error, specifier: &str, referrer: &str) -> Error {
+        let chain = self.describe(referrer);
+        error.context(format!(
+            "Unable to resolve module \"{}\" imported from {}{}",
+            specifier, referrer, chain
+        ))
+    }
+}
+
+/// Load a module from a `data:` URL, such as `data:application/javascript;base64,...`. Modules with
+/// a JSON media type are loaded as JSON modules, and all others as JavaScript.
+fn load_data_url(specifier: &deno_core::ModuleSpecifier) -> Result<deno_core::ModuleSource, Error> {
+    let data_url = match data_url::DataUrl::process(specifier.as_str()) {
+        Ok(data_url) => data_url,
+        Err(error) => anyhow::bail!("Unable to parse data: URL {}: {:?}", specifier, error),
+    };
+    let mime_type = data_url.mime_type();
+    let module_type = if mime_type.subtype == "json" || mime_type.subtype.ends_with("+json") {
+        deno_core::ModuleType::Json
+    } else {
+        deno_core::ModuleType::JavaScript
+    };
+    let code = match data_url.decode_to_vec() {
+        Ok((code, _fragment)) => code,
+        Err(error) => anyhow::bail!("Unable to decode data: URL {}: {:?}", specifier, error),
+    };
+
+    Ok(deno_core::ModuleSource {
+        code: code.into_boxed_slice(),
+        module_type,
+        module_url_specified: specifier.to_string(),
+        module_url_found: specifier.to_string(),
+    })
+}
+
+/// Get the module loader for the `module_loader` option, which is either a `Deno\Core\FsModuleLoader`
+/// or a PHP class implementing `Deno\Core\ModuleLoader`.
+fn module_loader_from_zval(
+    module_loader: CloneableZval,
+) -> std::rc::Rc<dyn deno_core::ModuleLoader> {
+    match module_loader.0.extract::<&FsModuleLoader>() {
+        Some(fs_module_loader) => std::rc::Rc::new(FsLoader::new(fs_module_loader)),
+        None => std::rc::Rc::new(ModuleLoader::new(module_loader)),
+    }
+}
+
+/// A module loader that loads modules from the filesystem, without the need to implement
+/// `Deno\Core\ModuleLoader` in PHP. Pass an instance to the `module_loader` property of
+/// `Deno\Runtime\WorkerOptions` or `Deno\Core\RuntimeOptions`.
+///
+/// Relative specifiers are resolved against the importing module, or against `base_dir` for the
+/// main module. The module type is inferred from the file extension: `.json` files are loaded as
+/// JSON modules, TypeScript and JSX files (`.ts`, `.tsx`, `.jsx` etc.) are transpiled, and all
+/// other files are loaded as JavaScript.
+#[php_class(name = "Deno\\Core\\FsModuleLoader")]
+#[derive(Clone, Debug)]
+struct FsModuleLoader {
+    base_dir: std::path::PathBuf,
+    allow_read: Option<Vec<std::path::PathBuf>>,
+    transpile_cache: Option<TranspileCache>,
+}
+
+#[php_impl(rename_methods = "none")]
+impl FsModuleLoader {
+    /// Only modules within `base_dir` can be loaded, or when `allow_read` is given, only modules
+    /// within those files and directories, in the same way as
+    /// `Deno\Runtime\PermissionsOptions::allow_read`. When used by a `Deno\Runtime\MainWorker`,
+    /// dynamic imports also require the worker's `allow_read` permission, as in Deno. When
+    /// `transpile_cache` is given, TypeScript and JSX modules are transpiled with it, so they are
+    /// only transpiled again when they change.
+    ///
+    /// @param string[]|null $allow_read
+    #[constructor]
+    #[optional(allow_read)]
+    fn __construct(
+        base_dir: &str,
+        allow_read: Option<Vec<String>>,
+        transpile_cache: Option<&TranspileCache>,
+    ) -> PhpResult<Self> {
+        let base_dir = match std::fs::canonicalize(base_dir) {
+            Ok(base_dir) => base_dir,
+            Err(error) => {
+                return Err(
+                    format!("Unable to resolve base directory {}: {}", base_dir, error).into(),
+                )
+            }
+        };
+        Ok(Self {
+            base_dir,
+            allow_read: allow_read
+                .map(|paths| paths.iter().map(std::path::PathBuf::from).collect()),
+            transpile_cache: transpile_cache.cloned(),
+        })
+    }
+}
+
+struct FsLoader {
+    base_dir: std::path::PathBuf,
+    permissions: std::cell::RefCell<deno_runtime::permissions::Permissions>,
+    worker_op_state: WorkerOpState,
+    transpile_cache: Option<TranspileCache>,
+    import_chain: ImportChain,
+}
+
+impl FsLoader {
+    fn new(fs_module_loader: &FsModuleLoader) -> Self {
+        let allow_read = match &fs_module_loader.allow_read {
+            Some(allow_read) => allow_read.clone(),
+            None => vec![fs_module_loader.base_dir.clone()],
+        };
+        let permissions_options = deno_runtime::permissions::PermissionsOptions {
+            allow_read: Some(allow_read),
+            prompt: false,
+            ..Default::default()
+        };
+        // Only allow_read is set, which can not fail to parse.
+        let permissions =
+            deno_runtime::permissions::Permissions::from_options(&permissions_options).unwrap();
+        Self {
+            base_dir: fs_module_loader.base_dir.clone(),
+            permissions: std::cell::RefCell::new(permissions),
+            worker_op_state: WorkerOpState::default(),
+            transpile_cache: fs_module_loader.transpile_cache.clone(),
+            import_chain: ImportChain::default(),
+        }
+    }
+
+    fn with_worker_op_state(mut self, worker_op_state: WorkerOpState) -> Self {
+        self.worker_op_state = worker_op_state;
+        self
+    }
+
+    fn load_file(
+        &self,
+        specifier: &deno_core::ModuleSpecifier,
+        is_dyn_import: bool,
+    ) -> Result<deno_core::ModuleSource, Error> {
+        let path = match specifier.to_file_path() {
+            Ok(path) => path,
+            Err(()) => anyhow::bail!(
+                "Unable to load {}, only file: URLs are supported.",
+                specifier
+            ),
+        };
+        self.permissions
+            .borrow_mut()
+            .read
+            .check(&path, Some("import()"))?;
+        if is_dyn_import {
+            self.worker_op_state.check_read(&path)?;
+        }
+        let code = match std::fs::read_to_string(&path) {
+            Ok(code) => code,
+            Err(error) => {
+                return Err(
+                    anyhow::Error::new(error).context(format!("Unable to load {}", path.display()))
+                )
+            }
+        };
+
+        let media_type = deno_ast::MediaType::from(specifier);
+        let (code, module_type) = match media_type {
+            deno_ast::MediaType::Json => (code, deno_core::ModuleType::Json),
+            deno_ast::MediaType::TypeScript
+            | deno_ast::MediaType::Mts
+            | deno_ast::MediaType::Cts
+            | deno_ast::MediaType::Tsx
+            | deno_ast::MediaType::Jsx => {
+                let transpiled_text = match &self.transpile_cache {
+                    Some(transpile_cache) => {
+                        transpile_cache
+                            .transpile_cached(&code, specifier.as_str())
+                            .map_err(Error::msg)?
+                            .text
+                    }
+                    None => {
+                        let parsed_source = deno_ast::parse_module(deno_ast::ParseParams {
+                            specifier: specifier.to_string(),
+                            text_info: deno_ast::SourceTextInfo::from_string(code),
+                            media_type,
+                            capture_tokens: false,
+                            scope_analysis: false,
+                            maybe_syntax: None,
+                        })?;
+                        parsed_source.transpile(&Default::default())?.text
+                    }
+                };
+                (transpiled_text, deno_core::ModuleType::JavaScript)
+            }
+            _ => (code, deno_core::ModuleType::JavaScript),
+        };
+
+        Ok(deno_core::ModuleSource {
+            code: code.into_bytes().into_boxed_slice(),
+            module_type,
+            module_url_specified: specifier.to_string(),
+            module_url_found: specifier.to_string(),
+        })
+    }
+}
+
+impl deno_core::ModuleLoader for FsLoader {
+    fn resolve(
+        &self,
+        specifier: &str,
+        referrer: &str,
+        _is_main: bool,
+    ) -> Result<deno_core::ModuleSpecifier, Error> {
+        let result = match url::Url::parse(referrer) {
+            Ok(_) => deno_core::resolve_import(specifier, referrer),
+            Err(_) => {
+                let base_url = url::Url::from_directory_path(&self.base_dir).unwrap();
+                deno_core::resolve_import(specifier, base_url.as_str())
+            }
+        };
+        result.map_err(|error| {
+            self.import_chain
+                .resolve_error(error.into(), specifier, referrer)
+        })
+    }
+
+    fn load(
+        &self,
+        module_specifier: &deno_core::ModuleSpecifier,
+        maybe_referrer: Option<deno_core::ModuleSpecifier>,
+        is_dyn_import: bool,
+    ) -> core::pin::Pin<Box<deno_core::ModuleSourceFuture>> {
+        self.import_chain.record(module_specifier, maybe_referrer);
+        let module_source = match module_specifier.scheme() {
+            "data" => load_data_url(module_specifier),
+            _ => self.load_file(module_specifier, is_dyn_import),
+        };
+        let module_source =
+            module_source.map_err(|error| self.import_chain.load_error(error, module_specifier));
+        async { module_source }.boxed_local()
+    }
+}
+
+/// The OpState of the MainWorker using a module loader, which holds the worker's permissions. It is
+/// set once the worker has been created, and is never set for a JsRuntime, which has no permissions.
+#[derive(Clone, Default)]
+struct WorkerOpState(
+    std::rc::Rc<std::cell::RefCell<Option<std::rc::Rc<std::cell::RefCell<deno_core::OpState>>>>>,
+);
+
+impl WorkerOpState {
+    fn set(&self, op_state: std::rc::Rc<std::cell::RefCell<deno_core::OpState>>) {
+        *self.0.borrow_mut() = Some(op_state);
+    }
+
+    /// Check the worker's `allow_read` permission for a dynamically imported file.
+    fn check_read(&self, path: &std::path::Path) -> Result<(), Error> {
+        let op_state = match &*self.0.borrow() {
+            Some(op_state) => op_state.clone(),
+            None => return Ok(()),
+        };
+        let mut op_state = op_state.borrow_mut();
+        op_state
+            .borrow_mut::<deno_runtime::permissions::Permissions>()
+            .read
+            .check(path, Some("import()"))
+    }
+}
+
+/// Loads the Node built-in modules in `NODE_BUILTINS` for `node:` specifiers, and all other
+/// modules with the worker's module loader.
+struct NodeBuiltinsLoader {
+    module_loader: std::rc::Rc<dyn deno_core::ModuleLoader>,
+}
+
+impl deno_core::ModuleLoader for NodeBuiltinsLoader {
+    fn resolve(
+        &self,
+        specifier: &str,
+        referrer: &str,
+        is_main: bool,
+    ) -> Result<deno_core::ModuleSpecifier, Error> {
+        if let Some(name) = specifier.strip_prefix("node:") {
+            if !NODE_BUILTINS.iter().any(|(builtin, _)| *builtin == name) {
+                anyhow::bail!(
+                    "Unable to import \"{}\", only the Node built-in modules {} are supported.",
+                    specifier,
+                    NODE_BUILTINS
+                        .iter()
+                        .map(|(builtin, _)| format!("node:{}", builtin))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            return Ok(deno_core::resolve_url(specifier)?);
+        }
+        self.module_loader.resolve(specifier, referrer, is_main)
+    }
+
+    fn load(
+        &self,
+        module_specifier: &deno_core::ModuleSpecifier,
+        maybe_referrer: Option<deno_core::ModuleSpecifier>,
+        is_dyn_import: bool,
+    ) -> core::pin::Pin<Box<deno_core::ModuleSourceFuture>> {
+        if module_specifier.scheme() != "node" {
+            return self
+                .module_loader
+                .load(module_specifier, maybe_referrer, is_dyn_import);
+        }
+        let module_source = match NODE_BUILTINS
+            .iter()
+            .find(|(name, _)| *name == module_specifier.path())
+        {
+            Some((_, code)) => Ok(deno_core::ModuleSource {
+                code: code.as_bytes().to_vec().into_boxed_slice(),
+                module_type: deno_core::ModuleType::JavaScript,
+                module_url_specified: module_specifier.to_string(),
+                module_url_found: module_specifier.to_string(),
+            }),
+            None => Err(anyhow::anyhow!(
+                "Unknown Node built-in module {}.",
+                module_specifier
+            )),
+        };
+        async { module_source }.boxed_local()
+    }
+}
+
+/// The modules invalidated with `invalidate_module()`, keyed by their specifier, with the number
+/// of times each has been invalidated. deno_core has no way to remove a module from its module
+/// map, so an invalidated module is instead resolved to its specifier with a `#reload=N` fragment,
+/// which isn't in the module map yet, so the module is loaded again.
+#[derive(Clone, Default)]
+struct InvalidatedModules(std::rc::Rc<std::cell::RefCell<HashMap<String, u32>>>);
+
+impl InvalidatedModules {
+    fn invalidate(&self, specifier: &str) -> Result<(), Error> {
+        let specifier = match deno_core::ModuleSpecifier::parse(specifier) {
+            Ok(specifier) => original_specifier(&specifier),
+            Err(error) => anyhow::bail!("Invalid module specifier {}: {}", specifier, error),
+        };
+        *self
+            .0
+            .borrow_mut()
+            .entry(specifier.to_string())
+            .or_default() += 1;
+        Ok(())
+    }
+
+    /// Wrap a module loader, so invalidated modules are loaded again.
+    fn loader(
+        &self,
+        module_loader: std::rc::Rc<dyn deno_core::ModuleLoader>,
+    ) -> std::rc::Rc<dyn deno_core::ModuleLoader> {
+        std::rc::Rc::new(ReloadingLoader {
+            module_loader,
+            invalidated_modules: self.clone(),
+        })
+    }
+
+    /// The specifier the module is registered as in the module map.
+    fn reload_specifier(
+        &self,
+        specifier: deno_core::ModuleSpecifier,
+    ) -> deno_core::ModuleSpecifier {
+        match self.0.borrow().get(specifier.as_str()) {
+            Some(count) => {
+                let mut reload_specifier = specifier.clone();
+                reload_specifier.set_fragment(Some(&format!("reload={}", count)));
+                reload_specifier
+            }
+            None => specifier,
+        }
+    }
+}
+
+/// Remove the `#reload=N` fragment of a reloaded module, so module loaders only ever see the
+/// module's own specifier.
+fn original_specifier(specifier: &deno_core::ModuleSpecifier) -> deno_core::ModuleSpecifier {
+    let mut specifier = specifier.clone();
+    if let Some(fragment) = specifier.fragment() {
+        if fragment.starts_with("reload=") {
+            specifier.set_fragment(None);
+        }
+    }
+    specifier
+}
+
+/// Loads invalidated modules again with the runtime's module loader, under their reload specifier.
+struct ReloadingLoader {
+    module_loader: std::rc::Rc<dyn deno_core::ModuleLoader>,
+    invalidated_modules: InvalidatedModules,
+}
+
+impl deno_core::ModuleLoader for ReloadingLoader {
+    fn resolve(
+        &self,
+        specifier: &str,
+        referrer: &str,
+        is_main: bool,
+    ) -> Result<deno_core::ModuleSpecifier, Error> {
+        let referrer = match deno_core::ModuleSpecifier::parse(referrer) {
+            Ok(referrer) => original_specifier(&referrer).to_string(),
+            Err(_) => referrer.to_string(),
+        };
+        let specifier = self.module_loader.resolve(specifier, &referrer, is_main)?;
+        Ok(self.invalidated_modules.reload_specifier(specifier))
+    }
+
+    fn load(
+        &self,
+        module_specifier: &deno_core::ModuleSpecifier,
+        maybe_referrer: Option<deno_core::ModuleSpecifier>,
+        is_dyn_import: bool,
+    ) -> core::pin::Pin<Box<deno_core::ModuleSourceFuture>> {
+        let specifier = original_specifier(module_specifier);
+        let maybe_referrer = maybe_referrer.map(|referrer| original_specifier(&referrer));
+        let module_source = self
+            .module_loader
+            .load(&specifier, maybe_referrer, is_dyn_import);
+        if &specifier == module_specifier {
+            return module_source;
+        }
+
+        // The module is registered under the reload specifier, including when the module loader
+        // redirected it, as the module it was redirected to is already in the module map too.
+        let reload_specifier = module_specifier.clone();
+        async move {
+            let mut module_source = module_source.await?;
+            let mut module_url_found =
+                deno_core::ModuleSpecifier::parse(&module_source.module_url_found)?;
+            module_url_found.set_fragment(reload_specifier.fragment());
+            module_source.module_url_specified = reload_specifier.to_string();
+            module_source.module_url_found = module_url_found.to_string();
+            Ok(module_source)
+        }
+        .boxed_local()
+    }
+}
+
+/// Minimal polyfills for the most commonly imported Node built-in modules, which are enabled with
+/// `WorkerOptions::node_builtins`. The deno_node crate that deno_runtime uses does not include
+/// Deno's Node polyfills (the Deno CLI loads them from deno.land/std), so these are partial
+/// stand-ins written for this extension. They cover the commonly used parts of each module's API,
+/// rather than all of it, and are not a port of Node's or Deno's implementations.
+const NODE_BUILTINS: &[(&str, &str)] = &[
+    ("buffer", NODE_BUFFER_JS),
+    ("events", NODE_EVENTS_JS),
+    ("path", NODE_PATH_JS),
+];
+
+/// `Buffer` as a `Uint8Array` subclass, with the utf8, hex, base64, latin1 and ascii encodings.
+const NODE_BUFFER_JS: &str = r#"
+const encodings = ["utf8", "utf-8", "hex", "base64", "latin1", "binary", "ascii"];
+const normalizeEncoding = (encoding = "utf8") => {
+  const normalized = String(encoding).toLowerCase();
+  if (!encodings.includes(normalized)) throw new TypeError(`Unknown encoding: ${encoding}`);
+  return normalized === "utf-8" ? "utf8" : normalized === "binary" ? "latin1" : normalized;
+};
+
+// Spreading a large buffer into String.fromCharCode() overflows the stack, so decode in chunks.
+const fromCharCodes = (bytes) => {
+  let string = "";
+  for (let i = 0; i < bytes.length; i += 0x8000) {
+    string += String.fromCharCode(...bytes.subarray(i, i + 0x8000));
+  }
+  return string;
+};
+
+const encode = (string, encoding) => {
+  switch (normalizeEncoding(encoding)) {
+    case "utf8":
+      return new TextEncoder().encode(string);
+    case "hex":
+      return Uint8Array.from(string.match(/../g) ?? [], (byte) => parseInt(byte, 16));
+    case "base64":
+      return Uint8Array.from(atob(string.replace(/-/g, "+").replace(/_/g, "/")), (c) => c.charCodeAt(0));
+    default:
+      return Uint8Array.from(string, (c) => c.charCodeAt(0) & 0xff);
+  }
+};
+
+export class Buffer extends Uint8Array {
+  static from(value, encodingOrOffset = undefined, length = undefined) {
+    if (typeof value === "string") {
+      const bytes = encode(value, encodingOrOffset);
+      return new Buffer(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+    }
+    if (value instanceof ArrayBuffer || value instanceof SharedArrayBuffer) {
+      const offset = encodingOrOffset ?? 0;
+      return new Buffer(value, offset, length ?? value.byteLength - offset);
+    }
+    const buffer = new Buffer(value.length);
+    buffer.set(value);
+    return buffer;
+  }
+
+  static alloc(size, fill = 0, encoding = undefined) {
+    const buffer = new Buffer(size);
+    if (typeof fill === "string") {
+      const bytes = encode(fill, encoding);
+      for (let i = 0; i < size && bytes.length > 0; i++) buffer[i] = bytes[i % bytes.length];
+    } else {
+      buffer.fill(fill);
+    }
+    return buffer;
+  }
+
+  static allocUnsafe(size) {
+    return new Buffer(size);
+  }
+
+  static byteLength(value, encoding = undefined) {
+    return typeof value === "string" ? encode(value, encoding).byteLength : value.byteLength;
+  }
+
+  static concat(list, totalLength = undefined) {
+    totalLength ??= list.reduce((length, buffer) => length + buffer.length, 0);
+    const result = Buffer.alloc(totalLength);
+    let offset = 0;
+    for (const buffer of list) {
+      if (offset >= totalLength) break;
+      result.set(buffer.subarray(0, totalLength - offset), offset);
+      offset += buffer.length;
+    }
+    return result;
+  }
+
+  static isBuffer(value) {
+    return value instanceof Buffer;
+  }
+
+  static isEncoding(encoding) {
+    return typeof encoding === "string" && encodings.includes(encoding.toLowerCase());
+  }
+
+  toString(encoding = "utf8", start = 0, end = this.length) {
+    const bytes = this.subarray(start, end);
+    switch (normalizeEncoding(encoding)) {
+      case "utf8":
+        return new TextDecoder().decode(bytes);
+      case "hex":
+        return Array.from(bytes, (byte) => byte.toString(16).padStart(2, "0")).join("");
+      case "base64":
+        return btoa(fromCharCodes(bytes));
+      case "ascii":
+        return fromCharCodes(Uint8Array.from(bytes, (byte) => byte & 0x7f));
+      default:
+        return fromCharCodes(bytes);
+    }
+  }
+
+  toJSON() {
+    return { type: "Buffer", data: Array.from(this) };
+  }
+
+  equals(other) {
+    return this.length === other.length && this.every((byte, i) => byte === other[i]);
+  }
+
+  slice(start = undefined, end = undefined) {
+    return this.subarray(start, end);
+  }
+}
+
+export const constants = { MAX_LENGTH: 2 ** 32 - 1 };
+export default { Buffer, constants };
+"#;
+
+const NODE_EVENTS_JS: &str = r#"
+const listenersOf = (emitter, eventName) => {
+  emitter._events ??= new Map();
+  if (!emitter._events.has(eventName)) emitter._events.set(eventName, []);
+  return emitter._events.get(eventName);
+};
+
+export class EventEmitter {
+  static defaultMaxListeners = 10;
+
+  on(eventName, listener) {
+    return this.addListener(eventName, listener);
+  }
+
+  addListener(eventName, listener) {
+    listenersOf(this, eventName).push(listener);
+    return this;
+  }
+
+  prependListener(eventName, listener) {
+    listenersOf(this, eventName).unshift(listener);
+    return this;
+  }
+
+  once(eventName, listener) {
+    const wrapper = (...args) => {
+      this.removeListener(eventName, wrapper);
+      return listener.apply(this, args);
+    };
+    wrapper.listener = listener;
+    return this.on(eventName, wrapper);
+  }
+
+  off(eventName, listener) {
+    return this.removeListener(eventName, listener);
+  }
+
+  removeListener(eventName, listener) {
+    const listeners = listenersOf(this, eventName);
+    const index = listeners.findIndex((l) => l === listener || l.listener === listener);
+    if (index !== -1) listeners.splice(index, 1);
+    return this;
+  }
+
+  removeAllListeners(eventName = undefined) {
+    if (eventName === undefined) {
+      this._events?.clear();
+    } else {
+      this._events?.delete(eventName);
+    }
+    return this;
+  }
+
+  emit(eventName, ...args) {
+    const listeners = [...listenersOf(this, eventName)];
+    if (eventName === "error" && listeners.length === 0) {
+      throw args[0] instanceof Error ? args[0] : new Error(`Unhandled error. (${args[0]})`);
+    }
+    for (const listener of listeners) {
+      listener.apply(this, args);
+    }
+    return listeners.length > 0;
+  }
+
+  listeners(eventName) {
+    return listenersOf(this, eventName).map((listener) => listener.listener ?? listener);
+  }
+
+  rawListeners(eventName) {
+    return [...listenersOf(this, eventName)];
+  }
+
+  listenerCount(eventName) {
+    return listenersOf(this, eventName).length;
+  }
+
+  eventNames() {
+    return [...(this._events?.keys() ?? [])].filter((eventName) =>
+      this._events.get(eventName).length > 0
+    );
+  }
+
+  setMaxListeners(n) {
+    this._maxListeners = n;
+    return this;
+  }
+
+  getMaxListeners() {
+    return this._maxListeners ?? EventEmitter.defaultMaxListeners;
+  }
+}
+
+export function once(emitter, eventName) {
+  return new Promise((resolve, reject) => {
+    const onError = (error) => {
+      emitter.removeListener(eventName, onEvent);
+      reject(error);
+    };
+    const onEvent = (...args) => {
+      if (eventName !== "error") emitter.removeListener("error", onError);
+      resolve(args);
+    };
+    emitter.once(eventName, onEvent);
+    if (eventName !== "error") emitter.once("error", onError);
+  });
+}
+
+EventEmitter.EventEmitter = EventEmitter;
+EventEmitter.once = once;
+export default EventEmitter;
+"#;
+
+/// The POSIX `path` functions. `resolve()` resolves relative paths against `Deno.cwd()`.
+const NODE_PATH_JS: &str = r#"
+const normalizeSegments = (segments, isAbsolute) => {
+  const result = [];
+  for (const segment of segments) {
+    if (segment === "" || segment === ".") continue;
+    if (segment !== "..") {
+      result.push(segment);
+    } else if (result.length > 0 && result[result.length - 1] !== "..") {
+      result.pop();
+    } else if (!isAbsolute) {
+      result.push("..");
+    }
+  }
+  return result;
+};
+const trimTrailingSlashes = (path) => {
+  let end = path.length;
+  while (end > 1 && path[end - 1] === "/") end--;
+  return path.slice(0, end);
+};
+
+export const sep = "/";
+export const delimiter = ":";
+
+export function isAbsolute(path) {
+  return path.startsWith("/");
+}
+
+export function normalize(path) {
+  if (path === "") return ".";
+  const absolute = isAbsolute(path);
+  let normalized = normalizeSegments(path.split("/"), absolute).join("/");
+  if (normalized === "" && !absolute) normalized = ".";
+  if (normalized !== "" && path.endsWith("/")) normalized += "/";
+  return (absolute ? "/" : "") + normalized;
+}
+
+export function join(...paths) {
+  const joined = paths.filter((path) => path !== "").join("/");
+  return joined === "" ? "." : normalize(joined);
+}
+
+export function resolve(...paths) {
+  let resolved = "";
+  for (let i = paths.length - 1; i >= -1 && !isAbsolute(resolved); i--) {
+    const path = i >= 0 ? paths[i] : Deno.cwd();
+    if (path !== "") resolved = resolved === "" ? path : `${path}/${resolved}`;
+  }
+  return "/" + normalizeSegments(resolved.split("/"), true).join("/");
+}
+
+export function relative(from, to) {
+  const fromSegments = resolve(from).split("/").filter(Boolean);
+  const toSegments = resolve(to).split("/").filter(Boolean);
+  let common = 0;
+  while (
+    common < fromSegments.length && common < toSegments.length &&
+    fromSegments[common] === toSegments[common]
+  ) common++;
+  return [
+    ...fromSegments.slice(common).map(() => ".."),
+    ...toSegments.slice(common),
+  ].join("/");
+}
+
+export function dirname(path) {
+  if (path === "") return ".";
+  path = trimTrailingSlashes(path);
+  const index = path.lastIndexOf("/");
+  if (index === -1) return ".";
+  if (index === 0) return "/";
+  return trimTrailingSlashes(path.slice(0, index));
+}
+
+export function basename(path, ext = undefined) {
+  path = trimTrailingSlashes(path);
+  const base = path === "/" ? "" : path.slice(path.lastIndexOf("/") + 1);
+  if (ext !== undefined && base !== ext && base.endsWith(ext)) {
+    return base.slice(0, base.length - ext.length);
+  }
+  return base;
+}
+
+export function extname(path) {
+  const base = basename(path);
+  const index = base.lastIndexOf(".");
+  return index <= 0 || base === ".." ? "" : base.slice(index);
+}
+
+export function parse(path) {
+  const root = isAbsolute(path) ? "/" : "";
+  const base = basename(path);
+  const ext = extname(path);
+  const dir = trimTrailingSlashes(path).includes("/") ? dirname(path) : "";
+  return { root, dir, base, ext, name: base.slice(0, base.length - ext.length) };
+}
+
+export function format({ root = "", dir = "", base, name = "", ext = "" }) {
+  base ??= name + ext;
+  if (dir === "") return root + base;
+  return dir === root ? dir + base : `${dir}/${base}`;
+}
+
+const path = {
+  sep, delimiter, isAbsolute, normalize, join, resolve, relative, dirname, basename, extname,
+  parse, format,
+};
+path.posix = path;
+export const posix = path;
+export default path;
+"#;
+
+/// Attempts to call a given PHP callable.
+///
+/// # Parameters
+///
+/// * `$fn` - The 'function' to call. Can be an [`Arg`] or a [`Zval`].
+/// * ...`$param` - The parameters to pass to the function. Must be able to be
+///   converted into a [`Zval`].
+///
+/// [`Arg`]: crate::args::Arg
+/// [`Zval`]: crate::types::Zval
+#[macro_export]
+macro_rules! call_user_method {
+    ($class: expr, $method: expr, $($param: expr),*) => {
+        {
+        let mut hashtable = ext_php_rs::types::ZendHashTable::new();
+        hashtable.insert_at_index(0, $class).ok();
+        hashtable.insert_at_index(1, $method).ok();
+
+        let result = hashtable.into_zval(false).unwrap().try_call(vec![$(&$param),*]);
+
+        // let result = ext_php_rs::call_user_func!(
+        //     hashtable.into_zval(false).unwrap(),
+        //     $(&$param),*
+        // );
+
+        result.ok()
+    }
+    };
+}
+
+/// JsFile is a descriptor for JavaScript files that are loaded as
+/// part of the Extension->js_files array. The `code` of `JsFile` is
+/// executed when the JsRuntime is initiated.
+#[php_class(name = "Deno\\Core\\JsFile")]
+#[derive(Clone, Debug)]
+struct JsFile {
+    /// The filename for the JS file
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    filename: String,
+    /// The code for the javascript file
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    code: String,
+}
+
+#[php_impl(rename_methods = "none")]
+impl JsFile {
+    #[constructor]
+    fn __construct(filename: String, code: String) -> Self {
+        Self { filename, code }
+    }
+}
+
+/// Extension contains PHP functions (ops) and associated js files which are
+/// exposed to JavaScript via the JsRuntime. PHP functions can be called from JavaScript
+/// via `Deno.core.$name` where `$name` is the array key string from the `ops` property.
+///
+/// It's common to provide `ops` and also more user-friendly accessible functions for those
+/// `ops` via the `js_files` property.
+///
+/// Extensions are initialized in the order they are given in the `extensions` array of the runtime
+/// options, so an extension's `js_files` can rely on the extensions before it.
+#[php_class(name = "Deno\\Core\\Extension")]
+#[derive(Clone, Debug)]
+struct Extension {
+    /// The name of the extension. Names must be unique within a runtime. The extension's `js_files`
+    /// are loaded as `ext:$name/$filename`, so errors thrown by them identify the extension.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    name: String,
+    /// The JS files that should be loaded into the V8 Isolate.
+    /// @var Deno\Core\JsFile[]
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    js_files: Vec<JsFile>,
+    /// The ops for the extension (bridged to PHP functions). The return value of the PHP function is
+    /// converted to a JavaScript value, where a list (an array with the keys 0, 1, 2...) becomes a
+    /// JavaScript array, so ops can return several values as `[ $a, $b ]` to be destructured with
+    /// `const [ a, b ] = Deno.core.ops.op_name()`.
+    /// @var array<string, callable>
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    ops: HashMap<String, CloneableZval>,
+    /// The async ops for the extension (bridged to PHP functions). Calling an async op from
+    /// JavaScript returns a Promise, which is resolved with the return value of the PHP function
+    /// (or rejected if it throws).
+    ///
+    /// PHP functions can't run in parallel with JavaScript, so the PHP function is not called
+    /// straight away: it is queued and called on the isolate's thread once the currently running
+    /// JavaScript yields to the event loop (e.g. via `await`). Arguments are converted to PHP
+    /// values at the time of the call from JavaScript.
+    ///
+    /// Async ops can also be called with `await Deno.core.opAsync("op_name", ...args)`, the
+    /// calling convention of Deno's own async ops, so code ported from Deno ops works unchanged.
+    /// @var array<string, callable>
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    async_ops: HashMap<String, CloneableZval>,
+    /// Sync ops whose return value is discarded, for high-frequency fire-and-forget ops such as
+    /// logging and metrics. The return value of the PHP function is not converted to a JavaScript
+    /// value, and calling the op from JavaScript always returns `undefined`.
+    /// @var array<string, callable>
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    void_ops: HashMap<String, CloneableZval>,
+    /// Ops that stream their output to JavaScript in chunks, such as the contents of a large file.
+    /// The PHP function returns an `Iterator` (usually a Generator that `yield`s each chunk) or an
+    /// array of chunks, and calling the op from JavaScript returns a `ReadableStream` of the chunks.
+    /// Each chunk is only produced by PHP when JavaScript reads it, so a slow consumer applies
+    /// backpressure rather than PHP building the whole output in memory. Canceling the stream
+    /// destroys the iterator, so the Generator's `finally` blocks run.
+    ///
+    /// String chunks are converted to `Uint8Array`s of their bytes, and other chunks are converted
+    /// like the return values of `ops`. The bare `Deno\Core\JsRuntime` has no `ReadableStream`, so
+    /// it returns an async iterator instead, which can be read with `for await` in the same way
+    /// as a `ReadableStream`.
+    /// @var array<string, callable>
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    stream_ops: HashMap<String, CloneableZval>,
+}
+
+#[php_impl(rename_methods = "none")]
+impl Extension {
+    #[constructor]
+    #[optional(name)]
+    fn __construct(name: Option<String>) -> Self {
+        Self {
+            name: name.unwrap_or_default(),
+            js_files: vec![],
+            ops: HashMap::new(),
+            async_ops: HashMap::new(),
+            void_ops: HashMap::new(),
+            stream_ops: HashMap::new(),
+        }
+    }
+
+    /// Build an extension from an associative array, rather than setting its properties one by
+    /// one. The keys are the names of the properties, with `js` as a shorthand for `js_files`.
+    /// The JS files can be given as `JsFile`s, or as the code keyed by its filename:
+    ///
+    /// ```php
+    /// $extension = Deno\Core\Extension::from_array( [
+    ///     'name' => 'greeter',
+    ///     'ops'  => [ 'op_greet' => fn( $name ) => "Hello $name" ],
+    ///     'js'   => [ 'greeter.js' => 'globalThis.greet = Deno.core.ops.op_greet;' ],
+    /// ] );
+    /// ```
+    ///
+    /// Throws if a key is unknown, or if a value is not of the expected type.
+    ///
+    /// @param array $spec
+    fn from_array(spec: &ext_php_rs::types::ZendHashTable) -> PhpResult<Self> {
+        let mut extension = Self::__construct(None);
+        for (key, value) in spec.iter() {
+            let key = match key {
+                ArrayKey::String(key) => key,
+                ArrayKey::Long(key) => key.to_string(),
+            };
+            match key.as_str() {
+                "name" => {
+                    extension.name = value
+                        .string()
+                        .ok_or("The extension's \"name\" must be a string.")?
+                }
+                "js" | "js_files" => extension.js_files = Self::js_files_from_array(&key, value)?,
+                "ops" => extension.ops = Self::ops_from_array(&key, value)?,
+                "async_ops" => extension.async_ops = Self::ops_from_array(&key, value)?,
+                "void_ops" => extension.void_ops = Self::ops_from_array(&key, value)?,
+                "stream_ops" => extension.stream_ops = Self::ops_from_array(&key, value)?,
+                other => {
+                    return Err(format!(
+                        "Unknown extension key \"{}\", expected \"name\", \"js\", \"ops\", \"async_ops\", \"void_ops\" or \"stream_ops\".",
+                        other
+                    )
+                    .into())
+                }
+            }
+        }
+        Ok(extension)
+    }
+}
+
+impl Extension {
+    fn js_files_from_array(key: &str, value: &Zval) -> Result<Vec<JsFile>, String> {
+        let error = || {
+            format!(
+                "The extension's \"{}\" must be an array of JsFile objects, or of code keyed by filename.",
+                key
+            )
+        };
+        let mut js_files = vec![];
+        for (filename, js_file) in value.array().ok_or_else(error)?.iter() {
+            match (filename, js_file.extract::<&JsFile>()) {
+                (_, Some(js_file)) => js_files.push(js_file.clone()),
+                (ArrayKey::String(filename), None) if js_file.is_string() => {
+                    js_files.push(JsFile {
+                        filename,
+                        code: js_file.string().unwrap(),
+                    })
+                }
+                _ => return Err(error()),
+            }
+        }
+        Ok(js_files)
+    }
+
+    fn ops_from_array(key: &str, value: &Zval) -> Result<HashMap<String, CloneableZval>, String> {
+        let error = || {
+            format!(
+                "The extension's \"{}\" must be an array of callables keyed by op name.",
+                key
+            )
+        };
+        let mut ops = HashMap::new();
+        for (name, callback) in value.array().ok_or_else(error)?.iter() {
+            match name {
+                ArrayKey::String(name) if callback.is_callable() => {
+                    ops.insert(name, CloneableZval::from_zval(callback).unwrap());
+                }
+                _ => return Err(error()),
+            }
+        }
+        Ok(ops)
+    }
+}
+
+impl From<Extension> for deno_core::Extension {
+    fn from(extension: Extension) -> Self {
+        let js_files = extension
+            .js_files
+            .iter()
+            .map(|js_file| -> (&str, &str) {
+                let filename = if extension.name.is_empty() {
+                    intern_str(&js_file.filename)
+                } else {
+                    intern_str(&format!("ext:{}/{}", extension.name, js_file.filename))
+                };
+                (filename, intern_str(&js_file.code))
+            })
+            .collect();
+        let mut ops: Vec<deno_core::OpDecl> = vec![];
+        for name in extension
+            .ops
+            .keys()
+            .chain(extension.async_ops.keys())
+            .chain(extension.void_ops.keys())
+            .chain(extension.stream_ops.keys())
+        {
+            ops.push(op_decl(intern_str(name)));
+        }
+        deno_core::Extension::builder()
+            .js(js_files)
+            .ops(ops)
+            .build()
+    }
+}
+
+// deno_core requires `&'static str` for op names and js files, so the strings have to
+// be leaked. Interning them means each distinct string is only leaked once, rather than
+// on every construction of a runtime.
+static INTERNED_STRINGS: std::sync::Mutex<std::collections::BTreeSet<&'static str>> =
+    std::sync::Mutex::new(std::collections::BTreeSet::new());
+
+fn intern_str(string: &str) -> &'static str {
+    let mut interned_strings = INTERNED_STRINGS.lock().unwrap();
+    match interned_strings.get(string) {
+        Some(interned) => interned,
+        None => {
+            let interned: &'static str = Box::leak(string.to_owned().into_boxed_str());
+            interned_strings.insert(interned);
+            interned
+        }
+    }
+}
+
+/// The time origin for `performance.now()`, and whether it is allowed at full resolution.
+struct PerformanceTimeOrigin {
+    start: std::time::Instant,
+    allow_hrtime: bool,
+}
+
+/// Returns the milliseconds since the runtime was created, coarsened to 2ms unless high resolution
+/// time is allowed. This matches Deno's `op_now`.
+#[deno_core::op]
+fn op_php_now(state: &mut deno_core::OpState) -> f64 {
+    let time_origin = state.borrow::<PerformanceTimeOrigin>();
+    let elapsed = time_origin.start.elapsed();
+    let seconds = elapsed.as_secs();
+    let mut subsec_nanos = elapsed.subsec_nanos() as f64;
+    if !time_origin.allow_hrtime {
+        let reduced_time_precision = 2_000_000.0; // 2ms in nanoseconds
+        subsec_nanos -= subsec_nanos % reduced_time_precision;
+    }
+    (seconds * 1_000) as f64 + (subsec_nanos / 1_000_000.0)
+}
+
+/// Provides `performance.now()` to the bare JsRuntime, which otherwise has no `performance` global.
+fn performance_extension(allow_hrtime: bool) -> deno_core::Extension {
+    deno_core::Extension::builder()
+        .js(vec![(
+            "ext:php_performance/performance.js",
+            r#"
+((globalThis) => {
+  if (globalThis.performance === undefined) {
+    globalThis.performance = { now: () => Deno.core.ops.op_php_now() };
+  }
+})(globalThis);
+"#,
+        )])
+        .ops(vec![op_php_now::decl()])
+        .state(move |state| {
+            state.put(PerformanceTimeOrigin {
+                start: std::time::Instant::now(),
+                allow_hrtime,
+            });
+            Ok(())
+        })
+        .build()
+}
+
+/// The environment backing `Deno.env`, see `WorkerOptions::env`.
+enum Env {
+    Php,
+    Vars(HashMap<String, String>),
+}
+
+/// Call a PHP function by name, such as `getenv`.
+fn call_php_function(
+    name: &str,
+    args: Vec<&dyn ext_php_rs::convert::IntoZvalDyn>,
+) -> Result<Zval, Error> {
+    let function = name.into_zval(false).unwrap();
+    match function.try_call(args) {
+        Ok(result) => Ok(result),
+        Err(error) => anyhow::bail!("Error calling PHP function {}(): {}", name, error),
+    }
+}
+
+/// Environment variable names can not be empty or contain `=`, the same as Deno's own `Deno.env`.
+fn validate_env_key(key: &str) -> Result<(), Error> {
+    if key.is_empty() || key.contains(&['=', '\0'] as &[char]) {
+        let message = format!("Key contains invalid characters: {:?}", key);
+        return Err(deno_core::error::type_error(message));
+    }
+    Ok(())
+}
+
+/// Check the worker's `allow_env` permission for the variable, the same as Deno's own `Deno.env`.
+fn check_env(state: &mut deno_core::OpState, key: &str) -> Result<(), Error> {
+    state
+        .borrow_mut::<deno_runtime::permissions::Permissions>()
+        .env
+        .check(key)
+}
+
+#[deno_core::op]
+fn op_php_env_get(state: &mut deno_core::OpState, key: String) -> Result<Option<String>, Error> {
+    check_env(state, &key)?;
+    match state.borrow::<Env>() {
+        Env::Php => Ok(call_php_function("getenv", vec![&key])?.string()),
+        Env::Vars(vars) => Ok(vars.get(&key).cloned()),
+    }
+}
+
+#[deno_core::op]
+fn op_php_env_set(state: &mut deno_core::OpState, key: String, value: String) -> Result<(), Error> {
+    validate_env_key(&key)?;
+    if value.contains('\0') {
+        return Err(deno_core::error::type_error("Value contains invalid characters."));
+    }
+    check_env(state, &key)?;
+    match state.borrow_mut::<Env>() {
+        Env::Php => {
+            call_php_function("putenv", vec![&format!("{}={}", key, value)])?;
+        }
+        Env::Vars(vars) => {
+            vars.insert(key, value);
+        }
+    }
+    Ok(())
+}
+
+#[deno_core::op]
+fn op_php_env_delete(state: &mut deno_core::OpState, key: String) -> Result<(), Error> {
+    validate_env_key(&key)?;
+    check_env(state, &key)?;
+    match state.borrow_mut::<Env>() {
+        Env::Php => {
+            call_php_function("putenv", vec![&key])?;
+        }
+        Env::Vars(vars) => {
+            vars.remove(&key);
+        }
+    }
+    Ok(())
+}
+
+#[deno_core::op]
+fn op_php_env_to_object(state: &mut deno_core::OpState) -> Result<HashMap<String, String>, Error> {
+    state
+        .borrow_mut::<deno_runtime::permissions::Permissions>()
+        .env
+        .check_all()?;
+    match state.borrow::<Env>() {
+        Env::Php => match call_php_function("getenv", vec![])?.extract() {
+            Some(vars) => Ok(vars),
+            None => anyhow::bail!("getenv() did not return an array of strings."),
+        },
+        Env::Vars(vars) => Ok(vars.clone()),
+    }
+}
+
+/// Provides the ops for `Deno.env` when `WorkerOptions::env` is set. `ENV_JS` replaces the
+/// `Deno.env` methods after the worker has been bootstrapped.
+fn env_extension(env: Env) -> deno_core::Extension {
+    deno_core::Extension::builder()
+        .ops(vec![
+            op_php_env_get::decl(),
+            op_php_env_set::decl(),
+            op_php_env_delete::decl(),
+            op_php_env_to_object::decl(),
+        ])
+        .state(move |state| {
+            // The state function can be called more than once, so the environment is cloned.
+            state.put(match &env {
+                Env::Php => Env::Php,
+                Env::Vars(vars) => Env::Vars(vars.clone()),
+            });
+            Ok(())
+        })
+        .build()
+}
+
+const ENV_JS: &str = r#"
+((env) => {
+  const { ops } = Deno.core;
+  env.get = (key) => ops.op_php_env_get(String(key)) ?? undefined;
+  env.has = (key) => ops.op_php_env_get(String(key)) !== null;
+  env.set = (key, value) => ops.op_php_env_set(String(key), String(value));
+  env.delete = (key) => ops.op_php_env_delete(String(key));
+  env.toObject = () => ops.op_php_env_to_object();
+})(Deno.env);
+"#;
+
+/// Replaces `Deno.test()` with one that registers tests, which `RUN_TESTS_JS` runs.
+const TESTING_JS: &str = r#"
+((Deno) => {
+  const tests = [];
+  const definition = (nameOrOptions, optionsOrFn, maybeFn) => {
+    if (typeof nameOrOptions === "function") {
+      return { name: nameOrOptions.name, fn: nameOrOptions };
+    }
+    if (typeof nameOrOptions === "string") {
+      return typeof optionsOrFn === "function"
+        ? { name: nameOrOptions, fn: optionsOrFn }
+        : { ...optionsOrFn, name: nameOrOptions, fn: maybeFn };
+    }
+    return typeof optionsOrFn === "function"
+      ? { ...nameOrOptions, fn: optionsOrFn }
+      : { ...nameOrOptions };
+  };
+  const context = (name) => ({
+    name,
+    async step(nameOrOptions, fn) {
+      const step = definition(nameOrOptions, fn);
+      if (step.ignore) {
+        return false;
+      }
+      await step.fn(context(step.name));
+      return true;
+    },
+  });
+
+  Deno.test = (nameOrOptions, optionsOrFn, maybeFn) => {
+    const test = definition(nameOrOptions, optionsOrFn, maybeFn);
+    if (typeof test.fn !== "function") {
+      throw new TypeError("Missing test function");
+    }
+    if (!test.name) {
+      throw new TypeError("The test name can't be empty");
+    }
+    tests.push(test);
+  };
+
+  Deno[Symbol.for("php.runTests")] = async () => {
+    const only = tests.filter((test) => test.only);
+    const results = [];
+    for (const test of only.length > 0 ? only : tests) {
+      if (test.ignore) {
+        results.push({ name: test.name, status: "ignored", duration_ms: 0, error: null });
+        continue;
+      }
+      const start = performance.now();
+      try {
+        await test.fn(context(test.name));
+        results.push({ name: test.name, status: "passed", duration_ms: performance.now() - start, error: null });
+      } catch (error) {
+        const message = error instanceof Error ? error.stack ?? String(error) : String(error);
+        results.push({ name: test.name, status: "failed", duration_ms: performance.now() - start, error: message });
+      }
+    }
+    return results;
+  };
+})(Deno);
+"#;
+
+const RUN_TESTS_JS: &str = r#"
+(() => {
+  const runTests = Deno[Symbol.for("php.runTests")];
+  if (runTests === undefined) {
+    throw new Error("Testing is not enabled, set BootstrapOptions::enable_testing_features to true.");
+  }
+  return runTests();
+})();
+"#;
+
+/// Declare an op that calls the PHP function registered for `name` via `op_callback`.
+fn op_decl(name: &'static str) -> deno_core::OpDecl {
+    use deno_core::v8::MapFnTo;
+    v8_op_decl(name, op_callback.map_fn_to())
+}
+
+/// Declare an op that is implemented by a V8 function callback.
+fn v8_op_decl(name: &'static str, v8_fn_ptr: v8::FunctionCallback) -> deno_core::OpDecl {
+    deno_core::OpDecl {
+        name,
+        v8_fn_ptr,
+        enabled: true,
+        fast_fn: None,
+        is_async: false,
+        is_unstable: false,
+        is_v8: false,
+    }
+}
+
+/// A JavaScript global (such as `console`) that is replaced by one calling a PHP callable
+/// given in the runtime options.
+struct PhpBinding {
+    op_name: &'static str,
+    is_async: bool,
+    js_filename: &'static str,
+    js: &'static str,
+}
+
+static PHP_CONSOLE: PhpBinding = PhpBinding {
+    op_name: "op_php_console",
+    is_async: false,
+    js_filename: "ext:php_bindings/console.js",
+    js: r#"
+((globalThis) => {
+  const log = (level) => (...args) => Deno.core.ops.op_php_console(level, args);
+  // Only the logging methods are replaced, so a MainWorker keeps Deno's console.table() etc.
+  if (typeof globalThis.console !== "object" || globalThis.console === null) {
+    globalThis.console = {};
+  }
+  for (const level of ["log", "info", "warn", "error", "debug"]) {
+    globalThis.console[level] = log(level);
+  }
+})(globalThis);
+"#,
+};
+
+static PHP_PRINT: PhpBinding = PhpBinding {
+    op_name: "op_php_print",
+    is_async: false,
+    js_filename: "ext:php_bindings/print.js",
+    js: r#"
+((Deno) => {
+  Deno.core.print = (message, isErr = false) =>
+    Deno.core.ops.op_php_print(String(message), Boolean(isErr));
+})(Deno);
+"#,
+};
+
+static PHP_FETCH: PhpBinding = PhpBinding {
+    op_name: "op_php_fetch",
+    is_async: true,
+    js_filename: "ext:php_bindings/fetch.js",
+    js: r#"
+((globalThis) => {
+  globalThis.fetch = async (input, init = undefined) => {
+    const request = new Request(input, init);
+    const body = request.body === null
+      ? null
+      : new Uint8Array(await request.arrayBuffer());
+    const response = await Deno.core.ops.op_php_fetch(
+      request.url,
+      request.method,
+      [...request.headers],
+      body,
+    );
+    return new Response(response.body ?? null, {
+      status: response.status ?? 200,
+      headers: response.headers ?? {},
+    });
+  };
+})(globalThis);
+"#,
+};
+
+/// The extension providing the ops used by the PHP bindings.
+fn php_bindings_extension(php_bindings: &[(&PhpBinding, CloneableZval)]) -> deno_core::Extension {
+    deno_core::Extension::builder()
+        .ops(
+            php_bindings
+                .iter()
+                .map(|(php_binding, _)| op_decl(php_binding.op_name))
+                .collect(),
+        )
+        .build()
+}
+
+/// Defines `Deno.core.transfer()`, which marks a buffer to be transferred to an op as a
+/// `Deno\Core\ArrayBuffer`. The buffer is detached when it is converted for the op, in
+/// `zval_from_jsvalue()`.
+const TRANSFER_JS: &str = r#"
+((core) => {
+  const transfer = Symbol.for("php.transfer");
+  core.transfer = (buffer) => {
+    if (!(buffer instanceof ArrayBuffer) && !ArrayBuffer.isView(buffer)) {
+      throw new TypeError("Only ArrayBuffers and typed arrays can be transferred.");
+    }
+    return Object.freeze({ [transfer]: buffer });
+  };
+})(Deno.core);
+"#;
+
+/// Creates the `ReadableStream` (or async iterator, when there is no `ReadableStream`) returned by
+/// a stream op, which reads the chunks of the PHP stream with the given id.
+const STREAM_JS: &str = r#"
+((core) => (id) => {
+  const next = () => core.ops.op_php_stream_next(id);
+  const close = () => core.ops.op_php_stream_close(id);
+  if (typeof ReadableStream === "function") {
+    // A high water mark of 0 only pulls a chunk from PHP when it is read.
+    return new ReadableStream({
+      pull(controller) {
+        const chunk = next();
+        if (chunk === undefined) {
+          controller.close();
+        } else {
+          controller.enqueue(chunk);
+        }
+      },
+      cancel: close,
+    }, { highWaterMark: 0 });
+  }
+  let done = false;
+  return {
+    [Symbol.asyncIterator]() {
+      return this;
+    },
+    next() {
+      if (done) {
+        return Promise.resolve({ done, value: undefined });
+      }
+      try {
+        const value = next();
+        done = value === undefined;
+        return Promise.resolve({ done, value });
+      } catch (error) {
+        done = true;
+        return Promise.reject(error);
+      }
+    },
+    return(value) {
+      if (!done) {
+        done = true;
+        close();
+      }
+      return Promise.resolve({ done: true, value });
+    },
+  };
+})(Deno.core);
+"#;
+
+/// The streams returned by stream ops that JavaScript has not finished reading, stored in the
+/// isolate's slot.
+#[derive(Clone)]
+struct PhpStreams {
+    /// The function defined by `STREAM_JS`.
+    factory: std::rc::Rc<v8::Global<v8::Function>>,
+    streams: std::rc::Rc<std::cell::RefCell<HashMap<u32, PhpStream>>>,
+    next_id: std::rc::Rc<std::cell::Cell<u32>>,
+}
+
+impl PhpStreams {
+    fn new(scope: &mut v8::HandleScope, factory: v8::Global<v8::Value>) -> Self {
+        let factory = v8::Local::new(scope, factory);
+        let factory = v8::Local::<v8::Function>::try_from(factory).unwrap();
+        Self {
+            factory: std::rc::Rc::new(v8::Global::new(scope, factory)),
+            streams: Default::default(),
+            next_id: Default::default(),
+        }
+    }
+}
+
+/// The iterable returned by a stream op, which is read one chunk at a time.
+struct PhpStream {
+    op_name: String,
+    op_state: std::rc::Rc<std::cell::RefCell<deno_core::OpState>>,
+    chunks: PhpStreamChunks,
+}
+
+enum PhpStreamChunks {
+    Iterator { iterator: Zval, started: bool },
+    Values(std::collections::VecDeque<CloneableZval>),
+}
+
+impl PhpStream {
+    /// Get the next chunk, or `None` when the stream has ended.
+    fn next_chunk(&mut self) -> Result<Option<Zval>, ext_php_rs::error::Error> {
+        let (iterator, started) = match &mut self.chunks {
+            PhpStreamChunks::Values(values) => return Ok(values.pop_front().map(|value| value.0)),
+            PhpStreamChunks::Iterator { iterator, started } => (iterator, started),
+        };
+        call_method(iterator, if *started { "next" } else { "rewind" })?;
+        *started = true;
+        if !call_method(iterator, "valid")?.bool().unwrap_or(false) {
+            return Ok(None);
+        }
+        call_method(iterator, "current").map(Some)
+    }
+}
+
+/// Call a method of a PHP object without arguments.
+fn call_method(object: &Zval, method: &str) -> Result<Zval, ext_php_rs::error::Error> {
+    let mut callable = ext_php_rs::types::ZendHashTable::new();
+    callable.insert_at_index(0, object.shallow_clone())?;
+    callable.insert_at_index(1, method)?;
+    callable.into_zval(false)?.try_call(vec![])
+}
+
+/// Register the return value of a stream op, returning the `ReadableStream` that reads it, or
+/// `None` if it isn't an array or an `Iterator`.
+fn php_stream<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    op_name: &str,
+    op_state: std::rc::Rc<std::cell::RefCell<deno_core::OpState>>,
+    iterable: Zval,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let chunks = if let Some(array) = iterable.array() {
+        PhpStreamChunks::Values(
+            array
+                .iter()
+                .map(|(_, value)| CloneableZval::from_zval(value).unwrap())
+                .collect(),
+        )
+    } else {
+        let iterator_class = ext_php_rs::zend::ClassEntry::try_find("Iterator")?;
+        if !iterable.object()?.instance_of(iterator_class) {
+            return None;
+        }
+        PhpStreamChunks::Iterator {
+            iterator: iterable,
+            started: false,
+        }
+    };
+    let php_streams = scope.get_slot::<PhpStreams>()?.clone();
+    let id = php_streams.next_id.get();
+    php_streams.next_id.set(id.wrapping_add(1));
+    php_streams.streams.borrow_mut().insert(
+        id,
+        PhpStream {
+            op_name: op_name.to_string(),
+            op_state,
+            chunks,
+        },
+    );
+    let factory = v8::Local::new(scope, &*php_streams.factory);
+    let undefined = v8::undefined(scope).into();
+    let id = v8::Integer::new_from_unsigned(scope, id).into();
+    factory.call(scope, undefined, &[id])
+}
+
+/// Op that returns the next chunk of a PHP stream, or `undefined` when it has ended.
+fn op_php_stream_next(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let id = args.get(0).uint32_value(scope).unwrap_or_default();
+    let php_streams = scope.get_slot::<PhpStreams>().unwrap().clone();
+    // The stream is taken out while PHP produces the chunk, as PHP may call ops that use streams.
+    let mut stream = match php_streams.streams.borrow_mut().remove(&id) {
+        Some(stream) => stream,
+        None => return,
+    };
+    let _call_depth = match CallDepthGuard::enter(scope) {
+        Ok(call_depth) => call_depth,
+        Err(message) => {
+            let message = v8::String::new(scope, &message).unwrap();
+            let exception = v8::Exception::range_error(scope, message);
+            scope.throw_exception(exception);
+            return;
+        }
+    };
+    let op_name = stream.op_name.clone();
+    let op_state = stream.op_state.clone();
+    let result = with_op_metrics(scope, &op_name, || {
+        with_current_op_state(&op_name, op_state, || stream.next_chunk())
+    });
+    match result {
+        Ok(Some(chunk)) => {
+            php_streams.streams.borrow_mut().insert(id, stream);
+            rv.set(stream_chunk(scope, &chunk));
+        }
+        // The stream has ended, so it is dropped, and JavaScript gets `undefined`.
+        Ok(None) => (),
+        Err(error) => {
+            let exception = op_error(scope, error);
+            scope.throw_exception(exception);
+        }
+    }
+}
+
+/// Op that drops a PHP stream that JavaScript canceled before reading all of it.
+fn op_php_stream_close(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let id = args.get(0).uint32_value(scope).unwrap_or_default();
+    let php_streams = scope.get_slot::<PhpStreams>().unwrap().clone();
+    let stream = php_streams.streams.borrow_mut().remove(&id);
+    // Dropped after the borrow ends, as destroying a Generator runs its `finally` blocks.
+    drop(stream);
+}
+
+/// Convert a chunk of a PHP stream, where strings become a `Uint8Array` of their bytes.
+fn stream_chunk<'s>(scope: &mut v8::HandleScope<'s>, chunk: &Zval) -> v8::Local<'s, v8::Value> {
+    if !chunk.is_string() {
+        return js_value_from_zval(scope, chunk);
+    }
+    let bytes: Vec<u8> = chunk.binary().unwrap_or_default();
+    let length = bytes.len();
+    let backing_store =
+        v8::ArrayBuffer::new_backing_store_from_boxed_slice(bytes.into_boxed_slice()).make_shared();
+    let buffer = v8::ArrayBuffer::with_backing_store(scope, &backing_store);
+    v8::Uint8Array::new(scope, buffer, 0, length)
+        .unwrap()
+        .into()
+}
+
+/// The extension providing the ops used by `STREAM_JS` to read PHP streams.
+fn php_streams_extension() -> deno_core::Extension {
+    use deno_core::v8::MapFnTo;
+    deno_core::Extension::builder()
+        .ops(vec![
+            v8_op_decl("op_php_stream_next", op_php_stream_next.map_fn_to()),
+            v8_op_decl("op_php_stream_close", op_php_stream_close.map_fn_to()),
+        ])
+        .build()
+}
+
+/// Replaces `Deno.core.opAsync()` so it also calls PHP ops, whose names are added to the set at
+/// `Deno.core[Symbol.for("php.ops")]`. deno_core's `opAsync()` passes a promise id as the first
+/// argument and waits for the op's result from the event loop, which PHP ops don't use, as async
+/// PHP ops return a Promise themselves.
+const OP_ASYNC_JS: &str = r#"
+((core) => {
+  const phpOps = Symbol.for("php.ops");
+  if (core[phpOps]) {
+    return;
+  }
+  const names = new Set();
+  Object.defineProperty(core, phpOps, { value: names });
+  const opAsync = core.opAsync;
+  core.opAsync = (opName, ...args) => {
+    if (!names.has(opName)) {
+      return opAsync(opName, ...args);
+    }
+    // Sync PHP ops are called the same way, and their exceptions reject the Promise.
+    return new Promise((resolve) => resolve(core.ops[opName](...args)));
+  };
+})(Deno.core);
+"#;
+
+/// Make `Deno.core.opAsync()` call the PHP ops with the given names.
+fn add_op_async_names(js_runtime: &mut deno_core::JsRuntime, names: &[&str]) -> Result<(), Error> {
+    let script = format!(
+        "for (const name of {}) Deno.core[Symbol.for(\"php.ops\")].add(name);",
+        deno_core::serde_json::to_string(names)?
+    );
+    js_runtime.execute_script("ext:php_op_async/names.js", &script)?;
+    Ok(())
+}
+
+/// Register the PHP callables for the extensions' ops and the PHP bindings with the runtime, and
+/// install the bindings' JavaScript.
+fn init_ops(
+    js_runtime: &mut deno_core::JsRuntime,
+    extensions: &[Extension],
+    php_bindings: &[(&PhpBinding, CloneableZval)],
+) -> Result<(), Error> {
+    let mut callbacks = op_callbacks(extensions);
+    for (php_binding, callback) in php_bindings {
+        callbacks.insert(
+            php_binding.op_name.to_string(),
+            OpCallback {
+                callback: callback.clone(),
+                is_async: php_binding.is_async,
+                is_void: false,
+                is_stream: false,
+            },
+        );
+    }
+    let names: Vec<String> = callbacks.keys().cloned().collect();
+    set_op_callbacks(js_runtime.v8_isolate(), callbacks);
+    js_runtime.v8_isolate().set_slot(RuntimeId::next());
+
+    js_runtime.execute_script("ext:php_transfer/transfer.js", TRANSFER_JS)?;
+    js_runtime.execute_script("ext:php_op_async/op_async.js", OP_ASYNC_JS)?;
+    let stream_factory = js_runtime.execute_script("ext:php_stream/stream.js", STREAM_JS)?;
+    let php_streams = PhpStreams::new(&mut js_runtime.handle_scope(), stream_factory);
+    js_runtime.v8_isolate().set_slot(php_streams);
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    add_op_async_names(js_runtime, &names)?;
+    for (php_binding, _) in php_bindings {
+        js_runtime.execute_script(php_binding.js_filename, php_binding.js)?;
+    }
+    Ok(())
+}
+
+impl From<&Extension> for deno_core::Extension {
+    fn from(extension: &Extension) -> Self {
+        extension.clone().into()
+    }
+}
+
+impl FromZval<'_> for Extension {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn from_zval(zval: &'_ Zval) -> Option<Self> {
+        let extension: &Extension = zval.extract().unwrap();
+        let new_extension = extension.to_owned();
+        Some(new_extension)
+    }
+}
+
+impl FromZval<'_> for BootstrapOptions {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn from_zval(zval: &'_ Zval) -> Option<Self> {
+        let bootstrap: &BootstrapOptions = zval.extract().unwrap();
+        let new_bootstrap = bootstrap.to_owned();
+        Some(new_bootstrap)
+    }
+}
+
+impl FromZval<'_> for JsFile {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn from_zval(zval: &'_ Zval) -> Option<Self> {
+        let file: &JsFile = zval.extract().unwrap();
+        let new_file = file.to_owned();
+        Some(new_file)
+    }
+}
+
+/// OpState is the per-runtime state that is shared between all ops of a JsRuntime. Ops can use it to
+/// store values and resources (such as an open database connection) in one op call and retrieve
+/// them in another. Use `OpState::current()` inside an op to get the state of the calling runtime,
+/// or `JsRuntime::op_state()` to get it from outside of an op.
+#[php_class(name = "Deno\\Core\\OpState")]
+struct OpState {
+    deno_op_state: std::rc::Rc<std::cell::RefCell<deno_core::OpState>>,
+}
+
+/// The values stored via `OpState::set()`, kept in deno_core's OpState.
+#[derive(Default)]
+struct OpStateValues(HashMap<String, CloneableZval>);
+
+/// A PHP value stored in the runtime's resource table via `OpState::add_resource()`.
+struct PhpResource {
+    name: String,
+    value: CloneableZval,
+}
+
+impl deno_core::Resource for PhpResource {
+    fn name(&self) -> std::borrow::Cow<str> {
+        self.name.clone().into()
+    }
+}
+
+/// An op that is currently being called, with the op state of the runtime calling it.
+struct CurrentOp {
+    name: String,
+    op_state: std::rc::Rc<std::cell::RefCell<deno_core::OpState>>,
+}
+
+thread_local! {
+    // The ops that are currently being called, the last being the innermost call.
+    static CURRENT_OPS: std::cell::RefCell<Vec<CurrentOp>> = std::cell::RefCell::new(vec![]);
+}
+
+/// Call `f` with the op's name and `op_state` available to PHP via `OpState::current_op_name()`
+/// and `OpState::current()`.
+fn with_current_op_state<T>(
+    name: &str,
+    op_state: std::rc::Rc<std::cell::RefCell<deno_core::OpState>>,
+    f: impl FnOnce() -> T,
+) -> T {
+    let name = name.to_string();
+    CURRENT_OPS.with(|current_ops| current_ops.borrow_mut().push(CurrentOp { name, op_state }));
+    let result = f();
+    CURRENT_OPS.with(|current_ops| current_ops.borrow_mut().pop());
+    result
+}
+
+#[php_impl(rename_methods = "none")]
+impl OpState {
+    /// Get the op state of the runtime that is calling the current op. Returns null when not
+    /// called from within an op.
+    ///
+    /// @return \Deno\Core\OpState|null
+    fn current() -> Option<OpState> {
+        CURRENT_OPS.with(|current_ops| {
+            current_ops.borrow().last().map(|current_op| OpState {
+                deno_op_state: current_op.op_state.clone(),
+            })
+        })
+    }
+
+    /// Get the name of the op that is currently being called, which lets one PHP function serve
+    /// several ops, such as a dispatcher for all of an Extension's ops. Returns null when not called
+    /// from within an op.
+    ///
+    /// @return string|null
+    fn current_op_name() -> Option<String> {
+        CURRENT_OPS.with(|current_ops| {
+            current_ops
+                .borrow()
+                .last()
+                .map(|current_op| current_op.name.clone())
+        })
+    }
+
+    /// Store a value in the op state.
+    fn set(&mut self, key: String, value: CloneableZval) {
+        let mut op_state = self.deno_op_state.borrow_mut();
+        match op_state.try_borrow_mut::<OpStateValues>() {
+            Some(values) => {
+                values.0.insert(key, value);
+            }
+            None => {
+                let mut values = OpStateValues::default();
+                values.0.insert(key, value);
+                op_state.put(values);
+            }
+        }
+    }
+
+    /// Get a value from the op state, or null if it has not been set.
+    ///
+    /// @return mixed
+    fn get(&self, key: &str) -> Option<CloneableZval> {
+        let op_state = self.deno_op_state.borrow();
+        op_state
+            .try_borrow::<OpStateValues>()
+            .and_then(|values| values.0.get(key).cloned())
+    }
+
+    /// Whether a value has been set in the op state.
+    fn has(&self, key: &str) -> bool {
+        let op_state = self.deno_op_state.borrow();
+        op_state
+            .try_borrow::<OpStateValues>()
+            .map_or(false, |values| values.0.contains_key(key))
+    }
+
+    /// Remove a value from the op state.
+    fn delete(&mut self, key: &str) {
+        let mut op_state = self.deno_op_state.borrow_mut();
+        if let Some(values) = op_state.try_borrow_mut::<OpStateValues>() {
+            values.0.remove(key);
+        }
+    }
+
+    /// Add a value to the runtime's resource table, returning the resource ID. The resource ID can
+    /// be passed to JavaScript and back to other ops to retrieve the value with `get_resource()`.
+    ///
+    /// @return int
+    fn add_resource(&mut self, name: String, value: CloneableZval) -> deno_core::ResourceId {
+        self.deno_op_state
+            .borrow_mut()
+            .resource_table
+            .add(PhpResource { name, value })
+    }
+
+    /// Get a value from the runtime's resource table. Throws an exception if the resource does
+    /// not exist.
+    ///
+    /// @return mixed
+    fn get_resource(&self, rid: deno_core::ResourceId) -> PhpResult<CloneableZval> {
+        match self
+            .deno_op_state
+            .borrow()
+            .resource_table
+            .get::<PhpResource>(rid)
+        {
+            Ok(resource) => Ok(resource.value.clone()),
+            Err(error) => Err(error.to_string().into()),
+        }
+    }
+
+    /// Close a resource, removing it from the runtime's resource table. Throws an exception if
+    /// the resource does not exist.
+    fn close_resource(&mut self, rid: deno_core::ResourceId) -> PhpResult<()> {
+        match self.deno_op_state.borrow_mut().resource_table.close(rid) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(error.to_string().into()),
+        }
+    }
+
+    /// Check that the worker's permissions allow reading `path`, the same as Deno's built-in file
+    /// system APIs. Throws `Deno\Errors\PermissionDenied` if reading is not allowed. Relative paths
+    /// are resolved from the current working directory.
+    ///
+    /// Ops run with all of PHP's privileges, so ops that act on behalf of untrusted JavaScript
+    /// should check the permissions first. Only `Deno\Runtime\MainWorker` has permissions, so the
+    /// checks always fail in the ops of a `JsRuntime`.
+    ///
+    /// @param string|null $api_name The name of the API for the error message, such as "readFile()".
+    #[optional(api_name)]
+    fn check_read(&mut self, path: &str, api_name: Option<String>) -> PhpResult<()> {
+        self.check_permissions(|permissions| {
+            permissions
+                .read
+                .check(std::path::Path::new(path), api_name.as_deref())
+        })
+    }
+
+    /// Check that the worker's permissions allow writing `path`. Throws
+    /// `Deno\Errors\PermissionDenied` if writing is not allowed. See `check_read()`.
+    ///
+    /// @param string|null $api_name The name of the API for the error message, such as "writeFile()".
+    #[optional(api_name)]
+    fn check_write(&mut self, path: &str, api_name: Option<String>) -> PhpResult<()> {
+        self.check_permissions(|permissions| {
+            permissions
+                .write
+                .check(std::path::Path::new(path), api_name.as_deref())
+        })
+    }
+
+    /// Check that the worker's permissions allow network access to `host`, optionally on `port`.
+    /// Throws `Deno\Errors\PermissionDenied` if network access is not allowed. See `check_read()`.
+    ///
+    /// @param int|null $port
+    /// @param string|null $api_name The name of the API for the error message, such as "connect()".
+    #[optional(port)]
+    fn check_net(
+        &mut self,
+        host: &str,
+        port: Option<u16>,
+        api_name: Option<String>,
+    ) -> PhpResult<()> {
+        self.check_permissions(|permissions| {
+            permissions.net.check(&(host, port), api_name.as_deref())
+        })
+    }
+
+    /// Check that the worker's permissions allow reading the environment variable `name`. Throws
+    /// `Deno\Errors\PermissionDenied` if it is not allowed. See `check_read()`.
+    fn check_env(&mut self, name: &str) -> PhpResult<()> {
+        self.check_permissions(|permissions| permissions.env.check(name))
+    }
+}
+
+impl OpState {
+    fn check_permissions(
+        &mut self,
+        check: impl FnOnce(&mut deno_runtime::permissions::Permissions) -> Result<(), Error>,
+    ) -> PhpResult<()> {
+        let mut op_state = self.deno_op_state.borrow_mut();
+        let permissions =
+            match op_state.try_borrow_mut::<deno_runtime::permissions::Permissions>() {
+                Some(permissions) => permissions,
+                None => return Err(php_exception_from_error(
+                    deno_core::error::custom_error(
+                        "PermissionDenied",
+                        "Permissions are only available to the ops of a Deno\\Runtime\\MainWorker.",
+                    ),
+                    None,
+                )),
+            };
+        match check(permissions) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(php_exception_from_error(error, None)),
+        }
+    }
+}
+
+/// ModuleSource represents an ES6 module, including the source code and type. An ModuleSource should
+/// be returned from your module loader passed to JsRuntime's RuntimeOptions::module_loader property.
 #[php_class(name = "Deno\\Core\\ModuleSource")]
 #[derive(Debug)]
 struct ModuleSource {
-    /// The module's source code.
+    /// The module's source code. Text, such as JavaScript and JSON, is given as a UTF-8 string.
+    /// Binary code is given as a `Deno\Core\Uint8Array`, and passed to the runtime unchanged.
+    /// @var string|\Deno\Core\Uint8Array
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    code: CloneableZval,
+    /// The module type, can be "javascript" or "json". JSON media types, such as "application/json",
+    /// are also JSON modules, and any other type is JavaScript.
+    ///
+    /// The type is checked against the import's assertion in the same way as Deno: JSON modules can
+    /// only be imported with `assert { type: "json" }`, and JavaScript modules only without it.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    module_type: String,
+    /// The specified module URL of the import, which is the specifier that was passed to `load()`.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    module_url_specified: String,
+    /// The final module URL, after any redirects (such as a 301 response when fetching a remote
+    /// module). When it differs from `module_url_specified`, the module is registered under this URL,
+    /// so `import.meta.url` is this URL and the module's relative imports are resolved against it:
+    /// `resolve()` is called with it as the referrer. Later imports of either URL share the module.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    module_url_found: String,
+}
+
+#[php_impl(rename_methods = "none")]
+impl ModuleSource {
+    /// @param string|\Deno\Core\Uint8Array $code
+    #[constructor]
+    fn __construct(
+        code: CloneableZval,
+        module_type: String,
+        module_url_specified: String,
+        module_url_found: String,
+    ) -> Self {
+        Self {
+            code,
+            module_type,
+            module_url_specified,
+            module_url_found,
+        }
+    }
+}
+
+impl ModuleSource {
+    /// The type of the module, where JSON media types such as "application/json" are JSON modules.
+    fn deno_module_type(&self) -> deno_core::ModuleType {
+        let module_type = self.module_type.to_ascii_lowercase();
+        if module_type == "json" || module_type.ends_with("/json") || module_type.ends_with("+json")
+        {
+            deno_core::ModuleType::Json
+        } else {
+            deno_core::ModuleType::JavaScript
+        }
+    }
+
+    /// The bytes of the module's code. Strings must be valid UTF-8, so that binary code which is
+    /// mistakenly given as a string is not silently mangled.
+    fn code_bytes(&self) -> Result<Vec<u8>, Error> {
+        if let Some(code) = self.code.0.extract::<&Uint8Array>() {
+            return Ok(code.bytes.clone());
+        }
+        match self.code.0.binary::<u8>() {
+            Some(code) if std::str::from_utf8(&code).is_ok() => Ok(code),
+            Some(_) => anyhow::bail!(
+                "The code of module {} is not valid UTF-8, use a Deno\\Core\\Uint8Array for binary code.",
+                self.module_url_found
+            ),
+            None => anyhow::bail!(
+                "The code of module {} must be a string or Deno\\Core\\Uint8Array.",
+                self.module_url_found
+            ),
+        }
+    }
+}
+
+/// ParseParams represent the arguments for Deno\AST\parse_module, which is used to
+/// parse TypeScript.
+#[php_class(name = "Deno\\AST\\ParseParams")]
+struct ParseParams {
+    /// The ES6 module specifier, must be a URL.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    specifier: String,
+    /// The source code of the ES6 module.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    text_info: String,
+    /// The type of the module, specified as one of the `Deno\AST\MediaType` constants or a
+    /// mime-type such as application/typescript etc. When empty (the default), the type is inferred
+    /// from the file extension of `specifier`, such as TypeScript for `file:///app/index.ts`.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    media_type: String,
+    /// Override the syntax that is otherwise inferred from `media_type`, for example to parse
+    /// experimental decorators in JavaScript.
+    /// @var \Deno\AST\Syntax|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    syntax: Option<Syntax>,
+}
+
+#[php_impl(rename_methods = "none")]
+impl ParseParams {
+    fn __construct() -> PhpResult<Self> {
+        Ok(Self {
+            specifier: "".to_string(),
+            media_type: "".to_string(),
+            text_info: "".to_string(),
+            syntax: None,
+        })
+    }
+}
+
+impl TryFrom<&ParseParams> for deno_ast::ParseParams {
+    type Error = String;
+    fn try_from(params: &ParseParams) -> Result<Self, String> {
+        let specifier = match url::Url::parse(params.specifier.as_str()) {
+            Ok(t) => t,
+            Err(err) => return Err(err.to_string()),
+        };
+        let media_type = if params.media_type.is_empty() {
+            deno_ast::MediaType::from(&specifier)
+        } else {
+            let content_type = MediaType::content_type(params.media_type.as_str())
+                .unwrap_or(params.media_type.as_str());
+            match deno_ast::MediaType::from_content_type(&specifier, content_type) {
+                deno_ast::MediaType::Unknown => {
+                    return Err(format!("Unknown media type \"{}\".", params.media_type))
+                }
+                media_type => media_type,
+            }
+        };
+
+        Ok(deno_ast::ParseParams {
+            specifier: params.specifier.clone(),
+            text_info: deno_ast::SourceTextInfo::from_string(params.text_info.clone()),
+            capture_tokens: false,
+            maybe_syntax: params.syntax.as_ref().map(|syntax| syntax.into()),
+            scope_analysis: false,
+            media_type,
+        })
+    }
+}
+
+/// The syntax to parse a module with, for `ParseParams::syntax`.
+#[php_class(name = "Deno\\AST\\Syntax")]
+#[derive(Clone, Debug)]
+struct Syntax {
+    /// Parse TypeScript rather than JavaScript. Defaults to `true`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    typescript: bool,
+    /// Parse JSX (TSX when `typescript` is true). Defaults to `false`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    jsx: bool,
+    /// Parse decorators. Defaults to `false`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    decorators: bool,
+    /// Parse decorators before `export`, such as `@decorator export class A {}`. JavaScript only.
+    /// Defaults to `false`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    decorators_before_export: bool,
+    /// Parse a TypeScript declaration (`.d.ts`) file. TypeScript only. Defaults to `false`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    dts: bool,
+}
+
+#[php_impl(rename_methods = "none")]
+impl Syntax {
+    #[constructor]
+    fn __construct() -> Self {
+        Self {
+            typescript: true,
+            jsx: false,
+            decorators: false,
+            decorators_before_export: false,
+            dts: false,
+        }
+    }
+}
+
+impl From<&Syntax> for deno_ast::swc::parser::Syntax {
+    fn from(syntax: &Syntax) -> Self {
+        if syntax.typescript {
+            deno_ast::swc::parser::Syntax::Typescript(deno_ast::swc::parser::TsConfig {
+                tsx: syntax.jsx,
+                decorators: syntax.decorators,
+                dts: syntax.dts,
+                ..Default::default()
+            })
+        } else {
+            deno_ast::swc::parser::Syntax::Es(deno_ast::swc::parser::EsConfig {
+                jsx: syntax.jsx,
+                decorators: syntax.decorators,
+                decorators_before_export: syntax.decorators_before_export,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+impl FromZval<'_> for Syntax {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn from_zval(zval: &'_ Zval) -> Option<Self> {
+        let syntax: &Syntax = zval.extract()?;
+        Some(syntax.to_owned())
+    }
+}
+
+/// MediaType contains the module types understood by Deno\AST\parse_module. Use these constants
+/// for `ParseParams::media_type` rather than hand-writing content-type strings.
+#[php_class(name = "Deno\\AST\\MediaType")]
+struct MediaType {}
+
+#[php_impl(rename_methods = "none")]
+impl MediaType {
+    const TYPESCRIPT: &'static str = "typescript";
+    const TSX: &'static str = "tsx";
+    const JAVASCRIPT: &'static str = "javascript";
+    const JSX: &'static str = "jsx";
+    const JSON: &'static str = "json";
+
+    /// Get the content-type string for one of the MediaType constants, such as
+    /// `application/typescript` for `MediaType::TYPESCRIPT`. Returns null for unknown media types.
+    /// @return string|null
+    fn content_type(media_type: &str) -> Option<&'static str> {
+        match media_type {
+            Self::TYPESCRIPT => Some("application/typescript"),
+            Self::TSX => Some("text/tsx"),
+            Self::JAVASCRIPT => Some("application/javascript"),
+            Self::JSX => Some("text/jsx"),
+            Self::JSON => Some("application/json"),
+            _ => None,
+        }
+    }
+}
+
+/// The name of a media type, as used for the `Deno\AST\MediaType` constants.
+fn media_type_name(media_type: deno_ast::MediaType) -> &'static str {
+    match media_type {
+        deno_ast::MediaType::TypeScript => MediaType::TYPESCRIPT,
+        deno_ast::MediaType::Tsx => MediaType::TSX,
+        deno_ast::MediaType::JavaScript => MediaType::JAVASCRIPT,
+        deno_ast::MediaType::Jsx => MediaType::JSX,
+        deno_ast::MediaType::Json => MediaType::JSON,
+        deno_ast::MediaType::Mjs => "mjs",
+        deno_ast::MediaType::Cjs => "cjs",
+        deno_ast::MediaType::Mts => "mts",
+        deno_ast::MediaType::Cts => "cts",
+        deno_ast::MediaType::Dts => "dts",
+        deno_ast::MediaType::Dmts => "dmts",
+        deno_ast::MediaType::Dcts => "dcts",
+        deno_ast::MediaType::Wasm => "wasm",
+        deno_ast::MediaType::TsBuildInfo => "tsbuildinfo",
+        deno_ast::MediaType::SourceMap => "sourcemap",
+        deno_ast::MediaType::Unknown => "unknown",
+    }
+}
+
+/// The transpiled code to TypeScript source code, this is the result of `Deno\AST\ParsedSource::transpile().
+#[php_class(name = "Deno\\AST\\TranspiledSource")]
+struct TranspiledSource {
+    /// Transpiled text.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub text: String,
+    /// Source map back to the original file.
+    /// @var string|null
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub source_map: Option<String>,
+}
+
+#[php_impl(rename_methods = "none")]
+impl TranspiledSource {
+    /// Write the transpiled text to a file. When there is a `source_map` (see
+    /// `EmitOptions::source_map`), it is written alongside as `$path.map`, and a
+    /// `//# sourceMappingURL` comment referencing it is appended to the text.
+    fn write_to_file(&self, path: &str) -> PhpResult<()> {
+        let mut text = self.text.clone();
+        if let Some(source_map) = &self.source_map {
+            let map_path = format!("{}.map", path);
+            if let Err(error) = std::fs::write(&map_path, source_map) {
+                return Err(
+                    format!("Unable to write source map file {}: {}", map_path, error).into(),
+                );
+            }
+            let map_filename = std::path::Path::new(&map_path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy();
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&format!("//# sourceMappingURL={}\n", map_filename));
+        }
+        match std::fs::write(path, text) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(format!("Unable to write file {}: {}", path, error).into()),
+        }
+    }
+}
+
+/// A problem found while parsing a module, such as a syntax error. See `ParsedSource::diagnostics()`.
+#[php_class(name = "Deno\\AST\\Diagnostic")]
+#[derive(Clone, Debug)]
+struct Diagnostic {
+    /// The specifier of the module.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    specifier: String,
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    message: String,
+    /// The one-based line number of the start of the problem.
+    /// @var int
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    line: usize,
+    /// The one-based column number of the start of the problem.
+    /// @var int
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    column: usize,
+    /// The byte offset of the start of the problem in the source text.
+    /// @var int
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    start: usize,
+    /// The byte offset of the end of the problem in the source text.
+    /// @var int
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    end: usize,
+}
+
+#[php_class(name = "Deno\\AST\\ParsedSource")]
+struct ParsedSource {
+    deno_ast_parsed_source: deno_ast::ParsedSource,
+}
+
+/// Represents the parsed AST via `Deno\AST\parse_modue()`.
+#[php_impl(rename_methods = "none")]
+impl ParsedSource {
+    /// Transpile the ASP to TypeScript, with the provided EmitOptions. Throws an exception or returns Deno\AST\TranspiledSource
+    fn transpile(&self, options: &EmitOptions) -> PhpResult<TranspiledSource> {
+        match self.deno_ast_parsed_source.transpile(&options.try_into()?) {
+            Ok(transpiled_source) => Ok(TranspiledSource {
+                text: transpiled_source.text,
+                source_map: transpiled_source.source_map,
+            }),
+            Err(error) => Err(error.to_string().into()),
+        }
+    }
+
+    /// Remove TypeScript types, without the other transforms of `transpile()`. JSX is preserved
+    /// and no source map is generated, which makes this faster than `transpile()` for large files.
+    /// Note that decorators are still transformed, as deno_ast always transforms them.
+    fn strip_types(&self) -> PhpResult<TranspiledSource> {
+        let emit_options = deno_ast::EmitOptions {
+            inline_source_map: false,
+            inline_sources: false,
+            source_map: false,
+            transform_jsx: false,
+            ..Default::default()
+        };
+        match self.deno_ast_parsed_source.transpile(&emit_options) {
+            Ok(transpiled_source) => Ok(TranspiledSource {
+                text: transpiled_source.text,
+                source_map: None,
+            }),
+            Err(error) => Err(error.to_string().into()),
+        }
+    }
+
+    /// Get the diagnostics, such as syntax errors, that were found while parsing the module. The
+    /// parser recovers from some syntax errors, so a module can be parsed (and transpiled) although
+    /// it has diagnostics, which is useful for tools such as editors. Errors that the parser can not
+    /// recover from are thrown by `parse_module()` instead.
+    ///
+    /// @return \Deno\AST\Diagnostic[]
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        let start = self.deno_ast_parsed_source.text_info().span().lo.0;
+        self.deno_ast_parsed_source
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| Diagnostic {
+                specifier: diagnostic.specifier.clone(),
+                message: diagnostic.message().to_string(),
+                line: diagnostic.display_position.line_number,
+                column: diagnostic.display_position.column_number,
+                start: (diagnostic.span.lo.0 - start) as usize,
+                end: (diagnostic.span.hi.0 - start) as usize,
+            })
+            .collect()
+    }
+
+    /// Get the specifiers of the modules that this module imports (including re-exports such as
+    /// `export * from "./mod.ts"`), and the names that it exports, for example to compute a
+    /// dependency graph without parsing the modules again. The specifiers are as written in the
+    /// source, and are not resolved. Dynamic imports are not included.
+    ///
+    /// @return array{imports: string[], exports: string[]}
+    fn module_info(&self) -> HashMap<&'static str, Vec<String>> {
+        use deno_ast::swc::ast::{
+            Decl, DefaultDecl, ExportSpecifier, ModuleDecl, ModuleExportName, ModuleItem,
+        };
+
+        let export_name = |name: &ModuleExportName| match name {
+            ModuleExportName::Ident(ident) => ident.sym.to_string(),
+            ModuleExportName::Str(str) => str.value.to_string(),
+        };
+        let mut imports: Vec<String> = vec![];
+        let mut exports: Vec<String> = vec![];
+        let mut add_import = |specifier: String| {
+            if !imports.contains(&specifier) {
+                imports.push(specifier);
+            }
+        };
+        for item in &self.deno_ast_parsed_source.module().body {
+            let module_decl = match item {
+                ModuleItem::ModuleDecl(module_decl) => module_decl,
+                ModuleItem::Stmt(_) => continue,
+            };
+            match module_decl {
+                ModuleDecl::Import(import_decl) => add_import(import_decl.src.value.to_string()),
+                ModuleDecl::ExportDecl(export_decl) => match &export_decl.decl {
+                    Decl::Var(var_decl) => {
+                        for declarator in &var_decl.decls {
+                            binding_names(&declarator.name, &mut exports);
+                        }
+                    }
+                    Decl::Fn(fn_decl) => exports.push(fn_decl.ident.sym.to_string()),
+                    Decl::Class(class_decl) => exports.push(class_decl.ident.sym.to_string()),
+                    Decl::TsInterface(interface_decl) => {
+                        exports.push(interface_decl.id.sym.to_string())
+                    }
+                    Decl::TsTypeAlias(type_alias_decl) => {
+                        exports.push(type_alias_decl.id.sym.to_string())
+                    }
+                    Decl::TsEnum(enum_decl) => exports.push(enum_decl.id.sym.to_string()),
+                    _ => (),
+                },
+                ModuleDecl::ExportNamed(named_export) => {
+                    if let Some(src) = &named_export.src {
+                        add_import(src.value.to_string());
+                    }
+                    for export_specifier in &named_export.specifiers {
+                        exports.push(match export_specifier {
+                            ExportSpecifier::Named(named) => {
+                                export_name(named.exported.as_ref().unwrap_or(&named.orig))
+                            }
+                            ExportSpecifier::Namespace(namespace) => export_name(&namespace.name),
+                            ExportSpecifier::Default(default) => default.exported.sym.to_string(),
+                        });
+                    }
+                }
+                ModuleDecl::ExportDefaultDecl(export_default_decl) => {
+                    if !matches!(export_default_decl.decl, DefaultDecl::TsInterfaceDecl(_)) {
+                        exports.push(String::from("default"));
+                    }
+                }
+                ModuleDecl::ExportDefaultExpr(_) => exports.push(String::from("default")),
+                ModuleDecl::ExportAll(export_all) => add_import(export_all.src.value.to_string()),
+                _ => (),
+            }
+        }
+        HashMap::from([("imports", imports), ("exports", exports)])
+    }
+
+    /// Get the zero-based line and column index of a byte offset in the source text, such as an
+    /// offset from a diagnostic.
+    ///
+    /// @return array{line: int, column: int}
+    fn line_and_column_index(&self, offset: usize) -> PhpResult<HashMap<&'static str, usize>> {
+        let text_info = self.deno_ast_parsed_source.text_info();
+        if offset > text_info.text_str().len() {
+            return Err(format!("Offset {} is outside of the source text.", offset).into());
+        }
+        let line_and_column = text_info.line_and_column_index(
+            text_info.span().lo + deno_ast::swc::common::BytePos(offset as u32),
+        );
+        Ok(HashMap::from([
+            ("line", line_and_column.line_index),
+            ("column", line_and_column.column_index),
+        ]))
+    }
+
+    /// Get the specifier of the module, as given in `ParseParams::specifier`.
+    fn specifier(&self) -> String {
+        self.deno_ast_parsed_source.specifier().to_string()
+    }
+
+    /// Get the media type that the module was parsed as, which is inferred from the file extension
+    /// of the specifier unless `ParseParams::media_type` was given. This is one of the
+    /// `Deno\AST\MediaType` constants, or the lowercase name of another media type known to
+    /// deno_ast, such as "mts" or "dts".
+    fn media_type(&self) -> &'static str {
+        media_type_name(self.deno_ast_parsed_source.media_type())
+    }
+
+    /// Get the byte offset in the source text of a zero-based line and column index.
+    fn byte_index(&self, line: usize, column: usize) -> PhpResult<usize> {
+        let text_info = self.deno_ast_parsed_source.text_info();
+        if line >= text_info.lines_count() {
+            return Err(format!("Line {} is outside of the source text.", line).into());
+        }
+        let byte_index = text_info.byte_index(deno_ast::LineAndColumnIndex {
+            line_index: line,
+            column_index: column,
+        });
+        Ok((byte_index - text_info.span().lo).0 as usize)
+    }
+}
+
+/// TypeScript compiler options used when transpiling.
+#[php_class(name = "Deno\\AST\\EmitOptions")]
+#[derive(Clone, Debug)]
+struct EmitOptions {
+    /// Which decorator transform to apply. This version of deno_ast only
+    /// implements `legacy` (TypeScript's `experimentalDecorators`), so `tc39`
+    /// (the standard decorators proposal) is rejected when transpiling.
+    /// Defaults to `legacy`.
     /// @var string
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    code: String,
-    /// The module type, can be "javascript" or "json".
+    pub decorators: String,
+    /// When emitting a legacy decorator, also emit experimental decorator meta
+    /// data, as used by `reflect-metadata`. Only supported when `decorators` is
+    /// `legacy`.  Defaults to `false`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub emit_metadata: bool,
+    /// What to do with import statements that only import types i.e. whether to
+    /// remove them (`remove`), keep them as side-effect imports (`preserve`) or
+    /// error (`error`). Matches the TypeScript `importsNotUsedAsValues` compiler
+    /// option. Defaults to `remove`.
     /// @var string
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    module_type: String,
-    /// The specified module URL of the import.
+    pub imports_not_used_as_values: String,
+    /// Should the source map be inlined in the emitted code file, or provided
+    /// as a separate file.  Defaults to `true`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub inline_source_map: bool,
+    /// Should the sources be inlined in the source map.  Defaults to `true`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub inline_sources: bool,
+    /// `true` if the program should use an implicit JSX import source/the "new"
+    /// JSX transforms.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub jsx_automatic: bool,
+    /// If JSX is automatic, if it is in development mode, meaning that it should
+    /// import `jsx-dev-runtime` and transform JSX using `jsxDEV` import from the
+    /// JSX import source as well as provide additional debug information to the
+    /// JSX factory.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub jsx_development: bool,
+    /// When transforming JSX, what value should be used for the JSX factory.
+    /// Defaults to `React.createElement`.
     /// @var string
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    module_url_specified: String,
-    /// The resolved module URL, after things like 301 redrects etc.
+    pub jsx_factory: String,
+    /// When transforming JSX, what value should be used for the JSX fragment
+    /// factory.  Defaults to `React.Fragment`.
     /// @var string
     #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    module_url_found: String,
+    pub jsx_fragment_factory: String,
+    /// The string module specifier to implicitly import JSX factories from when
+    /// transpiling JSX.
+    /// @var string
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub jsx_import_source: Option<String>,
+    /// Should a corresponding .map file be created for the output. This should be
+    /// false if inline_source_map is true. Defaults to `false`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub source_map: bool,
+    /// Should JSX be transformed or preserved.  Defaults to `true`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub transform_jsx: bool,
+    /// Should import declarations be transformed to variable declarations using
+    /// a dynamic import. This is useful for import & export declaration support
+    /// in script contexts such as the Deno REPL.  Defaults to `false`.
+    /// @var bool
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    pub var_decl_imports: bool,
 }
 
 #[php_impl(rename_methods = "none")]
-impl ModuleSource {
+impl EmitOptions {
+    fn __construct() -> EmitOptions {
+        return EmitOptions {
+            decorators: "legacy".into(),
+            emit_metadata: false,
+            imports_not_used_as_values: "remove".into(),
+            inline_source_map: true,
+            inline_sources: true,
+            source_map: false,
+            jsx_automatic: false,
+            jsx_development: false,
+            jsx_factory: "React.createElement".into(),
+            jsx_fragment_factory: "React.Fragment".into(),
+            jsx_import_source: None,
+            transform_jsx: true,
+            var_decl_imports: false,
+        };
+    }
+}
+
+impl TryFrom<&EmitOptions> for deno_ast::EmitOptions {
+    type Error = String;
+    fn try_from(options: &EmitOptions) -> Result<deno_ast::EmitOptions, String> {
+        let imports_not_used_as_values = match options.imports_not_used_as_values.as_str() {
+            "remove" => deno_ast::ImportsNotUsedAsValues::Remove,
+            "preserve" => deno_ast::ImportsNotUsedAsValues::Preserve,
+            "error" => deno_ast::ImportsNotUsedAsValues::Error,
+            other => {
+                return Err(format!(
+                    "Invalid imports_not_used_as_values \"{}\", expected \"remove\", \"preserve\" or \"error\".",
+                    other
+                ))
+            }
+        };
+
+        match options.decorators.as_str() {
+            "legacy" => (),
+            "tc39" if options.emit_metadata => {
+                return Err("EmitOptions::emit_metadata is only supported with legacy decorators, set EmitOptions::decorators to \"legacy\".".into())
+            }
+            "tc39" => {
+                return Err("TC39 decorators are not supported by this version of deno_ast, only \"legacy\" decorators can be transformed.".into())
+            }
+            other => {
+                return Err(format!(
+                    "Invalid decorators \"{}\", expected \"legacy\".",
+                    other
+                ))
+            }
+        }
+
+        Ok(deno_ast::EmitOptions {
+            emit_metadata: options.emit_metadata,
+            imports_not_used_as_values,
+            inline_source_map: options.inline_source_map,
+            inline_sources: options.inline_sources,
+            jsx_automatic: options.jsx_automatic,
+            jsx_development: options.jsx_development,
+            jsx_factory: options.jsx_factory.clone(),
+            jsx_fragment_factory: options.jsx_fragment_factory.clone(),
+            jsx_import_source: options.jsx_import_source.clone(),
+            source_map: options.source_map,
+            transform_jsx: options.transform_jsx,
+            var_decl_imports: options.var_decl_imports,
+        })
+    }
+}
+
+/// Parse a TypeScript (or similar) module. See ParseParams for options.
+#[php_function(ignore_module, name = "Deno\\AST\\parse_module")]
+fn parse_module(params: &ParseParams) -> PhpResult<ParsedSource> {
+    match deno_ast::parse_module(params.try_into()?) {
+        Ok(parsed_source) => Ok(ParsedSource {
+            deno_ast_parsed_source: parsed_source,
+        }),
+        Err(diagnostic) => Err(diagnostic.to_string().into()),
+    }
+}
+
+/// Parse and transpile a TypeScript (or similar) module in one call. The media type is inferred
+/// from the file extension of `specifier`, such as TypeScript for `file:///app/index.ts`. Throws
+/// an exception with the diagnostics if the source has syntax errors.
+#[php_function(ignore_module, name = "Deno\\AST\\transpile")]
+fn transpile(source: &str, specifier: &str, options: &EmitOptions) -> PhpResult<TranspiledSource> {
+    Ok(transpile_module(source, specifier, options)?)
+}
+
+/// Parse and transpile a module for `transpile()` and `TranspileCache`.
+fn transpile_module(
+    source: &str,
+    specifier: &str,
+    options: &EmitOptions,
+) -> Result<TranspiledSource, String> {
+    let params = ParseParams {
+        specifier: specifier.to_string(),
+        text_info: source.to_string(),
+        media_type: "".to_string(),
+        syntax: None,
+    };
+    let parsed_source = match deno_ast::parse_module((&params).try_into()?) {
+        Ok(parsed_source) => parsed_source,
+        Err(diagnostic) => return Err(diagnostic.to_string()),
+    };
+    let diagnostics = parsed_source.diagnostics();
+    if !diagnostics.is_empty() {
+        let diagnostics: Vec<String> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect();
+        return Err(diagnostics.join("\n"));
+    }
+    match parsed_source.transpile(&options.try_into()?) {
+        Ok(transpiled_source) => Ok(TranspiledSource {
+            text: transpiled_source.text,
+            source_map: transpiled_source.source_map,
+        }),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// A cache of transpiled modules on disk, so a module is only transpiled again when its source code
+/// changes. Entries are keyed by a hash of the source code, the specifier and the `EmitOptions`, so
+/// changed modules never need to be removed from the cache. Pass the cache to
+/// `Deno\Core\FsModuleLoader`, or call `transpile()` from a `Deno\Core\ModuleLoader`.
+#[php_class(name = "Deno\\AST\\TranspileCache")]
+#[derive(Clone, Debug)]
+struct TranspileCache {
+    dir: std::path::PathBuf,
+    options: EmitOptions,
+}
+
+#[php_impl(rename_methods = "none")]
+impl TranspileCache {
+    /// The cache directory is created if it doesn't exist. Modules are transpiled with the default
+    /// `EmitOptions` unless `options` is given.
     #[constructor]
-    fn __construct(
-        code: String,
-        module_type: String,
-        module_url_specified: String,
-        module_url_found: String,
-    ) -> Self {
-        Self {
-            code,
-            module_type,
-            module_url_specified,
-            module_url_found,
+    #[optional(options)]
+    fn __construct(dir: &str, options: Option<&EmitOptions>) -> PhpResult<Self> {
+        if let Err(error) = std::fs::create_dir_all(dir) {
+            return Err(format!("Unable to create cache directory {}: {}", dir, error).into());
+        }
+        Ok(Self {
+            dir: std::path::PathBuf::from(dir),
+            options: options.cloned().unwrap_or_else(EmitOptions::__construct),
+        })
+    }
+
+    /// Transpile a module in the same way as `Deno\AST\transpile()`, returning the cached result
+    /// when the same source code has been transpiled before.
+    fn transpile(&self, source: &str, specifier: &str) -> PhpResult<TranspiledSource> {
+        Ok(self.transpile_cached(source, specifier)?)
+    }
+}
+
+impl TranspileCache {
+    fn transpile_cached(&self, source: &str, specifier: &str) -> Result<TranspiledSource, String> {
+        let path = self.dir.join(format!("{}.js", self.key(source, specifier)));
+        let map_path = path.with_extension("js.map");
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            let source_map = std::fs::read_to_string(&map_path).ok();
+            // The source map is written before the text, so it's only missing if it wasn't emitted.
+            if source_map.is_some() || !self.options.source_map {
+                return Ok(TranspiledSource { text, source_map });
+            }
+        }
+        let transpiled_source = transpile_module(source, specifier, &self.options)?;
+        if let Some(source_map) = &transpiled_source.source_map {
+            write_cache_file(&map_path, source_map)?;
+        }
+        write_cache_file(&path, &transpiled_source.text)?;
+        Ok(transpiled_source)
+    }
+
+    /// The hex encoded SHA-256 hash of everything that the transpiled output depends on.
+    fn key(&self, source: &str, specifier: &str) -> String {
+        use sha2::Digest;
+
+        let options = format!("{:?}", self.options);
+        let mut hasher = sha2::Sha256::new();
+        for part in [
+            env!("CARGO_PKG_VERSION"),
+            options.as_str(),
+            specifier,
+            source,
+        ] {
+            hasher.update(part.len().to_le_bytes());
+            hasher.update(part);
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Write a file in the transpile cache, via a temporary file that is renamed so other processes
+/// using the cache never read a partially written file.
+fn write_cache_file(path: &std::path::Path, contents: &str) -> Result<(), String> {
+    let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    match std::fs::write(&temp_path, contents).and_then(|()| std::fs::rename(&temp_path, path)) {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(format!(
+                "Unable to write cache file {}: {}",
+                path.display(),
+                error
+            ))
+        }
+    }
+}
+
+/// Transpile a line of REPL input in to a script that can be run with `execute_script()`.
+///
+/// Imports are transpiled to `const ... = await import(...)`, which needs an async context, so the
+/// input is wrapped in an async function. Top level declarations are hoisted out of the function
+/// as `var`s (which may be redeclared) and the declarations become assignments to them, so they
+/// stay visible to later inputs. The function returns the value of the last expression statement.
+fn repl_script(source_code: &str) -> Result<String, String> {
+    use deno_ast::swc::ast::{Decl, ModuleItem, Stmt};
+    use deno_ast::swc::common::Spanned;
+
+    let parsed_source = match deno_ast::parse_module(deno_ast::ParseParams {
+        specifier: "file:///repl.ts".to_string(),
+        text_info: deno_ast::SourceTextInfo::from_string(source_code.to_string()),
+        media_type: deno_ast::MediaType::TypeScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    }) {
+        Ok(parsed_source) => parsed_source,
+        Err(diagnostic) => return Err(diagnostic.to_string()),
+    };
+    let transpiled_source = match parsed_source.transpile(&deno_ast::EmitOptions {
+        inline_source_map: false,
+        var_decl_imports: true,
+        ..Default::default()
+    }) {
+        Ok(transpiled_source) => transpiled_source,
+        Err(error) => return Err(error.to_string()),
+    };
+
+    // Parse the transpiled JavaScript again, so the declarations generated for imports are
+    // rewritten along with the ones from the input.
+    let parsed_source = match deno_ast::parse_module(deno_ast::ParseParams {
+        specifier: "file:///repl.js".to_string(),
+        text_info: deno_ast::SourceTextInfo::from_string(transpiled_source.text),
+        media_type: deno_ast::MediaType::JavaScript,
+        capture_tokens: false,
+        scope_analysis: false,
+        maybe_syntax: None,
+    }) {
+        Ok(parsed_source) => parsed_source,
+        Err(diagnostic) => return Err(diagnostic.to_string()),
+    };
+    let text = parsed_source.text_info().text_str();
+    let span_text =
+        |span: deno_ast::swc::common::Span| &text[span.lo.0 as usize..span.hi.0 as usize];
+
+    let items = &parsed_source.module().body;
+    let mut names: Vec<String> = Vec::new();
+    let mut body = String::new();
+    for (index, item) in items.iter().enumerate() {
+        match item {
+            ModuleItem::Stmt(Stmt::Decl(Decl::Var(var_decl))) => {
+                for declarator in &var_decl.decls {
+                    binding_names(&declarator.name, &mut names);
+                    let init = match &declarator.init {
+                        Some(init) => span_text(init.span()),
+                        None => "undefined",
+                    };
+                    body.push_str(&format!(
+                        "({} = {});\n",
+                        span_text(declarator.name.span()),
+                        init
+                    ));
+                }
+            }
+            ModuleItem::Stmt(Stmt::Decl(Decl::Fn(fn_decl))) => {
+                names.push(fn_decl.ident.sym.to_string());
+                // Function declarations are hoisted, so assign them first.
+                body.insert_str(0, &format!("globalThis.{0} = {0};\n", fn_decl.ident.sym));
+                body.push_str(span_text(item.span()));
+                body.push('\n');
+            }
+            ModuleItem::Stmt(Stmt::Decl(Decl::Class(class_decl))) => {
+                names.push(class_decl.ident.sym.to_string());
+                body.push_str(&format!(
+                    "{} = {};\n",
+                    class_decl.ident.sym,
+                    span_text(item.span())
+                ));
+            }
+            ModuleItem::Stmt(Stmt::Expr(expr_stmt)) if index == items.len() - 1 => {
+                body.push_str(&format!("return ({});\n", span_text(expr_stmt.expr.span())));
+            }
+            _ => {
+                body.push_str(span_text(item.span()));
+                body.push('\n');
+            }
+        }
+    }
+
+    let declarations = match names.is_empty() {
+        true => String::new(),
+        false => format!("var {};\n", names.join(", ")),
+    };
+    Ok(format!("{}(async () => {{\n{}}})();", declarations, body))
+}
+
+/// Collect the identifiers bound by a declaration's pattern, such as `a` and `b` in `const { a, b: [b] } = ...`.
+fn binding_names(pat: &deno_ast::swc::ast::Pat, names: &mut Vec<String>) {
+    use deno_ast::swc::ast::{ObjectPatProp, Pat};
+
+    match pat {
+        Pat::Ident(binding_ident) => names.push(binding_ident.id.sym.to_string()),
+        Pat::Array(array_pat) => {
+            for elem in array_pat.elems.iter().flatten() {
+                binding_names(elem, names);
+            }
+        }
+        Pat::Object(object_pat) => {
+            for prop in &object_pat.props {
+                match prop {
+                    ObjectPatProp::KeyValue(key_value) => binding_names(&key_value.value, names),
+                    ObjectPatProp::Assign(assign) => names.push(assign.key.sym.to_string()),
+                    ObjectPatProp::Rest(rest) => binding_names(&rest.arg, names),
+                }
+            }
         }
+        Pat::Rest(rest_pat) => binding_names(&rest_pat.arg, names),
+        Pat::Assign(assign_pat) => binding_names(&assign_pat.left, names),
+        _ => (),
+    }
+}
+
+/// Bundle an ES module and the modules it imports into a single ES module, for example to
+/// precompute a file for a snapshot. Modules are loaded with `module_loader` (see
+/// `Deno\Core\RuntimeOptions::module_loader`) and transpiled with `options`. The bundle
+/// exports the exports of the entry module.
+///
+/// Each module is evaluated in a function in the order it is imported, so top level await and
+/// cyclic imports are not supported. Imported bindings are read when the importing module is
+/// evaluated rather than being live bindings. Dynamic `import()`s are left as-is, and source maps
+/// are not emitted.
+///
+/// @param \Deno\Core\ModuleLoader|\Deno\Core\FsModuleLoader $module_loader
+#[php_function(ignore_module, name = "Deno\\AST\\bundle")]
+fn bundle(
+    entry_specifier: &str,
+    module_loader: CloneableZval,
+    options: &EmitOptions,
+) -> PhpResult<TranspiledSource> {
+    let mut emit_options: deno_ast::EmitOptions = options.try_into()?;
+    emit_options.inline_source_map = false;
+    emit_options.source_map = false;
+    let mut bundler = Bundler {
+        module_loader: module_loader_from_zval(module_loader),
+        emit_options,
+        modules: HashMap::new(),
+        visiting: std::collections::HashSet::new(),
+        text: String::from(
+            "const __bundle_modules = {};\n\
+             const __bundle_export = (exports, name, get) =>\n  \
+             Object.defineProperty(exports, name, { enumerable: true, get });\n",
+        ),
+    };
+    match bundler.bundle(entry_specifier) {
+        Ok(()) => Ok(TranspiledSource {
+            text: bundler.text,
+            source_map: None,
+        }),
+        Err(error) => Err(error.to_string().into()),
     }
 }
 
-/// ParseParams represent the arguments for Deno\AST\parse_module, which is used to
-/// parse TypeScript.
-#[php_class(name = "Deno\\AST\\ParseParams")]
-struct ParseParams {
-    /// The ES6 module specifier, must be a URL.
-    /// @var string
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    specifier: String,
-    /// The source code of the ES6 module.
-    /// @var string
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    text_info: String,
-    /// The type of the module, specified as a mime-type such as application/typescript etc.
-    /// @var string
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    media_type: String,
+/// The names exported by a bundled module, and the modules it re-exports with `export * from`.
+struct BundledModule {
+    export_names: Vec<String>,
+    export_all_specifiers: Vec<String>,
 }
 
-#[php_impl(rename_methods = "none")]
-impl ParseParams {
-    fn __construct() -> PhpResult<Self> {
-        Ok(Self {
-            specifier: "".to_string(),
-            media_type: "javascript".to_string(),
-            text_info: "".to_string(),
-        })
-    }
+struct Bundler {
+    module_loader: std::rc::Rc<dyn deno_core::ModuleLoader>,
+    emit_options: deno_ast::EmitOptions,
+    modules: HashMap<String, BundledModule>,
+    visiting: std::collections::HashSet<String>,
+    text: String,
 }
 
-impl TryFrom<&ParseParams> for deno_ast::ParseParams {
-    type Error = String;
-    fn try_from(params: &ParseParams) -> Result<Self, String> {
-        let media_type = match url::Url::parse(params.specifier.as_str()) {
-            Ok(t) => t,
-            Err(err) => return Err(err.to_string()),
+impl Bundler {
+    fn bundle(&mut self, entry_specifier: &str) -> Result<(), Error> {
+        let entry_specifier = self.module_loader.resolve(entry_specifier, ".", true)?;
+        self.add_module(&entry_specifier)?;
+
+        let mut export_names = self.export_names(entry_specifier.as_str());
+        export_names.sort();
+        export_names.dedup();
+        self.text.push_str(&format!(
+            "const __bundle_entry = {};\n",
+            bundled_module_expr(&entry_specifier)
+        ));
+        if export_names.iter().any(|name| name == "default") {
+            self.text
+                .push_str("export default __bundle_entry.default;\n");
+        }
+        export_names.retain(|name| name != "default");
+        if !export_names.is_empty() {
+            self.text.push_str(&format!(
+                "export const {{ {} }} = __bundle_entry;\n",
+                export_names.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// All the names exported by a module, including those it re-exports with `export * from`.
+    fn export_names(&self, specifier: &str) -> Vec<String> {
+        let module = &self.modules[specifier];
+        let mut export_names = module.export_names.clone();
+        for export_all_specifier in &module.export_all_specifiers {
+            export_names.extend(
+                self.export_names(export_all_specifier)
+                    .into_iter()
+                    .filter(|name| name != "default"),
+            );
+        }
+        export_names
+    }
+
+    /// Add a module to the bundle, after the modules it imports.
+    fn add_module(&mut self, specifier: &deno_core::ModuleSpecifier) -> Result<(), Error> {
+        use deno_ast::swc::ast::{
+            Decl, DefaultDecl, ExportSpecifier, ImportSpecifier, ModuleDecl, ModuleExportName,
+            ModuleItem,
         };
+        use deno_ast::swc::common::Spanned;
 
-        Ok(deno_ast::ParseParams {
-            specifier: params.specifier.clone(),
-            text_info: deno_ast::SourceTextInfo::from_string(params.text_info.clone()),
+        if self.modules.contains_key(specifier.as_str()) {
+            return Ok(());
+        }
+        if !self.visiting.insert(specifier.to_string()) {
+            anyhow::bail!(
+                "Unable to bundle {}, cyclic imports are not supported.",
+                specifier
+            );
+        }
+
+        let module_source =
+            futures::executor::block_on(self.module_loader.load(specifier, None, false))?;
+        let code = String::from_utf8(module_source.code.to_vec())?;
+        let (code, media_type) = match module_source.module_type {
+            deno_core::ModuleType::Json => (
+                format!("export default {};", code),
+                deno_ast::MediaType::JavaScript,
+            ),
+            _ => match deno_ast::MediaType::from(specifier) {
+                deno_ast::MediaType::Unknown => (code, deno_ast::MediaType::JavaScript),
+                media_type => (code, media_type),
+            },
+        };
+        let parsed_source = deno_ast::parse_module(deno_ast::ParseParams {
+            specifier: specifier.to_string(),
+            text_info: deno_ast::SourceTextInfo::from_string(code),
+            media_type,
             capture_tokens: false,
+            scope_analysis: false,
             maybe_syntax: None,
+        })?;
+        let transpiled_source = parsed_source.transpile(&self.emit_options)?;
+
+        // Parse the transpiled JavaScript, to rewrite its imports and exports.
+        let parsed_source = deno_ast::parse_module(deno_ast::ParseParams {
+            specifier: specifier.to_string(),
+            text_info: deno_ast::SourceTextInfo::from_string(transpiled_source.text),
+            media_type: deno_ast::MediaType::JavaScript,
+            capture_tokens: false,
             scope_analysis: false,
-            media_type: deno_ast::MediaType::from_content_type(
-                &media_type,
-                params.media_type.clone(),
-            ),
-        })
+            maybe_syntax: None,
+        })?;
+        let text = parsed_source.text_info().text_str();
+        let span_text =
+            |span: deno_ast::swc::common::Span| &text[span.lo.0 as usize..span.hi.0 as usize];
+        let export_name = |name: &ModuleExportName| match name {
+            ModuleExportName::Ident(ident) => ident.sym.to_string(),
+            ModuleExportName::Str(str) => str.value.to_string(),
+        };
+
+        let mut body = String::new();
+        // The exported names, and the expressions for their values.
+        let mut exports: Vec<(String, String)> = vec![];
+        let mut export_all_specifiers: Vec<deno_core::ModuleSpecifier> = vec![];
+        for item in &parsed_source.module().body {
+            let module_decl = match item {
+                ModuleItem::ModuleDecl(module_decl) => module_decl,
+                ModuleItem::Stmt(_) => {
+                    body.push_str(span_text(item.span()));
+                    body.push('\n');
+                    continue;
+                }
+            };
+            match module_decl {
+                ModuleDecl::Import(import_decl) => {
+                    let module = self.add_dependency(&import_decl.src.value, specifier)?;
+                    for import_specifier in &import_decl.specifiers {
+                        let (local, imported) = match import_specifier {
+                            ImportSpecifier::Named(named) => {
+                                let imported = match &named.imported {
+                                    Some(imported) => export_name(imported),
+                                    None => named.local.sym.to_string(),
+                                };
+                                (&named.local, format!("[{}]", js_string(&imported)))
+                            }
+                            ImportSpecifier::Default(default) => {
+                                (&default.local, String::from(".default"))
+                            }
+                            ImportSpecifier::Namespace(namespace) => {
+                                (&namespace.local, String::new())
+                            }
+                        };
+                        body.push_str(&format!(
+                            "const {} = {}{};\n",
+                            local.sym,
+                            bundled_module_expr(&module),
+                            imported
+                        ));
+                    }
+                }
+                ModuleDecl::ExportDecl(export_decl) => {
+                    let mut names = vec![];
+                    match &export_decl.decl {
+                        Decl::Var(var_decl) => {
+                            for declarator in &var_decl.decls {
+                                binding_names(&declarator.name, &mut names);
+                            }
+                        }
+                        Decl::Fn(fn_decl) => names.push(fn_decl.ident.sym.to_string()),
+                        Decl::Class(class_decl) => names.push(class_decl.ident.sym.to_string()),
+                        _ => (),
+                    }
+                    exports.extend(names.into_iter().map(|name| (name.clone(), name)));
+                    body.push_str(span_text(export_decl.decl.span()));
+                    body.push('\n');
+                }
+                ModuleDecl::ExportNamed(named_export) => {
+                    let module = match &named_export.src {
+                        Some(src) => Some(self.add_dependency(&src.value, specifier)?),
+                        None => None,
+                    };
+                    for export_specifier in &named_export.specifiers {
+                        match (export_specifier, &module) {
+                            (ExportSpecifier::Named(named), module) => {
+                                let orig = export_name(&named.orig);
+                                let exported = match &named.exported {
+                                    Some(exported) => export_name(exported),
+                                    None => orig.clone(),
+                                };
+                                let expr = match module {
+                                    Some(module) => format!(
+                                        "{}[{}]",
+                                        bundled_module_expr(module),
+                                        js_string(&orig)
+                                    ),
+                                    None => orig,
+                                };
+                                exports.push((exported, expr));
+                            }
+                            (ExportSpecifier::Namespace(namespace), Some(module)) => {
+                                exports.push((
+                                    export_name(&namespace.name),
+                                    bundled_module_expr(module),
+                                ));
+                            }
+                            (ExportSpecifier::Default(default), Some(module)) => {
+                                exports.push((
+                                    default.exported.sym.to_string(),
+                                    format!("{}.default", bundled_module_expr(module)),
+                                ));
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                ModuleDecl::ExportDefaultDecl(export_default_decl) => {
+                    let ident = match &export_default_decl.decl {
+                        DefaultDecl::Fn(fn_expr) => fn_expr.ident.as_ref(),
+                        DefaultDecl::Class(class_expr) => class_expr.ident.as_ref(),
+                        DefaultDecl::TsInterfaceDecl(_) => continue,
+                    };
+                    let decl_text = span_text(export_default_decl.decl.span());
+                    match ident {
+                        // Named functions and classes are declarations, which may be used by name.
+                        Some(ident) => {
+                            body.push_str(&format!("{}\n", decl_text));
+                            exports.push((String::from("default"), ident.sym.to_string()));
+                        }
+                        None => {
+                            body.push_str(&format!("const __bundle_default = {};\n", decl_text));
+                            exports
+                                .push((String::from("default"), String::from("__bundle_default")));
+                        }
+                    }
+                }
+                ModuleDecl::ExportDefaultExpr(export_default_expr) => {
+                    body.push_str(&format!(
+                        "const __bundle_default = ({});\n",
+                        span_text(export_default_expr.expr.span())
+                    ));
+                    exports.push((String::from("default"), String::from("__bundle_default")));
+                }
+                ModuleDecl::ExportAll(export_all) => {
+                    export_all_specifiers
+                        .push(self.add_dependency(&export_all.src.value, specifier)?);
+                }
+                _ => {
+                    body.push_str(span_text(item.span()));
+                    body.push('\n');
+                }
+            }
+        }
+
+        // The exports are getters, so they can be defined before the module's body is evaluated.
+        self.text.push_str(&format!(
+            "// {}\n__bundle_modules[{}] = (() => {{\n\
+             const __bundle_exports = Object.create(null);\n",
+            specifier,
+            js_string(specifier.as_str())
+        ));
+        for (name, expr) in &exports {
+            self.text.push_str(&format!(
+                "__bundle_export(__bundle_exports, {}, () => {});\n",
+                js_string(name),
+                expr
+            ));
+        }
+        for export_all_specifier in &export_all_specifiers {
+            self.text.push_str(&format!(
+                "for (const name of Object.keys({0})) {{\n  \
+                 if (name !== \"default\" && !(name in __bundle_exports)) {{\n    \
+                 __bundle_export(__bundle_exports, name, () => {0}[name]);\n  }}\n}}\n",
+                bundled_module_expr(export_all_specifier)
+            ));
+        }
+        self.text.push_str(&body);
+        self.text.push_str("return __bundle_exports;\n})();\n");
+
+        self.visiting.remove(specifier.as_str());
+        self.modules.insert(
+            specifier.to_string(),
+            BundledModule {
+                export_names: exports.into_iter().map(|(name, _)| name).collect(),
+                export_all_specifiers: export_all_specifiers
+                    .iter()
+                    .map(|specifier| specifier.to_string())
+                    .collect(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Add an imported module to the bundle, returning its resolved specifier.
+    fn add_dependency(
+        &mut self,
+        import_specifier: &str,
+        referrer: &deno_core::ModuleSpecifier,
+    ) -> Result<deno_core::ModuleSpecifier, Error> {
+        let specifier = self
+            .module_loader
+            .resolve(import_specifier, referrer.as_str(), false)?;
+        self.add_module(&specifier)?;
+        Ok(specifier)
     }
 }
 
-/// The transpiled code to TypeScript source code, this is the result of `Deno\AST\ParsedSource::transpile().
-#[php_class(name = "Deno\\AST\\TranspiledSource")]
-struct TranspiledSource {
-    /// Transpiled text.
-    /// @var string
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub text: String,
-    /// Source map back to the original file.
-    /// @var string|null
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub source_map: Option<String>,
+/// The expression for the exports of a bundled module.
+fn bundled_module_expr(specifier: &deno_core::ModuleSpecifier) -> String {
+    format!("__bundle_modules[{}]", js_string(specifier.as_str()))
 }
 
-#[php_class(name = "Deno\\AST\\ParsedSource")]
-struct ParsedSource {
-    deno_ast_parsed_source: deno_ast::ParsedSource,
+/// Quote a string as a JavaScript string literal.
+fn js_string(string: &str) -> String {
+    deno_core::serde_json::to_string(string).unwrap()
+}
+
+// Zval doesn't implement Clone, which means that Zval's can not
+// be passed to `ZendCallable.try_call()`, so we have to wrap it
+// in a Cloneable wrapper.
+#[derive(Debug)]
+struct CloneableZval(Zval);
+
+impl FromZval<'_> for CloneableZval {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn from_zval(zval: &'_ Zval) -> Option<Self> {
+        Some(Self(zval.shallow_clone()))
+    }
+}
+
+impl IntoZval for CloneableZval {
+    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
+    fn set_zval(self, zv: &mut Zval, _: bool) -> ext_php_rs::error::Result<()> {
+        *zv = self.0;
+        Ok(())
+    }
+    fn into_zval(self, _persistent: bool) -> ext_php_rs::error::Result<Zval> {
+        Ok(self.0)
+    }
+}
+
+impl Clone for CloneableZval {
+    fn clone(&self) -> Self {
+        Self(self.0.shallow_clone())
+    }
+}
+
+/// Wraps a PHP string so that it is passed to JavaScript as a `Uint8Array` of its bytes, rather
+/// than a JavaScript string (which would mangle any bytes that are not valid UTF-8). Use this to
+/// return raw bytes, such as from `file_get_contents()`, from ops.
+#[php_class(name = "Deno\\Core\\Uint8Array")]
+struct Uint8Array {
+    bytes: Vec<u8>,
 }
 
-/// Represents the parsed AST via `Deno\AST\parse_modue()`.
 #[php_impl(rename_methods = "none")]
-impl ParsedSource {
-    /// Transpile the ASP to TypeScript, with the provided EmitOptions. Throws an exception or returns Deno\AST\TranspiledSource
-    fn transpile(&self, options: &EmitOptions) -> PhpResult<TranspiledSource> {
-        match self.deno_ast_parsed_source.transpile(&options.into()) {
-            Ok(transpiled_source) => Ok(TranspiledSource {
-                text: transpiled_source.text,
-                source_map: transpiled_source.source_map,
-            }),
-            Err(error) => Err(error.to_string().into()),
+impl Uint8Array {
+    /// @param string $bytes
+    #[constructor]
+    fn __construct(bytes: &Zval) -> PhpResult<Self> {
+        match bytes.binary() {
+            Some(bytes) => Ok(Self { bytes }),
+            None => Err("Uint8Array bytes must be a string.".into()),
         }
     }
 }
 
-/// TypeScript compiler options used when transpiling.
-#[php_class(name = "Deno\\AST\\EmitOptions")]
-struct EmitOptions {
-    /// When emitting a legacy decorator, also emit experimental decorator meta
-    /// data.  Defaults to `false`.
-    /// @var bool
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub emit_metadata: bool,
-    /// Should the source map be inlined in the emitted code file, or provided
-    /// as a separate file.  Defaults to `true`.
-    /// @var bool
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub inline_source_map: bool,
-    /// Should the sources be inlined in the source map.  Defaults to `true`.
-    /// @var bool
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub inline_sources: bool,
-    /// `true` if the program should use an implicit JSX import source/the "new"
-    /// JSX transforms.
-    /// @var bool
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub jsx_automatic: bool,
-    /// If JSX is automatic, if it is in development mode, meaning that it should
-    /// import `jsx-dev-runtime` and transform JSX using `jsxDEV` import from the
-    /// JSX import source as well as provide additional debug information to the
-    /// JSX factory.
-    /// @var bool
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub jsx_development: bool,
-    /// When transforming JSX, what value should be used for the JSX factory.
-    /// Defaults to `React.createElement`.
-    /// @var string
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub jsx_factory: String,
-    /// When transforming JSX, what value should be used for the JSX fragment
-    /// factory.  Defaults to `React.Fragment`.
-    /// @var string
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub jsx_fragment_factory: String,
-    /// The string module specifier to implicitly import JSX factories from when
-    /// transpiling JSX.
-    /// @var string
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub jsx_import_source: Option<String>,
-    /// Should a corresponding .map file be created for the output. This should be
-    /// false if inline_source_map is true. Defaults to `false`.
-    /// @var bool
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub source_map: bool,
-    /// Should JSX be transformed or preserved.  Defaults to `true`.
-    /// @var bool
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub transform_jsx: bool,
-    /// Should import declarations be transformed to variable declarations using
-    /// a dynamic import. This is useful for import & export declaration support
-    /// in script contexts such as the Deno REPL.  Defaults to `false`.
-    /// @var bool
-    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
-    pub var_decl_imports: bool,
+/// The memory of an `ArrayBuffer` or typed array that JavaScript transferred to an op with
+/// `Deno.core.transfer()`, which is passed to the op without copying it into a PHP string. Use
+/// `read()` to copy parts of it, for example to process a large buffer in chunks.
+///
+/// Transferring a buffer detaches it (the whole `ArrayBuffer`, for a typed array) when the op is
+/// called, so the buffer has a length of 0 in JavaScript afterwards and must not be used again.
+/// Buffers that can not be detached, such as the memory of a WebAssembly instance, are shared
+/// instead, so JavaScript's later changes to them are visible in PHP. Returning the object from an
+/// op transfers the memory back to JavaScript as a `Uint8Array`, again without copying it.
+#[php_class(name = "Deno\\Core\\ArrayBuffer")]
+struct ArrayBuffer {
+    backing_store: v8::SharedRef<v8::BackingStore>,
+    offset: usize,
+    length: usize,
 }
 
 #[php_impl(rename_methods = "none")]
-impl EmitOptions {
-    fn __construct() -> EmitOptions {
-        return EmitOptions {
-            emit_metadata: false,
-            inline_source_map: true,
-            inline_sources: true,
-            source_map: false,
-            jsx_automatic: false,
-            jsx_development: false,
-            jsx_factory: "React.createElement".into(),
-            jsx_fragment_factory: "React.Fragment".into(),
-            jsx_import_source: None,
-            transform_jsx: true,
-            var_decl_imports: false,
+impl ArrayBuffer {
+    /// The length of the buffer in bytes.
+    fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Copy `length` bytes starting at `offset` into a binary string, or the bytes up to the end of
+    /// the buffer when `length` is null.
+    ///
+    /// @return string
+    #[optional(offset)]
+    fn read(&self, offset: Option<usize>, length: Option<usize>) -> PhpResult<Zval> {
+        let offset = offset.unwrap_or(0);
+        if offset > self.length {
+            return Err(format!("Offset {} is outside of the buffer.", offset).into());
+        }
+        let length = length
+            .unwrap_or(self.length - offset)
+            .min(self.length - offset);
+        let start = self.offset + offset;
+        let bytes: Vec<u8> = self.backing_store[start..start + length]
+            .iter()
+            .map(|byte| byte.get())
+            .collect();
+        let mut zval = Zval::new();
+        zval.set_binary(bytes);
+        Ok(zval)
+    }
+}
+
+/// Convert a buffer transferred with `Deno.core.transfer()` to a `Deno\Core\ArrayBuffer`, detaching it.
+fn transferred_array_buffer(
+    buffer: v8::Local<v8::Value>,
+    scope: &mut v8::HandleScope,
+) -> Option<Zval> {
+    let (array_buffer, offset, length) = if buffer.is_array_buffer_view() {
+        let view = v8::Local::<v8::ArrayBufferView>::try_from(buffer).unwrap();
+        (view.buffer(scope)?, view.byte_offset(), view.byte_length())
+    } else {
+        let array_buffer = v8::Local::<v8::ArrayBuffer>::try_from(buffer).ok()?;
+        (array_buffer, 0, array_buffer.byte_length())
+    };
+    let backing_store = array_buffer.get_backing_store();
+    if array_buffer.is_detachable() {
+        array_buffer.detach();
+    }
+    ArrayBuffer {
+        backing_store,
+        offset,
+        length,
+    }
+    .into_zval(false)
+    .ok()
+}
+
+/// A JavaScript string that is not valid UTF-16, as it contains lone surrogates, which can't be
+/// converted to a PHP (UTF-8) string exactly. Strings are converted to it when
+/// `RuntimeOptions::lossless_strings` is set, and it is converted back to the identical JavaScript
+/// string when passed to JavaScript.
+#[php_class(name = "Deno\\Core\\Utf16String")]
+#[derive(Clone, Debug)]
+struct Utf16String {
+    code_units: Vec<u16>,
+}
+
+#[php_impl(rename_methods = "none")]
+impl Utf16String {
+    /// Create a string from its UTF-16LE encoding, such as a string returned by `to_utf16le()`.
+    ///
+    /// @param string $utf16le
+    #[constructor]
+    fn __construct(utf16le: &Zval) -> PhpResult<Self> {
+        let bytes: Vec<u8> = match utf16le.binary() {
+            Some(bytes) if bytes.len() % 2 == 0 => bytes,
+            Some(_) => return Err("UTF-16LE data must have an even number of bytes.".into()),
+            None => return Err("UTF-16LE data must be a string.".into()),
         };
+        Ok(Self {
+            code_units: bytes
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect(),
+        })
+    }
+
+    /// The number of UTF-16 code units, which is the string's `length` in JavaScript.
+    fn length(&self) -> usize {
+        self.code_units.len()
+    }
+
+    /// Get the exact code units as a UTF-16LE binary string.
+    ///
+    /// @return string
+    fn to_utf16le(&self) -> Zval {
+        let mut zval = Zval::new();
+        zval.set_binary(
+            self.code_units
+                .iter()
+                .flat_map(|code_unit| code_unit.to_le_bytes())
+                .collect::<Vec<u8>>(),
+        );
+        zval
+    }
+
+    /// Convert to a UTF-8 string. Throws an exception if the string contains lone surrogates,
+    /// unless `lossy` is true, which replaces them with U+FFFD (�).
+    #[optional(lossy)]
+    fn to_utf8(&self, lossy: Option<bool>) -> PhpResult<String> {
+        if lossy.unwrap_or(false) {
+            return Ok(String::from_utf16_lossy(&self.code_units));
+        }
+        match String::from_utf16(&self.code_units) {
+            Ok(string) => Ok(string),
+            Err(_) => Err(
+                "The string contains lone surrogates, which can't be converted to UTF-8.".into(),
+            ),
+        }
+    }
+}
+
+impl Utf16String {
+    /// Copy a JavaScript string that contains lone surrogates, or return `None` for a valid one,
+    /// which converts to UTF-8 without loss.
+    fn from_ill_formed(scope: &mut v8::HandleScope, string: v8::Local<v8::String>) -> Option<Self> {
+        if string.contains_only_onebyte() {
+            return None;
+        }
+        let mut code_units = vec![0; string.length()];
+        string.write(
+            scope,
+            &mut code_units,
+            0,
+            v8::WriteOptions::NO_NULL_TERMINATION,
+        );
+        let has_lone_surrogates =
+            char::decode_utf16(code_units.iter().copied()).any(|char| char.is_err());
+        has_lone_surrogates.then(|| Self { code_units })
     }
 }
 
-impl From<&EmitOptions> for deno_ast::EmitOptions {
-    fn from(options: &EmitOptions) -> deno_ast::EmitOptions {
-        deno_ast::EmitOptions {
-            emit_metadata: options.emit_metadata,
-            imports_not_used_as_values: deno_ast::ImportsNotUsedAsValues::Remove,
-            inline_source_map: options.inline_source_map,
-            inline_sources: options.inline_sources,
-            jsx_automatic: options.jsx_automatic,
-            jsx_development: options.jsx_development,
-            jsx_factory: options.jsx_factory.clone(),
-            jsx_fragment_factory: options.jsx_fragment_factory.clone(),
-            jsx_import_source: options.jsx_import_source.clone(),
-            source_map: options.source_map,
-            transform_jsx: options.transform_jsx,
-            var_decl_imports: options.var_decl_imports,
+/// Set in the isolate's slot when `RuntimeOptions::lossless_strings` is set.
+struct LosslessStrings;
+
+/// A JavaScript object, which is what objects are converted to in PHP, other than those with a null
+/// prototype, which become arrays. The object's own enumerable properties are converted to PHP
+/// properties of the `Deno\Core\V8Object`, so they can be read as `$object->name` or with `get()`.
+/// Properties with symbol keys are skipped, as PHP has no equivalent of them.
+///
+/// The properties are a snapshot taken when the object is converted, so changes made in JavaScript
+/// afterwards are not visible in PHP, and `set()` only changes the PHP object. Passing the object
+/// back to JavaScript creates a new object with its current properties.
+#[php_class(name = "Deno\\Core\\V8Object")]
+#[derive(Default)]
+struct V8Object {}
+
+#[php_impl(rename_methods = "none")]
+impl V8Object {
+    /// Get the value of a property, or null if the object does not have the property.
+    ///
+    /// @return mixed
+    fn get(self_: &mut ext_php_rs::types::ZendClassObject<V8Object>, key: &str) -> PhpResult<Zval> {
+        match self_.std.get_properties()?.get(key) {
+            Some(value) => Ok(value.shallow_clone()),
+            None => {
+                let mut zval = Zval::new();
+                zval.set_null();
+                Ok(zval)
+            }
         }
     }
-}
 
-/// Parse a TypeScript (or similar) module. See ParseParams for options.
-#[php_function(ignore_module, name = "Deno\\AST\\parse_module")]
-fn parse_module(params: &ParseParams) -> PhpResult<ParsedSource> {
-    match deno_ast::parse_module(params.try_into()?) {
-        Ok(parsed_source) => Ok(ParsedSource {
-            deno_ast_parsed_source: parsed_source,
-        }),
-        Err(diagnostic) => Err(diagnostic.to_string().into()),
+    /// Set the value of a property, adding the property if the object does not have it.
+    ///
+    /// @param mixed $value
+    fn set(
+        self_: &mut ext_php_rs::types::ZendClassObject<V8Object>,
+        key: &str,
+        value: CloneableZval,
+    ) -> PhpResult<()> {
+        self_.std.set_property(key, value)?;
+        Ok(())
     }
-}
 
-// Zval doesn't implement Clone, which means that Zval's can not
-// be passed to `ZendCallable.try_call()`, so we have to wrap it
-// in a Cloneable wrapper.
-#[derive(Debug)]
-struct CloneableZval(Zval);
+    /// Get the names of the object's properties, in the order of the JavaScript object.
+    ///
+    /// @return string[]
+    fn keys(self_: &mut ext_php_rs::types::ZendClassObject<V8Object>) -> PhpResult<Vec<String>> {
+        let keys = self_
+            .std
+            .get_properties()?
+            .iter()
+            .map(|(key, _value)| match key {
+                ArrayKey::String(key) => key,
+                ArrayKey::Long(key) => key.to_string(),
+            })
+            .collect();
+        Ok(keys)
+    }
 
-impl FromZval<'_> for CloneableZval {
-    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
-    fn from_zval(zval: &'_ Zval) -> Option<Self> {
-        Some(Self(zval.shallow_clone()))
+    /// Get the object's properties as an array. Properties that are objects are kept as
+    /// `Deno\Core\V8Object`s, rather than being converted to arrays as well.
+    ///
+    /// @return array<string, mixed>
+    fn to_array(self_: &mut ext_php_rs::types::ZendClassObject<V8Object>) -> PhpResult<Zval> {
+        let mut zend_array = ext_php_rs::types::ZendHashTable::new();
+        for (key, value) in self_.std.get_properties()?.iter() {
+            let _result = match key {
+                ArrayKey::String(key) => zend_array.insert(key.as_str(), value.shallow_clone()),
+                ArrayKey::Long(key) => zend_array.insert_at_index(key, value.shallow_clone()),
+            };
+        }
+        let mut zval = Zval::new();
+        zval.set_hashtable(zend_array);
+        Ok(zval)
     }
 }
 
-impl IntoZval for CloneableZval {
-    const TYPE: ext_php_rs::flags::DataType = ext_php_rs::flags::DataType::Mixed;
-    fn set_zval(self, zv: &mut Zval, _: bool) -> ext_php_rs::error::Result<()> {
-        *zv = self.0;
-        Ok(())
+/// A JavaScript function, which is what functions are converted to in PHP. It can be called with
+/// `call_function()` of the runtime that returned it, and converts back to the same function when
+/// it is passed to JavaScript, such as in the arguments of an op or of another call.
+#[php_class(name = "Deno\\Core\\JsFunction")]
+struct JsFunction {
+    /// The name of the function, which is empty for anonymous functions.
+    #[prop(flags = ext_php_rs::flags::PropertyFlags::Public)]
+    name: String,
+    function: v8::Global<v8::Function>,
+    /// The runtime the function belongs to.
+    runtime_id: Option<RuntimeId>,
+}
+
+#[php_impl(rename_methods = "none")]
+impl JsFunction {}
+
+impl JsFunction {
+    fn new(function: v8::Local<v8::Function>, scope: &mut v8::HandleScope) -> Self {
+        let name = function.get_name(scope).to_rust_string_lossy(scope);
+        JsFunction {
+            name,
+            function: v8::Global::new(scope, function),
+            runtime_id: scope.get_slot::<RuntimeId>().copied(),
+        }
     }
-    fn into_zval(self, _persistent: bool) -> ext_php_rs::error::Result<Zval> {
-        Ok(self.0)
+
+    /// Get the function, if it belongs to the given isolate. Opening it in another isolate would
+    /// crash the process.
+    fn function_in(&self, isolate: &v8::Isolate) -> Result<&v8::Global<v8::Function>, String> {
+        match (self.runtime_id, isolate.get_slot::<RuntimeId>()) {
+            (Some(runtime_id), Some(isolate_runtime_id)) if runtime_id == *isolate_runtime_id => {
+                Ok(&self.function)
+            }
+            _ => Err("The function belongs to a different runtime.".into()),
+        }
     }
 }
 
-impl Clone for CloneableZval {
-    fn clone(&self) -> Self {
-        Self(self.0.shallow_clone())
+/// Identifies a runtime, to tell which runtime a `JsFunction` belongs to. The address of the
+/// isolate can't be used for this, as it is reused by isolates created after one is freed.
+#[derive(Clone, Copy, PartialEq)]
+struct RuntimeId(u64);
+
+static NEXT_RUNTIME_ID: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+impl RuntimeId {
+    fn next() -> Self {
+        Self(NEXT_RUNTIME_ID.fetch_add(1, std::sync::atomic::Ordering::SeqCst))
     }
 }
 
 pub fn zval_from_jsvalue(result: v8::Local<v8::Value>, scope: &mut v8::HandleScope) -> Zval {
+    zval_from_jsvalue_seen(result, scope, &mut vec![])
+}
+
+/// Convert a JavaScript value, where `seen` holds the arrays and objects that are currently being
+/// converted. A value that refers back to one of them (such as `globalThis.globalThis`) is converted
+/// to null, rather than recursing until the stack overflows.
+fn zval_from_jsvalue_seen(
+    result: v8::Local<v8::Value>,
+    scope: &mut v8::HandleScope,
+    seen: &mut Vec<v8::Global<v8::Object>>,
+) -> Zval {
+    if result.is_object() && !result.is_function() {
+        let is_cyclic = seen
+            .iter()
+            .any(|object| v8::Local::new(scope, object).strict_equals(result));
+        if is_cyclic {
+            let mut zval = Zval::new();
+            zval.set_null();
+            return zval;
+        }
+    }
     if result.is_string() {
-        return result.to_rust_string_lossy(scope).try_into().unwrap();
+        let string = v8::Local::<v8::String>::try_from(result).unwrap();
+        if scope.get_slot::<LosslessStrings>().is_some() {
+            if let Some(utf16_string) = Utf16String::from_ill_formed(scope, string) {
+                return utf16_string.into_zval(false).unwrap();
+            }
+        }
+        return string.to_rust_string_lossy(scope).try_into().unwrap();
     }
     if result.is_null_or_undefined() {
         let mut zval = Zval::new();
@@ -1162,51 +7103,221 @@ pub fn zval_from_jsvalue(result: v8::Local<v8::Value>, scope: &mut v8::HandleSco
     if result.is_number() {
         return result.number_value(scope).unwrap().into();
     }
+    if result.is_big_int() {
+        let big_int = v8::Local::<v8::BigInt>::try_from(result).unwrap();
+        return match big_int.i64_value() {
+            (long, true) => long.try_into().unwrap(),
+            // BigInts that don't fit in a PHP int are converted to their decimal string.
+            (_, false) => result.to_rust_string_lossy(scope).try_into().unwrap(),
+        };
+    }
+    // Typed arrays and ArrayBuffers are copied as bytes into a binary string, rather than
+    // being converted element-by-element.
+    if result.is_array_buffer_view() {
+        let view = v8::Local::<v8::ArrayBufferView>::try_from(result).unwrap();
+        let mut bytes = vec![0; view.byte_length()];
+        view.copy_contents(&mut bytes);
+        let mut zval = Zval::new();
+        zval.set_binary(bytes);
+        return zval;
+    }
+    if result.is_array_buffer() {
+        let buffer = v8::Local::<v8::ArrayBuffer>::try_from(result).unwrap();
+        let bytes: Vec<u8> = buffer
+            .get_backing_store()
+            .iter()
+            .take(buffer.byte_length())
+            .map(|byte| byte.get())
+            .collect();
+        let mut zval = Zval::new();
+        zval.set_binary(bytes);
+        return zval;
+    }
     if result.is_array() {
         let array = v8::Local::<v8::Array>::try_from(result).unwrap();
+        seen.push(v8::Global::new(scope, v8::Local::<v8::Object>::from(array)));
         let mut zend_array = ext_php_rs::types::ZendHashTable::new();
         for index in 0..array.length() {
-            let _result = zend_array.push(zval_from_jsvalue(
+            let _result = zend_array.push(zval_from_jsvalue_seen(
                 array.get_index(scope, index).unwrap(),
                 scope,
+                seen,
             ));
         }
+        seen.pop();
         let mut zval = Zval::new();
         zval.set_hashtable(zend_array);
         return zval;
     }
     if result.is_function() {
-        return "Function".try_into().unwrap();
+        let function = v8::Local::<v8::Function>::try_from(result).unwrap();
+        return JsFunction::new(function, scope).into_zval(false).unwrap();
     }
     if result.is_object() {
         let object = v8::Local::<v8::Object>::try_from(result).unwrap();
+        let transfer_description = v8::String::new(scope, "php.transfer").unwrap();
+        let transfer_key = v8::Symbol::for_global(scope, transfer_description);
+        if let Some(buffer) = object.get(scope, transfer_key.into()) {
+            if let Some(zval) = transferred_array_buffer(buffer, scope) {
+                return zval;
+            }
+        }
+        // Only own enumerable string keys are converted; V8 leaves symbol keys out of the names.
         let properties = object.get_own_property_names(scope).unwrap();
-        let class_entry = ext_php_rs::zend::ClassEntry::try_find("V8Object").unwrap();
-        let mut zend_object = ext_php_rs::types::ZendObject::new(class_entry);
+        // PHP arrays that are not lists are converted to objects with a null prototype, so objects
+        // with a null prototype are converted back to arrays, keeping the round trip symmetric.
+        // Integer keys are returned as numbers, and become integer keys again.
+        let has_null_prototype = object
+            .get_prototype(scope)
+            .map_or(false, |prototype| prototype.is_null());
+        if has_null_prototype {
+            let mut zend_array = ext_php_rs::types::ZendHashTable::new();
+            seen.push(v8::Global::new(scope, object));
+            for index in 0..properties.length() {
+                let key = properties.get_index(scope, index).unwrap();
+                let value = object.get(scope, key).unwrap();
+                let value = zval_from_jsvalue_seen(value, scope, seen);
+                let _result = if key.is_number() {
+                    zend_array.insert_at_index(key.uint32_value(scope).unwrap().into(), value)
+                } else {
+                    zend_array.insert(key.to_rust_string_lossy(scope).as_str(), value)
+                };
+            }
+            seen.pop();
+            let mut zval = Zval::new();
+            zval.set_hashtable(zend_array);
+            return zval;
+        }
+        let mut zval = V8Object::default().into_zval(false).unwrap();
+        let zend_object = zval.object_mut().unwrap();
+        seen.push(v8::Global::new(scope, object));
         for index in 0..properties.length() {
             let key = properties.get_index(scope, index).unwrap();
             let value = object.get(scope, key).unwrap();
-
-            zend_object
-                .set_property(
-                    key.to_rust_string_lossy(scope).as_str(),
-                    zval_from_jsvalue(value, scope),
-                )
-                .unwrap();
+            let value = zval_from_jsvalue_seen(value, scope, seen);
+            let _result = zend_object.set_property(key.to_rust_string_lossy(scope).as_str(), value);
         }
-        return zend_object.into_zval(false).unwrap();
+        seen.pop();
+        return zval;
     }
     result.to_rust_string_lossy(scope).try_into().unwrap()
 }
 
+/// Convert a JSON value, such as the result of a `JsThread`, in the same way as `zval_from_jsvalue()`
+/// converts the equivalent JavaScript value.
+fn zval_from_json(value: deno_core::serde_json::Value) -> Zval {
+    use deno_core::serde_json::Value;
+
+    match value {
+        Value::Null => {
+            let mut zval = Zval::new();
+            zval.set_null();
+            zval
+        }
+        Value::Bool(bool) => bool.into(),
+        Value::Number(number) => match number.as_i64() {
+            Some(long) => long.try_into().unwrap(),
+            None => number.as_f64().unwrap_or(f64::NAN).into(),
+        },
+        Value::String(string) => string.try_into().unwrap(),
+        Value::Array(values) => {
+            let mut zend_array = ext_php_rs::types::ZendHashTable::new();
+            for value in values {
+                let _result = zend_array.push(zval_from_json(value));
+            }
+            let mut zval = Zval::new();
+            zval.set_hashtable(zend_array);
+            zval
+        }
+        Value::Object(properties) => {
+            let mut zval = V8Object::default().into_zval(false).unwrap();
+            let zend_object = zval.object_mut().unwrap();
+            for (key, value) in properties {
+                let _result = zend_object.set_property(key.as_str(), zval_from_json(value));
+            }
+            zval
+        }
+    }
+}
+
 pub fn js_value_from_zval<'a>(
     scope: &mut v8::HandleScope<'a>,
     zval: &'_ Zval,
 ) -> v8::Local<'a, v8::Value> {
+    js_value_from_zval_seen(scope, zval, &mut vec![])
+}
+
+/// Convert a PHP value, where `seen` holds the arrays that are currently being converted. An array
+/// that refers back to one of them by reference (such as `$array['self'] = &$array`) is converted
+/// to null, rather than recursing until the stack overflows.
+fn js_value_from_zval_seen<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    zval: &'_ Zval,
+    seen: &mut Vec<*const ext_php_rs::types::ZendHashTable>,
+) -> v8::Local<'a, v8::Value> {
+    if let Some(zval) = zval.reference() {
+        return js_value_from_zval_seen(scope, zval, seen);
+    }
+    if let Some(uint8_array) = zval.extract::<&Uint8Array>() {
+        let length = uint8_array.bytes.len();
+        let backing_store = v8::ArrayBuffer::new_backing_store_from_boxed_slice(
+            uint8_array.bytes.clone().into_boxed_slice(),
+        )
+        .make_shared();
+        let buffer = v8::ArrayBuffer::with_backing_store(scope, &backing_store);
+        return v8::Uint8Array::new(scope, buffer, 0, length)
+            .unwrap()
+            .into();
+    }
+    if let Some(utf16_string) = zval.extract::<&Utf16String>() {
+        return v8::String::new_from_two_byte(
+            scope,
+            &utf16_string.code_units,
+            v8::NewStringType::Normal,
+        )
+        .unwrap()
+        .into();
+    }
+    if let Some(js_function) = zval.extract::<&JsFunction>() {
+        let isolate: &v8::Isolate = scope;
+        return match js_function.function_in(isolate) {
+            Ok(function) => v8::Local::new(scope, function).into(),
+            Err(_) => v8::null(scope).into(),
+        };
+    }
+    if let Some(array_buffer) = zval.extract::<&ArrayBuffer>() {
+        let buffer = v8::ArrayBuffer::with_backing_store(scope, &array_buffer.backing_store);
+        return v8::Uint8Array::new(scope, buffer, array_buffer.offset, array_buffer.length)
+            .unwrap()
+            .into();
+    }
     if zval.is_string() {
-        return v8::String::new(scope, zval.str().unwrap()).unwrap().into();
+        // Binary strings that are not valid UTF-8 have the invalid bytes replaced, use a
+        // `Deno\Core\Uint8Array` to pass binary data.
+        return match zval.str() {
+            Some(string) => v8::String::new(scope, string).unwrap().into(),
+            None => {
+                let bytes: Vec<u8> = zval.binary().unwrap_or_default();
+                v8::String::new(scope, &String::from_utf8_lossy(&bytes))
+                    .unwrap()
+                    .into()
+            }
+        };
     }
-    if zval.is_long() || zval.is_double() {
+    if zval.is_long() {
+        // Integers that are outside of the range a JavaScript number can represent exactly are
+        // converted to a BigInt, so they keep their precision.
+        const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+        let long = zval.long().unwrap();
+        if let Ok(int) = i32::try_from(long) {
+            return v8::Integer::new(scope, int).into();
+        }
+        if (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&long) {
+            return v8::Number::new(scope, long as f64).into();
+        }
+        return v8::BigInt::new_from_i64(scope, long).into();
+    }
+    if zval.is_double() {
         return v8::Number::new(scope, zval.double().unwrap()).into();
     }
     if zval.is_bool() {
@@ -1223,59 +7334,327 @@ pub fn js_value_from_zval<'a>(
     }
     if zval.is_array() {
         let zend_array = zval.array().unwrap();
+        let zend_array_ptr = zend_array as *const ext_php_rs::types::ZendHashTable;
+        if seen.contains(&zend_array_ptr) {
+            return v8::null(scope).into();
+        }
+        seen.push(zend_array_ptr);
         let mut values: Vec<v8::Local<'_, v8::Value>> = Vec::new();
         let mut keys: Vec<v8::Local<'_, v8::Name>> = Vec::new();
-        let mut has_string_keys = false;
+        // Only lists (arrays with the keys 0, 1, 2...) become JavaScript arrays. Any other array,
+        // including one with mixed string and integer keys, becomes an object with all of its
+        // keys. Note that JavaScript orders an object's integer keys (in ascending order) before
+        // its string keys (in insertion order), so `[0 => 'a', 'x' => 'b', 1 => 'c']` becomes
+        // `{ 0: 'a', 1: 'c', x: 'b' }`. The object has a null prototype, which `zval_from_jsvalue()`
+        // converts back to an array.
+        let mut is_list = true;
+        let mut next_index = 0;
         for (key, elem) in zend_array.iter() {
             let key = match key {
                 ArrayKey::String(key) => {
-                    has_string_keys = true;
+                    is_list = false;
                     key
-                },
+                }
                 ArrayKey::Long(key) => {
-                    key.to_string() 
+                    if key != next_index {
+                        is_list = false;
+                    }
+                    next_index += 1;
+                    key.to_string()
                 }
             };
             keys.push(v8::String::new(scope, key.as_str()).unwrap().into());
-            values.push(js_value_from_zval(scope, elem));
+            values.push(js_value_from_zval_seen(scope, elem, seen));
         }
+        seen.pop();
 
-        if has_string_keys {
+        if is_list {
+            return v8::Array::new_with_elements(scope, &values[..]).into();
+        } else {
             let null: v8::Local<v8::Value> = v8::null(scope).into();
             return v8::Object::with_prototype_and_properties(scope, null, &keys[..], &values[..])
                 .into();
-        } else {
-            return v8::Array::new_with_elements(scope, &values[..]).into();
         }
     }
+    if zval.extract::<&V8Object>().is_some() {
+        let properties = match zval
+            .object()
+            .and_then(|object| object.get_properties().ok())
+        {
+            Some(properties) => properties,
+            None => return v8::null(scope).into(),
+        };
+        // The properties are tracked in the same way as arrays, as objects can also refer to
+        // themselves (such as `$object->set( 'self', $object )`).
+        let properties_ptr = properties as *const ext_php_rs::types::ZendHashTable;
+        if seen.contains(&properties_ptr) {
+            return v8::null(scope).into();
+        }
+        seen.push(properties_ptr);
+        let object = v8::Object::new(scope);
+        for (key, value) in properties.iter() {
+            let key = match key {
+                ArrayKey::String(key) => key,
+                ArrayKey::Long(key) => key.to_string(),
+            };
+            let key = v8::String::new(scope, key.as_str()).unwrap();
+            let value = js_value_from_zval_seen(scope, value, seen);
+            object.set(scope, key.into(), value);
+        }
+        seen.pop();
+        return object.into();
+    }
     // Todo: is_object
     v8::null(scope).into()
 }
 
+/// The PHP callable for an op, stored in the isolate's slot so `op_callback` can find it.
+#[derive(Clone)]
+struct OpCallback {
+    callback: CloneableZval,
+    is_async: bool,
+    /// Whether the return value is discarded, rather than converted to a JavaScript value.
+    is_void: bool,
+    /// Whether the return value is an iterable that is streamed to JavaScript, see `PhpStream`.
+    is_stream: bool,
+}
+
+fn op_callbacks(extensions: &[Extension]) -> HashMap<String, OpCallback> {
+    let mut callbacks: HashMap<String, OpCallback> = HashMap::new();
+
+    for extension in extensions {
+        for (name, op) in &extension.ops {
+            callbacks.insert(
+                name.to_string(),
+                OpCallback {
+                    callback: op.clone(),
+                    is_async: false,
+                    is_void: false,
+                    is_stream: false,
+                },
+            );
+        }
+        for (name, op) in &extension.async_ops {
+            callbacks.insert(
+                name.to_string(),
+                OpCallback {
+                    callback: op.clone(),
+                    is_async: true,
+                    is_void: false,
+                    is_stream: false,
+                },
+            );
+        }
+        for (name, op) in &extension.void_ops {
+            callbacks.insert(
+                name.to_string(),
+                OpCallback {
+                    callback: op.clone(),
+                    is_async: false,
+                    is_void: true,
+                    is_stream: false,
+                },
+            );
+        }
+        for (name, op) in &extension.stream_ops {
+            callbacks.insert(
+                name.to_string(),
+                OpCallback {
+                    callback: op.clone(),
+                    is_async: false,
+                    is_void: false,
+                    is_stream: true,
+                },
+            );
+        }
+    }
+
+    callbacks
+}
+
+fn set_op_callbacks(isolate: &mut v8::Isolate, callbacks: HashMap<String, OpCallback>) {
+    isolate.set_slot(std::rc::Rc::new(std::cell::RefCell::new(callbacks)));
+    isolate.set_slot(PendingAsyncOps::default());
+}
+
+/// The number of calls to an op and the total time spent in its PHP function, recorded when
+/// `RuntimeOptions::op_metrics` is enabled.
+#[derive(Default)]
+struct OpMetric {
+    calls: u64,
+    total_time: std::time::Duration,
+}
+
+type OpMetrics = std::rc::Rc<std::cell::RefCell<HashMap<String, OpMetric>>>;
+
+/// Call `f`, recording its duration against the op `name` if op metrics are enabled for the isolate.
+fn with_op_metrics<T>(isolate: &v8::Isolate, name: &str, f: impl FnOnce() -> T) -> T {
+    let op_metrics = match isolate.get_slot::<OpMetrics>() {
+        Some(op_metrics) => op_metrics.clone(),
+        None => return f(),
+    };
+    let start = std::time::Instant::now();
+    let result = f();
+    let mut op_metrics = op_metrics.borrow_mut();
+    let op_metric = op_metrics.entry(name.to_string()).or_default();
+    op_metric.calls += 1;
+    op_metric.total_time += start.elapsed();
+    result
+}
+
+/// The depth of nested calls between PHP and JavaScript, stored in the isolate's slot when
+/// `RuntimeOptions::max_call_depth` is set.
+#[derive(Clone)]
+struct CallDepth {
+    depth: std::rc::Rc<std::cell::Cell<u32>>,
+    max: u32,
+}
+
+impl CallDepth {
+    fn new(max: u32) -> Self {
+        Self {
+            depth: Default::default(),
+            max,
+        }
+    }
+}
+
+/// Counts a call between PHP and JavaScript for as long as it is in scope.
+struct CallDepthGuard(Option<CallDepth>);
+
+impl CallDepthGuard {
+    /// Enter a call one level deeper, or return an error if that would exceed the isolate's
+    /// `max_call_depth`.
+    fn enter(isolate: &v8::Isolate) -> Result<Self, String> {
+        let call_depth = match isolate.get_slot::<CallDepth>() {
+            Some(call_depth) => call_depth.clone(),
+            None => return Ok(Self(None)),
+        };
+        let depth = call_depth.depth.get();
+        if depth >= call_depth.max {
+            return Err(format!(
+                "Maximum call depth of {} exceeded.",
+                call_depth.max
+            ));
+        }
+        call_depth.depth.set(depth + 1);
+        Ok(Self(Some(call_depth)))
+    }
+}
+
+impl Drop for CallDepthGuard {
+    fn drop(&mut self) {
+        if let Some(call_depth) = self.0.take() {
+            call_depth.depth.set(call_depth.depth.get() - 1);
+        }
+    }
+}
+
+/// An async op call from JavaScript that is waiting for its PHP function to be called.
+struct PendingAsyncOp {
+    op_name: String,
+    resolver: v8::Global<v8::PromiseResolver>,
+    op_state: std::rc::Rc<std::cell::RefCell<deno_core::OpState>>,
+    callback: CloneableZval,
+    args: Vec<CloneableZval>,
+}
+
+type PendingAsyncOps = std::rc::Rc<std::cell::RefCell<Vec<PendingAsyncOp>>>;
+
+/// Microtask that calls the PHP functions for all pending async ops and settles their Promises.
+fn run_pending_async_ops(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let pending_async_ops = scope.get_slot::<PendingAsyncOps>().unwrap().clone();
+    let pending_async_ops: Vec<PendingAsyncOp> = pending_async_ops.borrow_mut().drain(..).collect();
+
+    for pending_async_op in pending_async_ops {
+        let resolver = v8::Local::new(scope, &pending_async_op.resolver);
+        let callback: Zval = pending_async_op.callback.into_zval(false).unwrap();
+        let mut php_args_refs: Vec<&dyn ext_php_rs::convert::IntoZvalDyn> = Vec::new();
+        for arg in &pending_async_op.args {
+            php_args_refs.push(arg);
+        }
+
+        let _call_depth = match CallDepthGuard::enter(scope) {
+            Ok(call_depth) => call_depth,
+            Err(message) => {
+                let message = v8::String::new(scope, &message).unwrap();
+                let exception = v8::Exception::range_error(scope, message);
+                resolver.reject(scope, exception);
+                continue;
+            }
+        };
+        let result = with_op_metrics(scope, &pending_async_op.op_name, || {
+            with_current_op_state(&pending_async_op.op_name, pending_async_op.op_state, || {
+                callback.try_call(php_args_refs)
+            })
+        });
+        match result {
+            Ok(return_value) => {
+                let return_value_js = js_value_from_zval(scope, &return_value);
+                resolver.resolve(scope, return_value_js);
+            }
+            Err(error) => {
+                let exception = op_error(scope, error);
+                resolver.reject(scope, exception);
+            }
+        }
+    }
+}
+
 pub fn op_callback<'scope>(
     scope: &mut deno_core::v8::HandleScope<'scope>,
     args: deno_core::v8::FunctionCallbackArguments,
-    mut rv: deno_core::v8::ReturnValue,
+    rv: deno_core::v8::ReturnValue,
 ) {
     let ctx = unsafe {
         &*(deno_core::v8::Local::<deno_core::v8::External>::cast(args.data().unwrap_unchecked())
             .value() as *const deno_core::_ops::OpCtx)
     };
+    call_op(scope, args, rv, ctx.decl.name, ctx.state.clone())
+}
+
+/// The op state of the runtime, for ops registered with `JsRuntime::register_op()`, which have
+/// no `OpCtx`.
+struct RuntimeOpState(std::rc::Rc<std::cell::RefCell<deno_core::OpState>>);
+
+/// The function for ops registered with `JsRuntime::register_op()`, which has the op's name as
+/// its data.
+fn registered_op_callback<'scope>(
+    scope: &mut deno_core::v8::HandleScope<'scope>,
+    args: deno_core::v8::FunctionCallbackArguments,
+    rv: deno_core::v8::ReturnValue,
+) {
+    let callback_name = args.data().unwrap().to_rust_string_lossy(scope);
+    let op_state = scope.get_slot::<RuntimeOpState>().unwrap().0.clone();
+    call_op(scope, args, rv, &callback_name, op_state)
+}
+
+fn call_op<'scope>(
+    scope: &mut deno_core::v8::HandleScope<'scope>,
+    args: deno_core::v8::FunctionCallbackArguments,
+    mut rv: deno_core::v8::ReturnValue,
+    callback_name: &str,
+    op_state: std::rc::Rc<std::cell::RefCell<deno_core::OpState>>,
+) {
     let isolate: &mut v8::Isolate = scope.as_mut();
     let callbacks_slot = isolate
-        .get_slot::<std::rc::Rc<std::cell::RefCell<HashMap<String, CloneableZval>>>>()
+        .get_slot::<std::rc::Rc<std::cell::RefCell<HashMap<String, OpCallback>>>>()
         .unwrap()
         .clone();
-    let callbacks = callbacks_slot.borrow_mut();
-    let callback_name = ctx.decl.name.to_string();
-    let callback = callbacks.get(&callback_name);
-    if callback.is_none() {
-        // todo: error
-        println!("callback not found {:#?}", callback_name);
-        return;
-    }
-
-    let callback: Zval = callback.unwrap().clone().into_zval(false).unwrap();
+    let callback = match callbacks_slot.borrow().get(callback_name) {
+        Some(callback) => callback.clone(),
+        None => {
+            let message = format!("No PHP callable is registered for op {}.", callback_name);
+            let message = v8::String::new(scope, &message).unwrap();
+            let exception = v8::Exception::error(scope, message);
+            scope.throw_exception(exception);
+            return;
+        }
+    };
 
     let mut php_args: Vec<CloneableZval> = Vec::new();
     let mut php_args_refs: Vec<&dyn ext_php_rs::convert::IntoZvalDyn> = Vec::new();
@@ -1284,12 +7663,172 @@ pub fn op_callback<'scope>(
         let clonable_zval = CloneableZval::from_zval(&v).unwrap();
         php_args.push(clonable_zval);
     }
+
+    if callback.is_async {
+        let resolver = v8::PromiseResolver::new(scope).unwrap();
+        let promise = resolver.get_promise(scope);
+        let pending_async_ops = scope.get_slot::<PendingAsyncOps>().unwrap().clone();
+        if pending_async_ops.borrow().is_empty() {
+            let run_pending = v8::Function::new(scope, run_pending_async_ops).unwrap();
+            scope.enqueue_microtask(run_pending);
+        }
+        pending_async_ops.borrow_mut().push(PendingAsyncOp {
+            op_name: callback_name.to_string(),
+            resolver: v8::Global::new(scope, resolver),
+            op_state,
+            callback: callback.callback,
+            args: php_args,
+        });
+        rv.set(promise.into());
+        return;
+    }
+
+    let callback_is_void = callback.is_void;
+    let callback_is_stream = callback.is_stream;
+    let callback: Zval = callback.callback.into_zval(false).unwrap();
     for index in 0..php_args.len() {
         php_args_refs.push(php_args.get(index).unwrap());
     }
-    let return_value = callback.try_call(php_args_refs).unwrap();
-    let return_value_js = js_value_from_zval(scope, &return_value);
-    rv.set(return_value_js)
+    let _call_depth = match CallDepthGuard::enter(scope) {
+        Ok(call_depth) => call_depth,
+        Err(message) => {
+            let message = v8::String::new(scope, &message).unwrap();
+            let exception = v8::Exception::range_error(scope, message);
+            scope.throw_exception(exception);
+            return;
+        }
+    };
+    let result = with_op_metrics(scope, callback_name, || {
+        with_current_op_state(callback_name, op_state.clone(), || {
+            callback.try_call(php_args_refs)
+        })
+    });
+    match result {
+        // The return value of void ops is not converted, so JavaScript gets `undefined`.
+        Ok(_) if callback_is_void => (),
+        Ok(return_value) if callback_is_stream => {
+            match php_stream(scope, callback_name, op_state, return_value) {
+                Some(stream) => rv.set(stream),
+                None => {
+                    let message = v8::String::new(
+                        scope,
+                        "Stream ops must return an array or an Iterator, such as a Generator.",
+                    )
+                    .unwrap();
+                    let exception = v8::Exception::type_error(scope, message);
+                    scope.throw_exception(exception);
+                }
+            }
+        }
+        Ok(return_value) => {
+            let return_value_js = js_value_from_zval(scope, &return_value);
+            rv.set(return_value_js)
+        }
+        // Errors, such as an exception thrown by the PHP function, are thrown in JavaScript.
+        Err(error) => {
+            let exception = op_error(scope, error);
+            scope.throw_exception(exception);
+        }
+    }
+}
+
+/// The message of an error from calling a PHP function, which is the exception's message for
+/// exceptions thrown by the function.
+fn php_error_message(error: ext_php_rs::error::Error) -> String {
+    match error {
+        ext_php_rs::error::Error::Exception(exception) => {
+            exception_message(exception.into_zval(false).ok())
+        }
+        error => error.to_string(),
+    }
+}
+
+fn exception_message(exception: Option<Zval>) -> String {
+    exception
+        .and_then(|exception| call_user_method!(exception, "getMessage",))
+        .and_then(|message| message.string())
+        .unwrap_or_else(|| "The PHP function threw an exception.".to_string())
+}
+
+/// The callable of `RuntimeOptions::get_error_class`, stored in the isolate's slot.
+struct GetErrorClass(CloneableZval);
+
+/// Convert an error from calling an op's PHP function to the JavaScript error that is thrown for it.
+fn op_error<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    error: ext_php_rs::error::Error,
+) -> v8::Local<'s, v8::Value> {
+    let exception = match error {
+        ext_php_rs::error::Error::Exception(exception) => exception.into_zval(false).ok(),
+        error => {
+            let message = v8::String::new(scope, &error.to_string()).unwrap();
+            return v8::Exception::error(scope, message);
+        }
+    };
+    let class_name = exception
+        .as_ref()
+        .and_then(|exception| op_error_class(scope, exception));
+    let message = v8::String::new(scope, &exception_message(exception)).unwrap();
+    js_error(scope, class_name.as_deref().unwrap_or("Error"), message)
+}
+
+/// The JavaScript error class for an exception thrown by an op, which is the class returned by the
+/// runtime's `get_error_class` callable, or the original class of a `Deno\Core\JsException`.
+fn op_error_class(isolate: &v8::Isolate, exception: &Zval) -> Option<String> {
+    if let Some(get_error_class) = isolate.get_slot::<GetErrorClass>() {
+        let get_error_class = get_error_class.0.clone();
+        let class_name = get_error_class
+            .0
+            .try_call(vec![&CloneableZval(exception.shallow_clone())])
+            .ok()
+            .and_then(|class_name| class_name.string());
+        if class_name.is_some() {
+            return class_name;
+        }
+    }
+    exception
+        .extract::<&JsException>()
+        .map(|js_exception| js_exception.class_name.clone())
+        .filter(|class_name| !class_name.is_empty())
+}
+
+/// Create a JavaScript error of the given class, which is one of JavaScript's own error classes or
+/// one of the `Deno.errors` classes of a MainWorker. Other class names create an `Error` with the
+/// class name as its `name`.
+fn js_error<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    class_name: &str,
+    message: v8::Local<v8::String>,
+) -> v8::Local<'s, v8::Value> {
+    match class_name {
+        "Error" => return v8::Exception::error(scope, message),
+        "TypeError" => return v8::Exception::type_error(scope, message),
+        "RangeError" => return v8::Exception::range_error(scope, message),
+        "ReferenceError" => return v8::Exception::reference_error(scope, message),
+        "SyntaxError" => return v8::Exception::syntax_error(scope, message),
+        _ => (),
+    }
+    let global = scope.get_current_context().global(scope);
+    let mut value: v8::Local<v8::Value> = global.into();
+    for key in ["Deno", "errors", class_name] {
+        let key = v8::String::new(scope, key).unwrap();
+        value = match v8::Local::<v8::Object>::try_from(value) {
+            Ok(object) => object.get(scope, key.into()).unwrap_or(value),
+            Err(_) => break,
+        };
+    }
+    let deno_error = v8::Local::<v8::Function>::try_from(value)
+        .ok()
+        .and_then(|class| class.new_instance(scope, &[message.into()]));
+    if let Some(deno_error) = deno_error {
+        return deno_error.into();
+    }
+    let error = v8::Exception::error(scope, message);
+    let object = v8::Local::<v8::Object>::try_from(error).unwrap();
+    let key = v8::String::new(scope, "name").unwrap();
+    let name = v8::String::new(scope, class_name).unwrap();
+    object.set(scope, key.into(), name.into());
+    error
 }
 
 #[php_module]